@@ -0,0 +1,49 @@
+use aqa_asm::{Machine, MachineOptions};
+use criterion::{criterion_group, criterion_main, Criterion};
+use std::{fs, io};
+
+/// Loop with a branch taken on almost every iteration
+const BRANCH_HEAVY: &str = "examples/for_loop.aqasm";
+
+/// Tight loop doing several arithmetic instructions for every branch
+const ARITHMETIC_HEAVY: &str = "
+MOV R0, #200
+loop:
+ADD R1, R1, R0
+EOR R2, R2, R1
+LSL R3, R1, #1
+SUB R4, R3, R2
+AND R5, R4, #15
+ORR R6, R6, R5
+SUB R0, R0, #1
+CMP R0, #0
+BNE loop
+HALT
+";
+
+fn bench_program(c: &mut Criterion, name: &str, source: &str) {
+    c.bench_function(&format!("{name} end to end"), |b| {
+        b.iter(|| {
+            let mut machine = Machine::from_source(source, MachineOptions::default()).unwrap();
+            machine.run(io::empty(), io::sink()).unwrap()
+        })
+    });
+
+    let mut machine = Machine::from_source(source, MachineOptions::default()).unwrap();
+    c.bench_function(&format!("{name} interpret only"), |b| {
+        b.iter(|| {
+            // Copying 256 bytes back is negligible next to running the program
+            machine.reset();
+            machine.run(io::empty(), io::sink()).unwrap()
+        })
+    });
+}
+
+fn bench_examples(c: &mut Criterion) {
+    let branch_heavy = fs::read_to_string(BRANCH_HEAVY).unwrap();
+    bench_program(c, "branch heavy", &branch_heavy);
+    bench_program(c, "arithmetic heavy", ARITHMETIC_HEAVY);
+}
+
+criterion_group!(benches, bench_examples);
+criterion_main!(benches);
@@ -0,0 +1,661 @@
+use crate::{
+    include::{LineOrigin, LineOrigins},
+    interpreter::{
+        instruction::{
+            runtime_opcode::RuntimeOpcode,
+            signature::{SignatureArgument, SIGNATURE_TREE},
+        },
+        REGISTER_COUNT,
+    },
+    parser::{Instruction, LineTable, Parser, SymbolTable},
+    tokenizer::{Token, Tokenizer},
+    Error, MachineOptions, MemoryLayout,
+};
+use inline_colorization::{color_reset, color_yellow, style_bold, style_reset};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fmt,
+};
+
+/// An instruction decoded back out of an assembled program
+#[derive(Debug, Clone, PartialEq)]
+pub struct DecodedInstruction {
+    /// Byte offset of the opcode
+    pub offset: u8,
+    pub opcode: RuntimeOpcode,
+    pub arguments: Vec<SignatureArgument>,
+    /// Operand bytes, one for each argument
+    pub operands: Vec<u8>,
+}
+
+impl DecodedInstruction {
+    /// Amount of bytes the instruction occupies
+    pub fn byte_count(&self) -> usize {
+        1 + self.operands.len()
+    }
+
+    /// Register operands the instruction reads from and the register it writes to, if any
+    pub fn register_effects(&self) -> (Vec<u8>, Option<u8>) {
+        let mut registers = self
+            .arguments
+            .iter()
+            .zip(&self.operands)
+            .filter(|(argument, _)| **argument == SignatureArgument::Register)
+            .map(|(_, &register)| register);
+        let written = if writes_first_register(self.opcode) {
+            registers.next()
+        } else {
+            None
+        };
+        (registers.collect(), written)
+    }
+
+    /// Offsets execution may continue at after this instruction. RET has none, as where
+    /// it returns to depends on which CALL was executed.
+    pub fn successors(&self) -> Vec<usize> {
+        let next = self.offset as usize + self.byte_count();
+        match self.opcode {
+            RuntimeOpcode::HALT | RuntimeOpcode::RET => vec![],
+            RuntimeOpcode::B | RuntimeOpcode::CALL => vec![self.operands[0] as usize],
+            RuntimeOpcode::BEQ
+            | RuntimeOpcode::BNE
+            | RuntimeOpcode::BGT
+            | RuntimeOpcode::BLT
+            | RuntimeOpcode::BCS
+            | RuntimeOpcode::BCC => vec![self.operands[0] as usize, next],
+            _ => vec![next],
+        }
+    }
+
+    /// Whether the instruction leaves every register unchanged, such as `MOV R0, R0` or
+    /// `ADD R0, R0, #0`. CMP is never redundant, as it sets the comparison flags.
+    pub fn is_redundant(&self) -> bool {
+        match (self.opcode, self.operands.as_slice()) {
+            (RuntimeOpcode::MOV_REGISTER, &[store, operand]) => store == operand,
+            (
+                RuntimeOpcode::ADD_LITERAL
+                | RuntimeOpcode::SUB_LITERAL
+                | RuntimeOpcode::ORR_LITERAL
+                | RuntimeOpcode::EOR_LITERAL
+                | RuntimeOpcode::LSL_LITERAL
+                | RuntimeOpcode::LSR_LITERAL
+                | RuntimeOpcode::ROL_LITERAL
+                | RuntimeOpcode::ROR_LITERAL,
+                &[store, operand, literal],
+            ) => store == operand && literal == 0,
+            (RuntimeOpcode::AND_LITERAL, &[store, operand, literal]) => {
+                store == operand && literal == u8::MAX
+            }
+            (RuntimeOpcode::DIV_LITERAL, &[store, operand, literal]) => {
+                store == operand && literal == 1
+            }
+            (
+                RuntimeOpcode::AND_REGISTER | RuntimeOpcode::ORR_REGISTER,
+                &[store, operand_1, operand_2],
+            ) => store == operand_1 && store == operand_2,
+            _ => false,
+        }
+    }
+}
+
+/// Whether the first register operand of an instruction is its destination
+fn writes_first_register(opcode: RuntimeOpcode) -> bool {
+    !matches!(
+        opcode,
+        RuntimeOpcode::STR
+            | RuntimeOpcode::CMP_REGISTER
+            | RuntimeOpcode::CMP_LITERAL
+            | RuntimeOpcode::PRINT_REGISTER
+            | RuntimeOpcode::PUSH
+    )
+}
+
+/// Decode the instruction starting at `offset`, returning `None` if the bytes there are
+/// not a complete instruction
+pub fn decode_instruction(memory: &[u8], offset: u8) -> Option<DecodedInstruction> {
+    let opcode = RuntimeOpcode::try_from(*memory.get(offset as usize)?).ok()?;
+    let (_, arguments) = SIGNATURE_TREE.get_signature_for_runtime_opcode(opcode)?;
+    let start = offset as usize + 1;
+    let operands = memory.get(start..start + arguments.len())?.to_vec();
+    Some(DecodedInstruction {
+        offset,
+        opcode,
+        arguments,
+        operands,
+    })
+}
+
+/// Something suspicious about a program that doesn't stop it from running
+#[derive(Debug, Clone, PartialEq)]
+pub enum Warning {
+    /// Register read when no instruction before it could have written to it
+    UninitializedRegister(Box<UninitializedRegister>),
+    /// Memory reference past the end of the data region, so it will always fail
+    MemoryReferenceLikelyOutOfBounds(Box<MemoryReferenceLikelyOutOfBounds>),
+    /// Instruction that can carry on past the end of the program without a HALT
+    MayRunPastEnd(Box<MayRunPastEnd>),
+    /// Instruction that has no effect, which is usually a typo or left over from debugging
+    RedundantInstruction(Box<RedundantInstruction>),
+    /// Warning about a line of source that was expanded from includes, along with the
+    /// file and line it was read from
+    InFile(Box<InFile>),
+}
+
+impl Warning {
+    /// Source line the warning is about
+    pub fn line(&self) -> usize {
+        match self {
+            Warning::UninitializedRegister(warning) => warning.line,
+            Warning::MemoryReferenceLikelyOutOfBounds(warning) => warning.line,
+            Warning::MayRunPastEnd(warning) => warning.line,
+            Warning::RedundantInstruction(warning) => warning.line,
+            Warning::InFile(warning) => warning.origin.line,
+        }
+    }
+
+    /// Point the warning at the file and line its line was read from, if the source was
+    /// expanded from includes
+    pub fn in_file(self, origins: &LineOrigins) -> Warning {
+        match origins.get(self.line()) {
+            Some(origin) => Warning::InFile(Box::new(InFile {
+                origin: origin.clone(),
+                warning: self,
+            })),
+            None => self,
+        }
+    }
+
+    fn message(&self) -> String {
+        match self {
+            Warning::UninitializedRegister(warning) => format!(
+                "Register 'R{}' is read before it is ever written to, so it will always be 0 here",
+                warning.register
+            ),
+            Warning::MemoryReferenceLikelyOutOfBounds(warning) => format!(
+                "Memory reference {} is out of bounds, the program only leaves {} bytes of data memory",
+                warning.offset, warning.data_bytes
+            ),
+            Warning::MayRunPastEnd(_) => String::from(
+                "The program can run past this instruction without reaching a HALT",
+            ),
+            Warning::RedundantInstruction(_) => String::from(
+                "This instruction has no effect, as it leaves the register it writes to unchanged",
+            ),
+            Warning::InFile(warning) => warning.warning.message(),
+        }
+    }
+}
+
+impl fmt::Display for Warning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{color_yellow}{style_bold}")?;
+        match self {
+            Warning::InFile(warning) => write!(f, "{}", warning.origin)?,
+            warning => write!(f, "Line {}", warning.line())?,
+        }
+        write!(
+            f,
+            " :: Warning: {}{color_reset}{style_reset}",
+            self.message()
+        )
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct InFile {
+    pub warning: Warning,
+    pub origin: LineOrigin,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct UninitializedRegister {
+    pub line: usize,
+    pub register: u8,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct MemoryReferenceLikelyOutOfBounds {
+    pub line: usize,
+    /// The memory reference, relative to the start of the data region
+    pub offset: u8,
+    /// Size of the data region
+    pub data_bytes: u16,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct MayRunPastEnd {
+    pub line: usize,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct RedundantInstruction {
+    pub line: usize,
+}
+
+/// Statically check an assembled program for likely mistakes. The line table gives the
+/// offset of every instruction, so data after the program is never decoded.
+pub fn analyse(memory: &[u8; 256], program_bytes: u8, line_table: &LineTable) -> Vec<Warning> {
+    let instructions: BTreeMap<usize, DecodedInstruction> = line_table
+        .keys()
+        .filter_map(|&offset| decode_instruction(memory, offset))
+        .map(|instruction| (instruction.offset as usize, instruction))
+        .collect();
+
+    // Registers that may have been written on some path to each instruction. Instructions
+    // that can never be reached are left out.
+    let mut maybe_written: BTreeMap<usize, u16> = BTreeMap::new();
+    // Reachable instructions that can fall through into the data region
+    let mut runs_past_end = BTreeSet::new();
+    // RET may return to just after any CALL
+    let return_sites: Vec<usize> = instructions
+        .values()
+        .filter(|instruction| instruction.opcode == RuntimeOpcode::CALL)
+        .map(|instruction| instruction.offset as usize + instruction.byte_count())
+        .collect();
+    let mut worklist = vec![0];
+    if instructions.contains_key(&0) {
+        maybe_written.insert(0, 0);
+    }
+    while let Some(offset) = worklist.pop() {
+        let Some(instruction) = instructions.get(&offset) else {
+            continue;
+        };
+        let mut written = maybe_written[&offset];
+        if let (_, Some(register)) = instruction.register_effects() {
+            written |= register_bit(register);
+        }
+        let successors = if instruction.opcode == RuntimeOpcode::RET {
+            return_sites.clone()
+        } else {
+            instruction.successors()
+        };
+        for successor in successors {
+            if successor >= program_bytes as usize {
+                runs_past_end.insert(instruction.offset);
+            }
+            if !instructions.contains_key(&successor) {
+                continue;
+            }
+            let previous = maybe_written.get(&successor).copied();
+            let merged = previous.map_or(written, |previous| previous | written);
+            if previous != Some(merged) {
+                maybe_written.insert(successor, merged);
+                worklist.push(successor);
+            }
+        }
+    }
+
+    let mut warned = 0u16;
+    let mut warnings = Vec::new();
+    let data_bytes = MemoryLayout::new(program_bytes).data_capacity;
+    for instruction in instructions.values() {
+        if instruction.is_redundant() {
+            warnings.push(Warning::RedundantInstruction(Box::new(
+                RedundantInstruction {
+                    line: line_table[&instruction.offset],
+                },
+            )));
+        }
+        for (argument, &operand) in instruction.arguments.iter().zip(&instruction.operands) {
+            if *argument == SignatureArgument::MemoryRef && operand as u16 >= data_bytes {
+                warnings.push(Warning::MemoryReferenceLikelyOutOfBounds(Box::new(
+                    MemoryReferenceLikelyOutOfBounds {
+                        line: line_table[&instruction.offset],
+                        offset: operand,
+                        data_bytes,
+                    },
+                )));
+            }
+        }
+    }
+    for (offset, written) in maybe_written {
+        let (read, _) = instructions[&offset].register_effects();
+        for register in read {
+            let bit = register_bit(register);
+            if written & bit == 0 && warned & bit == 0 {
+                warned |= bit;
+                warnings.push(Warning::UninitializedRegister(Box::new(
+                    UninitializedRegister {
+                        line: line_table[&(offset as u8)],
+                        register,
+                    },
+                )));
+            }
+        }
+    }
+    for offset in runs_past_end {
+        warnings.push(Warning::MayRunPastEnd(Box::new(MayRunPastEnd {
+            line: line_table[&offset],
+        })));
+    }
+    warnings.sort_by_key(Warning::line);
+    warnings
+}
+
+fn register_bit(register: u8) -> u16 {
+    if register < REGISTER_COUNT {
+        1 << register
+    } else {
+        0
+    }
+}
+
+/// A program that assembled without errors
+#[derive(Debug, Clone, PartialEq)]
+pub struct AssembledProgram {
+    pub memory: [u8; 256],
+    pub program_bytes: u8,
+    pub line_table: LineTable,
+}
+
+/// A problem found in source code, either fatal or a warning
+#[derive(Debug, PartialEq)]
+pub enum Diagnostic {
+    Error(Error),
+    Warning(Warning),
+}
+
+/// Everything known about a piece of source code after a single pass, as needed by an
+/// editor after each change
+#[derive(Debug, PartialEq)]
+pub struct Analysis {
+    /// Tokens of the source, empty if it failed to tokenize
+    pub tokens: Vec<Token>,
+    /// The assembled program, if there were no errors
+    pub program: Option<AssembledProgram>,
+    /// Instructions as they were written, if there were no errors
+    pub ast: Option<Vec<Instruction>>,
+    /// Labels and the byte offsets they refer to
+    pub symbols: SymbolTable,
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+/// Tokenize, parse and check source code, collecting every result instead of stopping
+/// at the first error
+pub fn analyze_source(source: &str, options: MachineOptions) -> Analysis {
+    let mut analysis = Analysis {
+        tokens: Vec::new(),
+        program: None,
+        ast: None,
+        symbols: SymbolTable::new(),
+        diagnostics: Vec::new(),
+    };
+    let comments = match Tokenizer::tokenize_with_options(source, options.tokenizer_options()) {
+        Ok(tokenizer) => {
+            analysis.tokens = tokenizer.tokens;
+            tokenizer.comments
+        }
+        Err(err) => {
+            analysis.diagnostics.push(Diagnostic::Error(err.into()));
+            return analysis;
+        }
+    };
+    // Constants and labels are resolved once, and shared by assembling and the AST
+    let tokens = match Parser::expand_constants(&analysis.tokens) {
+        Ok(tokens) => tokens,
+        Err(err) => {
+            analysis.diagnostics.push(Diagnostic::Error(err.into()));
+            return analysis;
+        }
+    };
+    let program_bytes = match Parser::label_offsets(&tokens) {
+        Ok((symbols, program_bytes)) => {
+            analysis.symbols = symbols;
+            program_bytes
+        }
+        Err(err) => {
+            analysis.diagnostics.push(Diagnostic::Error(err.into()));
+            return analysis;
+        }
+    };
+    match Parser::parse_resolved(tokens.clone(), analysis.symbols.clone(), program_bytes) {
+        Ok((memory, line_table)) => {
+            analysis.diagnostics.extend(
+                analyse(&memory, program_bytes, &line_table)
+                    .into_iter()
+                    .map(Diagnostic::Warning),
+            );
+            analysis.program = Some(AssembledProgram {
+                memory,
+                program_bytes,
+                line_table,
+            });
+            analysis.ast = Some(Parser::build_ast(&tokens, &comments));
+        }
+        Err(err) => analysis.diagnostics.extend(
+            err.into_errors()
+                .into_iter()
+                .map(|err| Diagnostic::Error(err.into())),
+        ),
+    }
+    analysis
+}
+
+/// Check that a single line is a valid instruction, without the rest of a program.
+/// Label operands are not checked as there are no labels for them to refer to.
+pub fn check_instruction(line: &str, tabsize: u8) -> Result<(), Vec<Diagnostic>> {
+    let tokenizer =
+        Tokenizer::tokenize(line, tabsize).map_err(|err| vec![Diagnostic::Error(err.into())])?;
+    Parser::check_statement(tokenizer.tokens).map_err(|err| vec![Diagnostic::Error(err.into())])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::ParserError;
+
+    fn source_warnings(source: &str) -> Vec<Warning> {
+        let tokens = Tokenizer::tokenize(source, 4).unwrap().tokens;
+        let (memory, program_bytes, line_table) = Parser::parse_with_line_table(tokens).unwrap();
+        analyse(&memory, program_bytes, &line_table)
+    }
+
+    #[test]
+    fn test_decode_instruction() {
+        let mut memory = [0; 256];
+        memory[..4].copy_from_slice(&[RuntimeOpcode::ADD_LITERAL as u8, 1, 2, 3]);
+        assert_eq!(
+            decode_instruction(&memory, 0),
+            Some(DecodedInstruction {
+                offset: 0,
+                opcode: RuntimeOpcode::ADD_LITERAL,
+                arguments: vec![
+                    SignatureArgument::Register,
+                    SignatureArgument::Register,
+                    SignatureArgument::Literal,
+                ],
+                operands: vec![1, 2, 3],
+            })
+        );
+        memory[255] = RuntimeOpcode::LDR as u8;
+        assert_eq!(decode_instruction(&memory, 255), None);
+    }
+
+    #[test]
+    fn test_uninitialized_register() {
+        assert_eq!(
+            source_warnings("MOV R0, #1\nADD R2, R0, R1\nPRINT R1\nHALT\n"),
+            [Warning::UninitializedRegister(Box::new(UninitializedRegister {
+                line: 2,
+                register: 1,
+            }))]
+        );
+    }
+
+    #[test]
+    fn test_initialized_register() {
+        assert_eq!(source_warnings("MOV R1, #5\nPRINT R1\nHALT\n"), []);
+        assert_eq!(source_warnings("INPUT R1\nPRINT R1\nHALT\n"), []);
+    }
+
+    #[test]
+    fn test_register_written_on_some_path() {
+        // R1 is only written on one path, so there may be a reaching definition
+        let source = "INPUT R0\nCMP R0, #0\nBEQ skip\nMOV R1, #1\nskip: PRINT R1\nHALT\n";
+        assert_eq!(source_warnings(source), []);
+    }
+
+    #[test]
+    fn test_register_written_in_loop() {
+        // The write to R1 in the loop body reaches the read on the second iteration
+        let source = "loop: PRINT R1\nADD R1, R1, #1\nCMP R1, #5\nBNE loop\nHALT\n";
+        assert_eq!(source_warnings(source), []);
+    }
+
+    #[test]
+    fn test_register_written_in_subroutine() {
+        // R1 is written by the subroutine, and read after it returns
+        let source = "CALL set\nPRINT R1\nHALT\nset: MOV R1, #1\nRET\n";
+        assert_eq!(source_warnings(source), []);
+    }
+
+    #[test]
+    fn test_may_run_past_end() {
+        assert_eq!(
+            source_warnings("MOV R0, #1\nPRINT R0\n"),
+            [Warning::MayRunPastEnd(Box::new(MayRunPastEnd { line: 2 }))]
+        );
+        // Only halts because R0 is always 1 by the time it gets to the BEQ
+        assert_eq!(
+            source_warnings("MOV R0, #1\nB check\ndone: HALT\ncheck: CMP R0, #1\nBEQ done\n"),
+            [Warning::MayRunPastEnd(Box::new(MayRunPastEnd { line: 5 }))]
+        );
+        assert_eq!(source_warnings("loop: B loop\n"), []);
+        assert_eq!(source_warnings(""), []);
+    }
+
+    #[test]
+    fn test_warning_in_file() {
+        let warning = Warning::MayRunPastEnd(Box::new(MayRunPastEnd { line: 2 }));
+        assert_eq!(warning.clone().in_file(&LineOrigins::default()), warning);
+
+        let origins = (1..=2)
+            .map(|line| LineOrigin {
+                filepath: String::from("lib.aqasm"),
+                line: line + 4,
+            })
+            .collect();
+        let warning = warning.in_file(&origins);
+        assert_eq!(warning.line(), 6);
+        assert_eq!(
+            warning.to_string(),
+            format!("{color_yellow}{style_bold}lib.aqasm, Line 6 :: Warning: The program can run past this instruction without reaching a HALT{color_reset}{style_reset}")
+        );
+    }
+
+    #[test]
+    fn test_redundant_instruction() {
+        let source = "MOV R0, #1\nMOV R0, R0\nADD R0, R0, #0\nORR R0, R0, R0\nAND R0, R0, #255\nLSL R0, R0, #0\nSUB R0, R0, #0\nDIV R0, R0, #1\nPRINT R0\nHALT\n";
+        assert_eq!(
+            source_warnings(source),
+            (2..=8)
+                .map(|line| Warning::RedundantInstruction(Box::new(RedundantInstruction { line })))
+                .collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn test_not_redundant_instruction() {
+        // CMP sets the comparison flags even when comparing a register with itself
+        let source = "MOV R0, #1\nCMP R0, R0\nMOV R1, R0\nADD R0, R0, R0\nADD R1, R0, #0\nAND R0, R0, #254\nORR R0, R0, R1\nPRINT R0\nHALT\n";
+        assert_eq!(source_warnings(source), []);
+    }
+
+    #[test]
+    fn test_unreachable_code_is_ignored() {
+        assert_eq!(source_warnings("HALT\nPRINT R3\n"), []);
+    }
+
+    #[test]
+    fn test_analyze_source_valid_program() {
+        let analysis = analyze_source(
+            "start: INPUT R0\nCMP R0, #0\nBNE start\nend: HALT\n",
+            MachineOptions::default(),
+        );
+        assert_eq!(analysis.diagnostics, []);
+        assert_eq!(
+            analysis.symbols,
+            SymbolTable::from([(String::from("start"), 0), (String::from("end"), 7)])
+        );
+        assert!(!analysis.tokens.is_empty());
+        assert_eq!(analysis.program.unwrap().program_bytes, 8);
+        let ast = analysis.ast.unwrap();
+        let offsets: Vec<u8> = ast.iter().map(|instruction| instruction.offset).collect();
+        assert_eq!(offsets, [0, 2, 5, 7]);
+    }
+
+    #[test]
+    fn test_analyze_source_invalid_program() {
+        let analysis = analyze_source(
+            "loop: PRINT R1\nMOV R0\nB loop\n",
+            MachineOptions::default(),
+        );
+        assert_eq!(analysis.program, None);
+        assert_eq!(analysis.ast, None);
+        assert_eq!(analysis.symbols["loop"], 0);
+        assert!(matches!(
+            analysis.diagnostics[..],
+            [Diagnostic::Error(Error::ParserError(_))]
+        ));
+
+        let analysis = analyze_source("MOV R0, #999\n", MachineOptions::default());
+        assert_eq!(analysis.tokens, []);
+        assert!(matches!(
+            analysis.diagnostics[..],
+            [Diagnostic::Error(Error::TokenizerError(_))]
+        ));
+    }
+
+    #[test]
+    fn test_check_instruction() {
+        assert_eq!(check_instruction("ADD R0, R1, #2", 4), Ok(()));
+        assert_eq!(check_instruction("BNE somewhere_else;", 4), Ok(()));
+        assert_eq!(check_instruction("LDR R0, table", 4), Ok(()));
+        assert_eq!(check_instruction("STR R1, buf", 4), Ok(()));
+
+        let Err(diagnostics) = check_instruction("MOV R0", 4) else {
+            panic!("MOV with one operand should be rejected");
+        };
+        assert!(matches!(
+            &diagnostics[..],
+            [Diagnostic::Error(Error::ParserError(
+                ParserError::InvalidInstructionSignature(_)
+            ))]
+        ));
+
+        let Err(diagnostics) = check_instruction("JUMP label", 4) else {
+            panic!("unknown opcode should be rejected");
+        };
+        assert!(matches!(
+            &diagnostics[..],
+            [Diagnostic::Error(Error::ParserError(
+                ParserError::ExpectedOpcode(_)
+            ))]
+        ));
+    }
+
+    #[test]
+    fn test_memory_reference_out_of_bounds() {
+        // 13 bytes of program leaves 243 bytes of data, so only references 0-242 are valid
+        let source = "MOV R0, #1\nSTR R0, 242\nSTR R0, 243\nLDR R1, 250\nHALT\n";
+        assert_eq!(
+            source_warnings(source),
+            [
+                Warning::MemoryReferenceLikelyOutOfBounds(Box::new(
+                    MemoryReferenceLikelyOutOfBounds {
+                        line: 3,
+                        offset: 243,
+                        data_bytes: 243,
+                    }
+                )),
+                Warning::MemoryReferenceLikelyOutOfBounds(Box::new(
+                    MemoryReferenceLikelyOutOfBounds {
+                        line: 4,
+                        offset: 250,
+                        data_bytes: 243,
+                    }
+                )),
+            ]
+        );
+    }
+}
@@ -0,0 +1,347 @@
+use crate::{
+    analysis::{decode_instruction, DecodedInstruction},
+    formats::initial_data,
+    interpreter::instruction::{
+        runtime_opcode::RuntimeOpcode,
+        signature::{SignatureArgument, SIGNATURE_TREE},
+    },
+};
+use inline_colorization::{color_red, color_reset, style_bold, style_reset};
+use std::{collections::HashMap, fmt};
+
+/// Turn assembled program bytes back into source code, one instruction per line.
+/// Branch targets are shown as raw byte offsets. If the data region after the program
+/// starts with values other than 0, they are written after '.data' with DATA.
+pub fn disassemble(memory: &[u8], program_bytes: u8) -> Result<String, DisassembleError> {
+    disassemble_with_labels(memory, program_bytes, None)
+}
+
+/// Same as [`disassemble`], but when `labels` is given branch targets are shown as label
+/// names. Targets without a name are given one based on their offset, such as `L_0A`, and
+/// a label line is inserted before each labelled instruction.
+pub fn disassemble_with_labels(
+    memory: &[u8],
+    program_bytes: u8,
+    labels: Option<&HashMap<u8, String>>,
+) -> Result<String, DisassembleError> {
+    let instructions = decode_program(memory, program_bytes)?;
+
+    let mut names = labels.cloned();
+    if let Some(names) = &mut names {
+        for instruction in &instructions {
+            for (argument, &operand) in instruction.arguments.iter().zip(&instruction.operands) {
+                if *argument == SignatureArgument::Label {
+                    names
+                        .entry(operand)
+                        .or_insert_with(|| format!("L_{operand:02X}"));
+                }
+            }
+        }
+    }
+
+    let mut output = String::new();
+    let write_label = |offset: u8, output: &mut String| {
+        if let Some(name) = names.as_ref().and_then(|names| names.get(&offset)) {
+            output.push_str(&format!("{name}:\n"));
+        }
+    };
+    for instruction in &instructions {
+        write_label(instruction.offset, &mut output);
+        output.push_str(&format_instruction(instruction, names.as_ref()));
+        output.push('\n');
+    }
+    // A label can point just past the last instruction
+    write_label(program_bytes, &mut output);
+    let data = initial_data(memory, program_bytes);
+    if !data.is_empty() {
+        output.push_str(".data\n");
+        for values in data.chunks(16) {
+            let values: Vec<String> = values.iter().map(|value| format!("#{value}")).collect();
+            output.push_str(&format!("DATA {}\n", values.join(", ")));
+        }
+    }
+    Ok(output)
+}
+
+/// Write the program bytes as hex, one instruction per line after its offset, with the
+/// instruction as source code in a trailing comment such as `00: 08 00 05   ; MOV R0, #5`
+pub fn annotate(memory: &[u8], program_bytes: u8) -> Result<String, DisassembleError> {
+    let instructions = decode_program(memory, program_bytes)?;
+    let lines = instructions
+        .iter()
+        .map(|instruction| {
+            let start = instruction.offset as usize;
+            (
+                instruction.offset,
+                &memory[start..start + instruction.byte_count()],
+                format!("; {}", format_instruction(instruction, None)),
+            )
+        })
+        .collect();
+    Ok(hex_listing(lines))
+}
+
+/// Show how the interpreter decodes every byte of the program, in the same layout as
+/// [`annotate`]. Unlike [`annotate`] this never fails. A byte that does not start a
+/// complete instruction is shown as `.byte`, and decoding carries on from the next byte.
+pub fn decode_dump(memory: &[u8], program_bytes: u8) -> String {
+    let program = &memory[..(program_bytes as usize).min(memory.len())];
+    let mut lines = Vec::new();
+    let mut offset = 0;
+    while offset < program.len() {
+        match decode_instruction(program, offset as u8) {
+            Some(instruction) => {
+                let end = offset + instruction.byte_count();
+                lines.push((
+                    offset as u8,
+                    &program[offset..end],
+                    format_instruction(&instruction, None),
+                ));
+                offset = end;
+            }
+            None => {
+                let reason = if RuntimeOpcode::try_from(program[offset]).is_err() {
+                    "invalid opcode"
+                } else {
+                    "program ends part way through this instruction"
+                };
+                lines.push((
+                    offset as u8,
+                    &program[offset..=offset],
+                    format!(".byte {}   ; {}", program[offset], reason),
+                ));
+                offset += 1;
+            }
+        }
+    }
+    hex_listing(lines)
+}
+
+/// Write each offset, its bytes in hex and a description on their own line, lining up
+/// the descriptions
+fn hex_listing(lines: Vec<(u8, &[u8], String)>) -> String {
+    let lines: Vec<(u8, String, String)> = lines
+        .into_iter()
+        .map(|(offset, bytes, text)| {
+            let hex: Vec<String> = bytes.iter().map(|byte| format!("{byte:02X}")).collect();
+            (offset, hex.join(" "), text)
+        })
+        .collect();
+    let width = lines.iter().map(|(_, hex, _)| hex.len()).max().unwrap_or(0);
+    let mut output = String::new();
+    for (offset, hex, text) in lines {
+        output.push_str(&format!("{offset:02X}: {hex:width$}   {text}\n"));
+    }
+    output
+}
+
+/// Decode every instruction in the program region
+fn decode_program(
+    memory: &[u8],
+    program_bytes: u8,
+) -> Result<Vec<DecodedInstruction>, DisassembleError> {
+    let program = &memory[..(program_bytes as usize).min(memory.len())];
+    let mut instructions = Vec::new();
+    let mut offset = 0;
+    while offset < program.len() {
+        if RuntimeOpcode::try_from(program[offset]).is_err() {
+            return Err(DisassembleError::InvalidOpcode(Box::new(InvalidOpcode {
+                opcode: program[offset],
+                offset: offset as u8,
+            })));
+        }
+        let Some(instruction) = decode_instruction(program, offset as u8) else {
+            return Err(DisassembleError::TruncatedInstruction(Box::new(
+                TruncatedInstruction {
+                    offset: offset as u8,
+                },
+            )));
+        };
+        offset += instruction.byte_count();
+        instructions.push(instruction);
+    }
+    Ok(instructions)
+}
+
+/// Write a decoded instruction as source code, using label names for branch targets
+/// if they are known
+pub(crate) fn format_instruction(
+    instruction: &DecodedInstruction,
+    labels: Option<&HashMap<u8, String>>,
+) -> String {
+    let (source_opcode, _) = SIGNATURE_TREE
+        .get_signature_for_runtime_opcode(instruction.opcode)
+        .expect("every runtime opcode has a signature");
+    let operands: Vec<String> = instruction
+        .arguments
+        .iter()
+        .zip(&instruction.operands)
+        .map(|(argument, &operand)| match argument {
+            SignatureArgument::Register => format!("R{operand}"),
+            SignatureArgument::Literal => format!("#{operand}"),
+            SignatureArgument::MemoryRef => operand.to_string(),
+            SignatureArgument::Label => labels
+                .and_then(|labels| labels.get(&operand))
+                .cloned()
+                .unwrap_or_else(|| operand.to_string()),
+        })
+        .collect();
+    if operands.is_empty() {
+        source_opcode.to_string()
+    } else {
+        format!("{} {}", source_opcode, operands.join(", "))
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub enum DisassembleError {
+    /// Byte where an instruction should start does not decode to an opcode
+    InvalidOpcode(Box<InvalidOpcode>),
+    /// Program ends part way through an instruction's operands
+    TruncatedInstruction(Box<TruncatedInstruction>),
+}
+
+impl std::error::Error for DisassembleError {}
+
+impl fmt::Display for DisassembleError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{color_red}{style_bold}")?;
+        match self {
+            DisassembleError::InvalidOpcode(err) => write!(
+                f,
+                "Disassembly Error at byte {} :: Invalid opcode '{}'",
+                err.offset, err.opcode
+            ),
+            DisassembleError::TruncatedInstruction(err) => write!(
+                f,
+                "Disassembly Error at byte {} :: Program ends part way through an instruction",
+                err.offset
+            ),
+        }?;
+        write!(f, "{color_reset}{style_reset}")
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub struct InvalidOpcode {
+    pub opcode: u8,
+    pub offset: u8,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct TruncatedInstruction {
+    pub offset: u8,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{parser::Parser, tokenizer::Tokenizer};
+
+    fn assemble(source: &str) -> ([u8; 256], u8) {
+        let tokens = Tokenizer::tokenize(source, 4).unwrap().tokens;
+        Parser::parse(tokens).unwrap()
+    }
+
+    #[test]
+    fn test_disassemble() {
+        let (memory, program_bytes) = assemble("MOV R0, #5\nSTR R0, 3\nHALT\n");
+        assert_eq!(
+            disassemble(&memory, program_bytes).unwrap(),
+            "MOV R0, #5\nSTR R0, 3\nHALT\n"
+        );
+    }
+
+    #[test]
+    fn test_disassemble_data() {
+        let (memory, program_bytes) = assemble(
+            "LDR R0, table\nPRINT R0\nHALT\n.data\ntable: DATA #7, #8\n.space 20\nDATA 9\n",
+        );
+        let source = disassemble(&memory, program_bytes).unwrap();
+        assert_eq!(
+            source,
+            format!(
+                "LDR R0, 0\nPRINT R0\nHALT\n.data\nDATA #7, #8{}\nDATA #0, #0, #0, #0, #0, #0, #9\n",
+                ", #0".repeat(14)
+            )
+        );
+        assert_eq!(assemble(&source), (memory, program_bytes));
+    }
+
+    #[test]
+    fn test_disassemble_synthesised_labels() {
+        let (memory, program_bytes) =
+            assemble("MOV R0, #3\nloop:\nSUB R0, R0, #1\nCMP R0, #0\nBNE loop\nHALT\n");
+        assert_eq!(
+            disassemble(&memory, program_bytes).unwrap(),
+            "MOV R0, #3\nSUB R0, R0, #1\nCMP R0, #0\nBNE 3\nHALT\n"
+        );
+        assert_eq!(
+            disassemble_with_labels(&memory, program_bytes, Some(&HashMap::new())).unwrap(),
+            "MOV R0, #3\nL_03:\nSUB R0, R0, #1\nCMP R0, #0\nBNE L_03\nHALT\n"
+        );
+    }
+
+    #[test]
+    fn test_disassemble_known_labels() {
+        let (memory, program_bytes) = assemble("B end\nNOP\nend: HALT\n");
+        let labels = HashMap::from([(3, String::from("end"))]);
+        assert_eq!(
+            disassemble_with_labels(&memory, program_bytes, Some(&labels)).unwrap(),
+            "B end\nNOP\nend:\nHALT\n"
+        );
+    }
+
+    #[test]
+    fn test_annotate() {
+        let (memory, program_bytes) = assemble("MOV R0, #5\nloop: PRINT R0\nB loop\nHALT\n");
+        assert_eq!(
+            annotate(&memory, program_bytes).unwrap(),
+            format!(
+                "00: {:02X} 00 05   ; MOV R0, #5\n03: {:02X} 00      ; PRINT R0\n05: {:02X} 03      ; B 3\n07: {:02X}         ; HALT\n",
+                RuntimeOpcode::MOV_LITERAL as u8,
+                RuntimeOpcode::PRINT_REGISTER as u8,
+                RuntimeOpcode::B as u8,
+                RuntimeOpcode::HALT as u8
+            )
+        );
+        assert_eq!(annotate(&memory, 0).unwrap(), "");
+    }
+
+    #[test]
+    fn test_decode_dump() {
+        let image = [
+            RuntimeOpcode::MOV_LITERAL as u8,
+            0,
+            5,
+            200,
+            RuntimeOpcode::HALT as u8,
+            RuntimeOpcode::PRINT_REGISTER as u8,
+        ];
+        assert_eq!(
+            decode_dump(&image, image.len() as u8),
+            format!(
+                "00: {:02X} 00 05   MOV R0, #5\n03: C8         .byte 200   ; invalid opcode\n04: {:02X}         HALT\n05: {:02X}         .byte {}   ; program ends part way through this instruction\n",
+                RuntimeOpcode::MOV_LITERAL as u8,
+                RuntimeOpcode::HALT as u8,
+                RuntimeOpcode::PRINT_REGISTER as u8,
+                RuntimeOpcode::PRINT_REGISTER as u8
+            )
+        );
+    }
+
+    #[test]
+    fn test_disassemble_errors() {
+        assert_eq!(
+            disassemble(&[RuntimeOpcode::NOP as u8, 200], 2).unwrap_err(),
+            DisassembleError::InvalidOpcode(Box::new(InvalidOpcode {
+                opcode: 200,
+                offset: 1,
+            }))
+        );
+        assert_eq!(
+            disassemble(&[RuntimeOpcode::MOV_LITERAL as u8, 0], 2).unwrap_err(),
+            DisassembleError::TruncatedInstruction(Box::new(TruncatedInstruction { offset: 0 }))
+        );
+    }
+}
@@ -0,0 +1,308 @@
+use crate::{
+    analysis::Warning,
+    disassembler::DisassembleError,
+    formats::FormatError,
+    include::{IncludeError, LineOrigins},
+    interpreter::RuntimeError,
+    parser::ParserError,
+    profile::InvalidCostModel,
+    tokenizer::TokenizerError,
+};
+use inline_colorization::{color_red, color_reset, style_bold, style_reset};
+use std::fmt;
+
+/// Any error that can occur while assembling or running a program
+#[derive(Debug, PartialEq)]
+pub enum Error {
+    /// Source file could not be read
+    FailedToReadFile(Box<FailedToReadFile>),
+    /// Output file could not be written
+    FailedToWriteFile(Box<FailedToWriteFile>),
+    /// Binary image could not be loaded
+    FormatError(FormatError),
+    /// Program bytes could not be decoded back into instructions
+    DisassembleError(DisassembleError),
+    /// Include directive could not be expanded
+    IncludeError(IncludeError),
+    /// Cost model used for profiling could not be parsed
+    InvalidCostModel(Box<InvalidCostModel>),
+    /// Source could not be tokenized
+    TokenizerError(TokenizerError),
+    /// Tokens could not be parsed into a program
+    ParserError(ParserError),
+    /// Program has warnings, which were treated as errors
+    Warnings(Vec<Warning>),
+    /// Program failed while running
+    RuntimeError(RuntimeError),
+    /// Program output was not the same as the expected output
+    OutputMismatch(Box<OutputMismatch>),
+    /// Tokenizer or parser error, along with the source it was found in so that the line
+    /// can be shown
+    WithSource(Box<WithSource>),
+}
+
+impl Error {
+    /// Keep the source with a tokenizer or parser error, so it is displayed with the line
+    /// it is on. Other errors are returned unchanged. If the source was expanded from
+    /// includes, `origins` gives the file and line each error was really on.
+    pub fn with_source(self, source: &str, tabsize: u8, origins: &LineOrigins) -> Error {
+        match self {
+            Error::TokenizerError(_) | Error::ParserError(_) => {
+                Error::WithSource(Box::new(WithSource {
+                    error: self,
+                    source: source.to_string(),
+                    tabsize,
+                    origins: origins.clone(),
+                }))
+            }
+            err => err,
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::FailedToReadFile(err) => write!(
+                f,
+                "{color_red}{style_bold}Failed to read the file {}: {}{color_reset}{style_reset}",
+                err.filepath, err.reason
+            ),
+            Error::FailedToWriteFile(err) => write!(
+                f,
+                "{color_red}{style_bold}Failed to write the file {}: {}{color_reset}{style_reset}",
+                err.filepath, err.reason
+            ),
+            Error::FormatError(err) => write!(f, "{err}"),
+            Error::DisassembleError(err) => write!(f, "{err}"),
+            Error::IncludeError(err) => write!(f, "{err}"),
+            Error::InvalidCostModel(err) => write!(
+                f,
+                "{color_red}{style_bold}Cost Model Error :: {err}{color_reset}{style_reset}"
+            ),
+            Error::TokenizerError(err) => write!(f, "{err}"),
+            Error::ParserError(err) => write!(f, "{err}"),
+            Error::Warnings(warnings) => {
+                for warning in warnings {
+                    writeln!(f, "{warning}")?;
+                }
+                write!(
+                    f,
+                    "{color_red}{style_bold}Assembly failed because of the warnings above{color_reset}{style_reset}"
+                )
+            }
+            Error::RuntimeError(err) => write!(f, "{err}"),
+            Error::OutputMismatch(err) => {
+                let describe = |line: &Option<String>| {
+                    line.as_deref().unwrap_or("<end of output>").to_string()
+                };
+                write!(
+                    f,
+                    "{color_red}{style_bold}Output differs from '{}' at line {}\n  expected: {}\n  found:    {}{color_reset}{style_reset}",
+                    err.filepath,
+                    err.line,
+                    describe(&err.expected),
+                    describe(&err.actual)
+                )
+            }
+            Error::WithSource(err) => write!(f, "{err}"),
+        }
+    }
+}
+
+impl From<FormatError> for Error {
+    fn from(err: FormatError) -> Self {
+        Error::FormatError(err)
+    }
+}
+
+impl From<DisassembleError> for Error {
+    fn from(err: DisassembleError) -> Self {
+        Error::DisassembleError(err)
+    }
+}
+
+impl From<IncludeError> for Error {
+    fn from(err: IncludeError) -> Self {
+        Error::IncludeError(err)
+    }
+}
+
+impl From<InvalidCostModel> for Error {
+    fn from(err: InvalidCostModel) -> Self {
+        Error::InvalidCostModel(Box::new(err))
+    }
+}
+
+impl From<TokenizerError> for Error {
+    fn from(err: TokenizerError) -> Self {
+        Error::TokenizerError(err)
+    }
+}
+
+impl From<ParserError> for Error {
+    fn from(err: ParserError) -> Self {
+        Error::ParserError(err)
+    }
+}
+
+impl From<RuntimeError> for Error {
+    fn from(err: RuntimeError) -> Self {
+        Error::RuntimeError(err)
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub struct FailedToReadFile {
+    pub filepath: String,
+    pub reason: String,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct FailedToWriteFile {
+    pub filepath: String,
+    pub reason: String,
+}
+
+/// First line where a program's output differs from the expected output
+#[derive(Debug, PartialEq)]
+pub struct OutputMismatch {
+    /// File the expected output was read from
+    pub filepath: String,
+    pub line: usize,
+    /// Expected line, or `None` if the program printed more lines than expected
+    pub expected: Option<String>,
+    /// Line the program printed, or `None` if it printed fewer lines than expected
+    pub actual: Option<String>,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct WithSource {
+    pub error: Error,
+    pub source: String,
+    /// Width of tabs, which column numbers were counted with
+    pub tabsize: u8,
+    /// Where each line of the source was read from, if it was expanded from includes
+    pub origins: LineOrigins,
+}
+
+impl WithSource {
+    /// Write the source line followed by carets under columns `col` to `end_col`. Tabs are
+    /// expanded the same way the tokenizer counts columns, so the carets line up.
+    fn write_excerpt(
+        &self,
+        f: &mut fmt::Formatter<'_>,
+        line: usize,
+        col: usize,
+        end_col: usize,
+    ) -> fmt::Result {
+        let Some(text) = line
+            .checked_sub(1)
+            .and_then(|idx| self.source.lines().nth(idx))
+        else {
+            return Ok(());
+        };
+        let text = text.replace('\t', &" ".repeat(self.tabsize as usize));
+        write!(
+            f,
+            "\n{}\n{}{}",
+            text,
+            " ".repeat(col.saturating_sub(1)),
+            "^".repeat(end_col.saturating_sub(col) + 1)
+        )
+    }
+
+    /// Write an error that is at `line` using the file and line it was read from, which
+    /// is only known if the source was expanded from includes
+    fn write_error(
+        &self,
+        f: &mut fmt::Formatter<'_>,
+        err: &impl fmt::Display,
+        (line, col): (usize, usize),
+        end_col: Option<usize>,
+        message: String,
+    ) -> fmt::Result {
+        match self.origins.get(line) {
+            Some(origin) => {
+                write!(f, "{color_red}{style_bold}{origin}, ")?;
+                match end_col {
+                    Some(end_col) => write!(f, "Columns {col}-{end_col}")?,
+                    None => write!(f, "Column {col}")?,
+                }
+                write!(f, " :: {message}{color_reset}{style_reset}")?;
+            }
+            None => write!(f, "{err}")?,
+        }
+        self.write_excerpt(f, line, col, end_col.unwrap_or(col))
+    }
+}
+
+impl fmt::Display for WithSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.error {
+            Error::TokenizerError(err) => {
+                self.write_error(f, err, err.position(), err.end_col(), err.message())
+            }
+            Error::ParserError(err) => {
+                let errors = match err {
+                    ParserError::Multiple(errors) => &errors[..],
+                    err => std::slice::from_ref(err),
+                };
+                for (i, err) in errors.iter().enumerate() {
+                    if i > 0 {
+                        writeln!(f)?;
+                    }
+                    match err.position() {
+                        Some(position) => {
+                            self.write_error(f, err, position, None, err.message())?
+                        }
+                        None => write!(f, "{err}")?,
+                    }
+                }
+                Ok(())
+            }
+            err => write!(f, "{err}"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{interpreter::ReadPastMemory, tokenizer::Tokenizer};
+
+    #[test]
+    fn test_display_error() {
+        assert_eq!(
+            Error::FailedToReadFile(Box::new(FailedToReadFile {
+                filepath: String::from("missing.aqasm"),
+                reason: String::from("not found"),
+            }))
+            .to_string(),
+            format!("{color_red}{style_bold}Failed to read the file missing.aqasm: not found{color_reset}{style_reset}")
+        );
+
+        let runtime_error =
+            RuntimeError::ReadPastMemory(Box::new(ReadPastMemory { program_counter: 0 }));
+        assert_eq!(
+            Error::RuntimeError(runtime_error).to_string(),
+            RuntimeError::ReadPastMemory(Box::new(ReadPastMemory { program_counter: 0 }))
+                .to_string()
+        );
+    }
+
+    #[test]
+    fn test_display_with_source() {
+        // The tab counts as 4 columns, so the literal starts at column 13
+        let source = "HALT\n\tMOV R0, #300\n";
+        let err = Error::from(Tokenizer::tokenize(source, 4).unwrap_err());
+        assert_eq!(
+            err.with_source(source, 4, &LineOrigins::default()).to_string(),
+            format!(
+                "{color_red}{style_bold}Line 2, Columns 13-16 :: Literal value '300' too large (max value of 255){color_reset}{style_reset}\n    MOV R0, #300\n            ^^^^"
+            )
+        );
+    }
+}
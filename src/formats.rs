@@ -0,0 +1,473 @@
+use crate::{
+    interpreter::MAX_PROGRAM_BYTES,
+    parser::{LineTable, Parser, SymbolTable},
+    tokenizer::Tokenizer,
+    Error, MachineOptions,
+};
+use inline_colorization::{color_red, color_reset, style_bold, style_reset};
+use std::fmt;
+
+/// Bytes every headered binary starts with. 'A' is not a valid opcode, so a raw image
+/// can never be mistaken for one with a header.
+pub const MAGIC: [u8; 4] = *b"AQAB";
+/// Version of the binary format written by this build
+pub const FORMAT_VERSION: u8 = 2;
+/// Size of a serialised [`Header`] in bytes
+pub const HEADER_LEN: usize = MAGIC.len() + 4;
+
+/// Metadata prepended to an assembled program so that it can be validated when loaded
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Header {
+    pub version: u8,
+    /// Amount of program bytes following the header
+    pub program_bytes: u8,
+    /// Amount of data bytes following the program, stored as a two byte little endian
+    /// number as an empty program leaves all 256 bytes for data
+    pub data_bytes: u16,
+}
+
+impl Header {
+    pub fn new(program_bytes: u8, data_bytes: u16) -> Self {
+        Self {
+            version: FORMAT_VERSION,
+            program_bytes,
+            data_bytes,
+        }
+    }
+
+    pub fn serialise(&self) -> [u8; HEADER_LEN] {
+        let mut bytes = [0; HEADER_LEN];
+        bytes[..MAGIC.len()].copy_from_slice(&MAGIC);
+        bytes[MAGIC.len()] = self.version;
+        bytes[MAGIC.len() + 1] = self.program_bytes;
+        bytes[MAGIC.len() + 2..].copy_from_slice(&self.data_bytes.to_le_bytes());
+        bytes
+    }
+
+    /// Parse a header from the start of `bytes`
+    pub fn parse(bytes: &[u8]) -> Result<Self, FormatError> {
+        if !bytes.starts_with(&MAGIC) {
+            return Err(FormatError::BadMagic);
+        }
+        if bytes.len() < HEADER_LEN {
+            return Err(FormatError::TruncatedHeader);
+        }
+        let version = bytes[MAGIC.len()];
+        if version != FORMAT_VERSION {
+            return Err(FormatError::UnsupportedVersion(Box::new(UnsupportedVersion {
+                version,
+            })));
+        }
+        Ok(Self {
+            version,
+            program_bytes: bytes[MAGIC.len() + 1],
+            data_bytes: u16::from_le_bytes([bytes[MAGIC.len() + 2], bytes[MAGIC.len() + 3]]),
+        })
+    }
+}
+
+/// The data region after a program up to its last non-zero byte, which is all an image
+/// needs to store as memory starts out as zero
+pub fn initial_data(memory: &[u8], program_bytes: u8) -> &[u8] {
+    let data = memory.get(program_bytes as usize..).unwrap_or_default();
+    let len = data
+        .iter()
+        .rposition(|&byte| byte != 0)
+        .map_or(0, |idx| idx + 1);
+    &data[..len]
+}
+
+/// Serialise an assembled program, optionally prepending a [`Header`]. The initial data
+/// region is only written with a header, as a raw image is nothing but program bytes.
+pub fn serialise_image(program: &[u8], data: &[u8], with_header: bool) -> Vec<u8> {
+    let mut bytes = Vec::with_capacity(HEADER_LEN + program.len() + data.len());
+    if with_header {
+        bytes.extend(Header::new(program.len() as u8, data.len() as u16).serialise());
+    }
+    bytes.extend_from_slice(program);
+    if with_header {
+        bytes.extend_from_slice(data);
+    }
+    bytes
+}
+
+/// Load a binary image into memory, returning the memory and amount of program bytes.
+/// Images starting with [`MAGIC`] have their header checked and their data loaded after
+/// the program, anything else is loaded as raw program bytes.
+pub fn load_image(bytes: &[u8]) -> Result<([u8; 256], u8), FormatError> {
+    let (program, data) = if bytes.starts_with(&MAGIC) {
+        let header = Header::parse(bytes)?;
+        let body = &bytes[HEADER_LEN..];
+        let expected = header.program_bytes as usize + header.data_bytes as usize;
+        if body.len() != expected {
+            return Err(FormatError::LengthMismatch(Box::new(LengthMismatch {
+                expected,
+                actual: body.len(),
+            })));
+        }
+        body.split_at(header.program_bytes as usize)
+    } else {
+        (bytes, &[][..])
+    };
+    if program.len() > MAX_PROGRAM_BYTES as usize {
+        return Err(FormatError::ImageTooLarge(Box::new(ImageTooLarge {
+            size: program.len(),
+        })));
+    }
+    if program.len() + data.len() > 256 {
+        return Err(FormatError::DataTooLarge(Box::new(DataTooLarge {
+            program_bytes: program.len(),
+            data_bytes: data.len(),
+        })));
+    }
+    let mut memory = [0; 256];
+    memory[..program.len()].copy_from_slice(program);
+    memory[program.len()..program.len() + data.len()].copy_from_slice(data);
+    Ok((memory, program.len() as u8))
+}
+
+/// Bytes every source map starts with
+pub const SOURCE_MAP_MAGIC: [u8; 4] = *b"AQAM";
+
+/// Line table and symbol table of an assembled program, written alongside a binary so
+/// that runtime errors can be traced back to source lines without the source code.
+///
+/// After the magic bytes and format version, the line table is stored as a one byte entry
+/// count followed by a one byte offset and four byte little endian line number for each
+/// entry. The symbol table is a two byte little endian count followed by a one byte
+/// offset, two byte little endian name length and UTF-8 name for each label.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SourceMap {
+    pub line_table: LineTable,
+    pub symbols: SymbolTable,
+}
+
+impl SourceMap {
+    pub fn serialise(&self) -> Vec<u8> {
+        let mut bytes = SOURCE_MAP_MAGIC.to_vec();
+        bytes.push(FORMAT_VERSION);
+        // Every instruction is at least one byte, so there are at most 255 of them
+        bytes.push(self.line_table.len() as u8);
+        for (&offset, &line) in &self.line_table {
+            bytes.push(offset);
+            bytes.extend((line as u32).to_le_bytes());
+        }
+        let mut symbols: Vec<(&String, &u8)> = self.symbols.iter().collect();
+        symbols.sort();
+        bytes.extend((symbols.len() as u16).to_le_bytes());
+        for (name, &offset) in symbols {
+            bytes.push(offset);
+            bytes.extend((name.len() as u16).to_le_bytes());
+            bytes.extend(name.as_bytes());
+        }
+        bytes
+    }
+
+    pub fn parse(bytes: &[u8]) -> Result<Self, FormatError> {
+        if !bytes.starts_with(&SOURCE_MAP_MAGIC) {
+            return Err(FormatError::BadMagic);
+        }
+        let mut reader = ByteReader {
+            bytes,
+            position: SOURCE_MAP_MAGIC.len(),
+        };
+        let version = reader.take(1)?[0];
+        if version != FORMAT_VERSION {
+            return Err(FormatError::UnsupportedVersion(Box::new(UnsupportedVersion {
+                version,
+            })));
+        }
+        let mut source_map = SourceMap::default();
+        for _ in 0..reader.take(1)?[0] {
+            let offset = reader.take(1)?[0];
+            let line = u32::from_le_bytes(reader.take(4)?.try_into().unwrap());
+            source_map.line_table.insert(offset, line as usize);
+        }
+        for _ in 0..reader.take_u16()? {
+            let offset = reader.take(1)?[0];
+            let length = reader.take_u16()?;
+            let name = std::str::from_utf8(reader.take(length as usize)?)
+                .map_err(|_| FormatError::InvalidSymbolName)?;
+            source_map.symbols.insert(name.to_string(), offset);
+        }
+        Ok(source_map)
+    }
+}
+
+/// Version of the JSON program representation written by [`program_to_json`]
+pub const JSON_FORMAT_VERSION: u8 = 1;
+
+/// Assemble source code into a JSON document for front-ends that show programs
+/// structurally. The document is an object with these fields:
+/// * `version`: [`JSON_FORMAT_VERSION`]
+/// * `instructions`: an object for each instruction with its `offset`, `mnemonic`,
+///   `operands` as written in the source, and the `line` it is on
+/// * `labels`: the byte offset of each label, in name order
+/// * `bytes`: the assembled program
+pub fn program_to_json(source: &str, options: &MachineOptions) -> Result<String, Error> {
+    let tokenizer = Tokenizer::tokenize_with_options(source, options.tokenizer_options())?;
+    let instructions = Parser::parse_to_ast(&tokenizer.tokens, &tokenizer.comments)?;
+    let (memory, program_bytes, _, symbols) = Parser::parse_with_symbols(tokenizer.tokens)?;
+
+    let instructions: Vec<String> = instructions
+        .iter()
+        .map(|instruction| {
+            // Lexemes and label names are letters, digits, '#' and '_', so need no escaping
+            let operands: Vec<String> = instruction
+                .operands
+                .iter()
+                .map(|token| format!("\"{}\"", token.lexeme))
+                .collect();
+            format!(
+                "{{\"offset\":{},\"mnemonic\":\"{}\",\"operands\":[{}],\"line\":{}}}",
+                instruction.offset,
+                instruction.source_opcode,
+                operands.join(","),
+                instruction.opcode_token.line
+            )
+        })
+        .collect();
+    let mut symbols: Vec<(&String, &u8)> = symbols.iter().collect();
+    symbols.sort();
+    let labels: Vec<String> = symbols
+        .iter()
+        .map(|(name, offset)| format!("\"{name}\":{offset}"))
+        .collect();
+    let bytes: Vec<String> = memory[..program_bytes as usize]
+        .iter()
+        .map(|byte| byte.to_string())
+        .collect();
+    Ok(format!(
+        "{{\"version\":{},\"instructions\":[{}],\"labels\":{{{}}},\"bytes\":[{}]}}",
+        JSON_FORMAT_VERSION,
+        instructions.join(","),
+        labels.join(","),
+        bytes.join(",")
+    ))
+}
+
+/// Reads fields from a byte slice, erroring if it ends early
+struct ByteReader<'a> {
+    bytes: &'a [u8],
+    position: usize,
+}
+
+impl<'a> ByteReader<'a> {
+    fn take(&mut self, count: usize) -> Result<&'a [u8], FormatError> {
+        let taken = self
+            .bytes
+            .get(self.position..self.position + count)
+            .ok_or(FormatError::TruncatedSourceMap)?;
+        self.position += count;
+        Ok(taken)
+    }
+
+    fn take_u16(&mut self) -> Result<u16, FormatError> {
+        Ok(u16::from_le_bytes(self.take(2)?.try_into().unwrap()))
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub enum FormatError {
+    /// Header does not begin with the magic bytes
+    BadMagic,
+    /// File ends part way through the header
+    TruncatedHeader,
+    /// Header was written by an unknown version of the format
+    UnsupportedVersion(Box<UnsupportedVersion>),
+    /// Amount of program bytes differs from the header, the file is likely truncated
+    LengthMismatch(Box<LengthMismatch>),
+    /// Program does not fit into memory
+    ImageTooLarge(Box<ImageTooLarge>),
+    /// Data does not fit into the memory left after the program
+    DataTooLarge(Box<DataTooLarge>),
+    /// Source map ends part way through an entry
+    TruncatedSourceMap,
+    /// Label name in a source map is not valid UTF-8
+    InvalidSymbolName,
+}
+
+impl std::error::Error for FormatError {}
+
+impl fmt::Display for FormatError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{color_red}{style_bold}")?;
+        match self {
+            FormatError::BadMagic => write!(f, "Binary Error :: Missing or invalid header magic"),
+            FormatError::TruncatedHeader => {
+                write!(f, "Binary Error :: File ends part way through the header")
+            }
+            FormatError::UnsupportedVersion(err) => write!(
+                f,
+                "Binary Error :: Unsupported format version {} (expected {})",
+                err.version, FORMAT_VERSION
+            ),
+            FormatError::LengthMismatch(err) => write!(
+                f,
+                "Binary Error :: Header says the program is {} bytes but {} bytes were found",
+                err.expected, err.actual
+            ),
+            FormatError::ImageTooLarge(err) => write!(
+                f,
+                "Binary Error :: Program of {} bytes is too large to load into memory (max {})",
+                err.size, MAX_PROGRAM_BYTES
+            ),
+            FormatError::DataTooLarge(err) => write!(
+                f,
+                "Binary Error :: {} bytes of data do not fit in memory after a program of {} bytes",
+                err.data_bytes, err.program_bytes
+            ),
+            FormatError::TruncatedSourceMap => {
+                write!(f, "Binary Error :: Source map ends part way through an entry")
+            }
+            FormatError::InvalidSymbolName => {
+                write!(f, "Binary Error :: Source map contains a label name that is not valid UTF-8")
+            }
+        }?;
+        write!(f, "{color_reset}{style_reset}")
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub struct UnsupportedVersion {
+    pub version: u8,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct LengthMismatch {
+    pub expected: usize,
+    pub actual: usize,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct ImageTooLarge {
+    pub size: usize,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct DataTooLarge {
+    pub program_bytes: usize,
+    pub data_bytes: usize,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_header_round_trip() {
+        let header = Header::new(42, 300);
+        assert_eq!(Header::parse(&header.serialise()).unwrap(), header);
+    }
+
+    #[test]
+    fn test_header_bad_magic() {
+        assert_eq!(
+            Header::parse(b"AQAX\x02\x00\x00\x00").unwrap_err(),
+            FormatError::BadMagic
+        );
+        assert_eq!(Header::parse(b"AQ").unwrap_err(), FormatError::BadMagic);
+        assert_eq!(
+            Header::parse(b"AQAB\x02\x00").unwrap_err(),
+            FormatError::TruncatedHeader
+        );
+    }
+
+    #[test]
+    fn test_header_unsupported_version() {
+        assert_eq!(
+            Header::parse(b"AQAB\x09\x00\x00\x00").unwrap_err(),
+            FormatError::UnsupportedVersion(Box::new(UnsupportedVersion { version: 9 }))
+        );
+    }
+
+    #[test]
+    fn test_image_round_trip() {
+        let program = [5, 0, 1, 32];
+        for with_header in [false, true] {
+            let (memory, program_bytes) =
+                load_image(&serialise_image(&program, &[], with_header)).unwrap();
+            assert_eq!(program_bytes, 4);
+            assert_eq!(memory[..4], program);
+        }
+    }
+
+    #[test]
+    fn test_image_data() {
+        let mut memory = [0; 256];
+        memory[..4].copy_from_slice(&[5, 0, 1, 32]);
+        memory[5] = 7;
+        let data = initial_data(&memory, 4);
+        assert_eq!(data, [0, 7]);
+
+        let (loaded, program_bytes) =
+            load_image(&serialise_image(&memory[..4], data, true)).unwrap();
+        assert_eq!(program_bytes, 4);
+        assert_eq!(loaded, memory);
+        assert_eq!(initial_data(&[0; 256], 0), []);
+
+        let mut image = Header::new(200, 100).serialise().to_vec();
+        image.resize(HEADER_LEN + 300, 0);
+        assert_eq!(
+            load_image(&image).unwrap_err(),
+            FormatError::DataTooLarge(Box::new(DataTooLarge {
+                program_bytes: 200,
+                data_bytes: 100,
+            }))
+        );
+    }
+
+    #[test]
+    fn test_image_truncated() {
+        let mut image = serialise_image(&[5, 0, 1, 32], &[9], true);
+        image.pop();
+        assert_eq!(
+            load_image(&image).unwrap_err(),
+            FormatError::LengthMismatch(Box::new(LengthMismatch {
+                expected: 5,
+                actual: 4,
+            }))
+        );
+    }
+
+    #[test]
+    fn test_source_map_round_trip() {
+        let source_map = SourceMap {
+            line_table: LineTable::from([(0, 2), (3, 5), (5, 1000)]),
+            symbols: SymbolTable::from([(String::from("loop"), 3), (String::from("end"), 5)]),
+        };
+        let bytes = source_map.serialise();
+        assert_eq!(SourceMap::parse(&bytes).unwrap(), source_map);
+
+        assert_eq!(
+            SourceMap::parse(&bytes[..bytes.len() - 1]).unwrap_err(),
+            FormatError::TruncatedSourceMap
+        );
+        assert_eq!(SourceMap::parse(b"AQAB\x01").unwrap_err(), FormatError::BadMagic);
+    }
+
+    #[test]
+    fn test_program_to_json() {
+        let json = program_to_json(
+            "MOV R0, #2\nloop: SUB R0, R0, #1\nCMP R0, #0\nBNE loop\nHALT\n",
+            &MachineOptions::default(),
+        )
+        .unwrap();
+        assert!(json.starts_with(&format!("{{\"version\":{JSON_FORMAT_VERSION},")));
+        assert!(json
+            .contains(r##"{"offset":3,"mnemonic":"SUB","operands":["R0","R0","#1"],"line":2}"##));
+        assert!(json.contains(r#"{"offset":10,"mnemonic":"BNE","operands":["loop"],"line":4}"#));
+        assert!(json.contains(r#""labels":{"loop":3}"#));
+        assert!(json.ends_with(",3,32]}"));
+
+        assert!(program_to_json("MOV R0\n", &MachineOptions::default()).is_err());
+    }
+
+    #[test]
+    fn test_image_too_large() {
+        assert_eq!(
+            load_image(&[0; 256]).unwrap_err(),
+            FormatError::ImageTooLarge(Box::new(ImageTooLarge { size: 256 }))
+        );
+    }
+}
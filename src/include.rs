@@ -0,0 +1,330 @@
+use crate::{
+    tokenizer::{Tokenizer, TokenizerOptions},
+    Error, FailedToReadFile,
+};
+use inline_colorization::{color_red, color_reset, style_bold, style_reset};
+use std::{
+    fmt, fs,
+    path::{Path, PathBuf},
+};
+
+/// How many files deep includes can be nested
+pub const MAX_INCLUDE_DEPTH: usize = 16;
+
+/// Source code with every include expanded, along with where each line came from
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ExpandedSource {
+    pub source: String,
+    pub origins: LineOrigins,
+}
+
+/// The file and line that each line of expanded source was read from. This is empty
+/// when nothing was included, as line numbers then already match the file.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct LineOrigins(Vec<LineOrigin>);
+
+impl LineOrigins {
+    /// Where a line of the expanded source was read from, counting lines from 1
+    pub fn get(&self, line: usize) -> Option<&LineOrigin> {
+        self.0.get(line.checked_sub(1)?)
+    }
+}
+
+impl FromIterator<LineOrigin> for LineOrigins {
+    fn from_iter<I: IntoIterator<Item = LineOrigin>>(iter: I) -> Self {
+        LineOrigins(iter.into_iter().collect())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct LineOrigin {
+    pub filepath: String,
+    pub line: usize,
+}
+
+impl fmt::Display for LineOrigin {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}, Line {}", self.filepath, self.line)
+    }
+}
+
+/// A line of source while includes are being expanded
+struct SourceLine {
+    text: String,
+    origin: LineOrigin,
+    /// The file the line is in, followed by each file that included it, outermost last
+    files: Vec<PathBuf>,
+}
+
+/// Read a source file, replacing every `.include "file.aqasm"` line with the contents of
+/// that file. Included paths are relative to the directory of the file including them,
+/// and labels are shared between all files as they are assembled as one program. Include
+/// lines inside comments or '.if' blocks that are not included are left alone, so the
+/// files they name are never read.
+pub fn read_source_with_includes(
+    filepath: &Path,
+    options: &TokenizerOptions,
+) -> Result<ExpandedSource, Error> {
+    let mut lines = read_lines(filepath, Vec::new())?;
+    let mut expanded = false;
+    loop {
+        let source = join_lines(&lines);
+        let includes = Tokenizer::find_includes(&source, options.clone());
+        if includes.is_empty() {
+            let origins = if expanded {
+                lines.into_iter().map(|line| line.origin).collect()
+            } else {
+                LineOrigins::default()
+            };
+            return Ok(ExpandedSource { source, origins });
+        }
+        expanded = true;
+        // Replace from the end so the line numbers of earlier includes stay the same
+        for include in includes.iter().rev() {
+            let idx = include.line - 1;
+            let included = resolve(&lines[idx], &include.argument)?;
+            let included = read_lines(&included, lines[idx].files.clone())?;
+            lines.splice(idx..=idx, included);
+        }
+    }
+}
+
+/// Find the file an include line names, checking that including it would not recurse
+/// forever or nest too deeply
+fn resolve(line: &SourceLine, argument: &str) -> Result<PathBuf, Error> {
+    let location = || {
+        Box::new(IncludeLocation {
+            filepath: line.origin.filepath.clone(),
+            line: line.origin.line,
+        })
+    };
+    let Some(included) = argument
+        .trim()
+        .strip_prefix('"')
+        .and_then(|rest| rest.strip_suffix('"'))
+    else {
+        return Err(IncludeError::InvalidDirective(location()).into());
+    };
+    let included = Path::new(&line.origin.filepath)
+        .parent()
+        .unwrap_or(Path::new(""))
+        .join(included);
+    if included
+        .canonicalize()
+        .is_ok_and(|included| line.files.contains(&included))
+    {
+        return Err(IncludeError::RecursiveInclude(location()).into());
+    }
+    if line.files.len() >= MAX_INCLUDE_DEPTH {
+        return Err(IncludeError::DepthExceeded(location()).into());
+    }
+    Ok(included)
+}
+
+/// Read each line of a file, which was included by the innermost file of `files`
+fn read_lines(filepath: &Path, mut files: Vec<PathBuf>) -> Result<Vec<SourceLine>, Error> {
+    let read_error = |err: std::io::Error| {
+        Error::FailedToReadFile(Box::new(FailedToReadFile {
+            filepath: filepath.display().to_string(),
+            reason: err.to_string(),
+        }))
+    };
+    let source = fs::read_to_string(filepath).map_err(read_error)?;
+    files.insert(0, filepath.canonicalize().map_err(read_error)?);
+    Ok(source
+        .lines()
+        .enumerate()
+        .map(|(idx, text)| SourceLine {
+            text: text.to_string(),
+            origin: LineOrigin {
+                filepath: filepath.display().to_string(),
+                line: idx + 1,
+            },
+            files: files.clone(),
+        })
+        .collect())
+}
+
+fn join_lines(lines: &[SourceLine]) -> String {
+    let mut source = String::new();
+    for line in lines {
+        source.push_str(&line.text);
+        source.push('\n');
+    }
+    source
+}
+
+#[derive(Debug, PartialEq)]
+pub enum IncludeError {
+    /// Include directive is not followed by a quoted path
+    InvalidDirective(Box<IncludeLocation>),
+    /// File includes itself, directly or through other files
+    RecursiveInclude(Box<IncludeLocation>),
+    /// Includes are nested more than [`MAX_INCLUDE_DEPTH`] files deep
+    DepthExceeded(Box<IncludeLocation>),
+}
+
+/// Where an include directive was written
+#[derive(Debug, PartialEq)]
+pub struct IncludeLocation {
+    pub filepath: String,
+    pub line: usize,
+}
+
+impl std::error::Error for IncludeError {}
+
+impl fmt::Display for IncludeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{color_red}{style_bold}")?;
+        match self {
+            IncludeError::InvalidDirective(err) => write!(
+                f,
+                "{}, Line {} :: Expected a quoted file path after '.include'",
+                err.filepath, err.line
+            ),
+            IncludeError::RecursiveInclude(err) => write!(
+                f,
+                "{}, Line {} :: File is already being included, so including it again would never end",
+                err.filepath, err.line
+            ),
+            IncludeError::DepthExceeded(err) => write!(
+                f,
+                "{}, Line {} :: Includes are nested more than {} files deep",
+                err.filepath, err.line, MAX_INCLUDE_DEPTH
+            ),
+        }?;
+        write!(f, "{color_reset}{style_reset}")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Create an empty directory for a test to write its files into
+    fn test_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("aqa_asm_include_{name}"));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn test_include_two_files() {
+        let dir = test_dir("two_files");
+        fs::create_dir(dir.join("lib")).unwrap();
+        fs::write(
+            dir.join("main.aqasm"),
+            "MOV R0, #1\n.include \"lib/double.aqasm\"\nPRINT R0\nHALT\n",
+        )
+        .unwrap();
+        fs::write(dir.join("lib/double.aqasm"), "double: ADD R0, R0, R0").unwrap();
+
+        assert_eq!(
+            read_source_with_includes(&dir.join("main.aqasm"), &TokenizerOptions::default())
+                .unwrap()
+                .source,
+            "MOV R0, #1\ndouble: ADD R0, R0, R0\nPRINT R0\nHALT\n"
+        );
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_include_self() {
+        let dir = test_dir("self");
+        let path = dir.join("main.aqasm");
+        fs::write(&path, "NOP\n  .include \"main.aqasm\"\n").unwrap();
+
+        assert_eq!(
+            read_source_with_includes(&path, &TokenizerOptions::default()).unwrap_err(),
+            Error::IncludeError(IncludeError::RecursiveInclude(Box::new(IncludeLocation {
+                filepath: path.display().to_string(),
+                line: 2,
+            })))
+        );
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_include_invalid_directive() {
+        let dir = test_dir("invalid");
+        let path = dir.join("main.aqasm");
+        fs::write(&path, ".include other.aqasm\n").unwrap();
+
+        assert!(matches!(
+            read_source_with_includes(&path, &TokenizerOptions::default()).unwrap_err(),
+            Error::IncludeError(IncludeError::InvalidDirective(_))
+        ));
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_include_depth_exceeded() {
+        let dir = test_dir("depth");
+        for idx in 0..=MAX_INCLUDE_DEPTH {
+            fs::write(
+                dir.join(format!("{idx}.aqasm")),
+                format!(".include \"{}.aqasm\"\n", idx + 1),
+            )
+            .unwrap();
+        }
+
+        assert!(matches!(
+            read_source_with_includes(&dir.join("0.aqasm"), &TokenizerOptions::default())
+                .unwrap_err(),
+            Error::IncludeError(IncludeError::DepthExceeded(_))
+        ));
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_include_line_origins() {
+        let dir = test_dir("origins");
+        let main = dir.join("main.aqasm");
+        let lib = dir.join("lib.aqasm");
+        fs::write(&main, "MOV R0, #1\n.include \"lib.aqasm\"\nHALT\n").unwrap();
+        fs::write(&lib, "NOP\nNOP\n").unwrap();
+
+        let expanded = read_source_with_includes(&main, &TokenizerOptions::default()).unwrap();
+        assert_eq!(expanded.source, "MOV R0, #1\nNOP\nNOP\nHALT\n");
+        for (line, filepath, origin_line) in
+            [(1, &main, 1), (2, &lib, 1), (3, &lib, 2), (4, &main, 3)]
+        {
+            assert_eq!(
+                expanded.origins.get(line),
+                Some(&LineOrigin {
+                    filepath: filepath.display().to_string(),
+                    line: origin_line,
+                })
+            );
+        }
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_include_skipped() {
+        let dir = test_dir("skipped");
+        let path = dir.join("main.aqasm");
+        let source = "/*\n.include \"missing.aqasm\"\n*/\n.if DEBUG\n.include \"debug.aqasm\"\n.endif\nHALT\n";
+        fs::write(&path, source).unwrap();
+
+        // Neither file exists, but neither is read unless DEBUG is defined
+        let expanded = read_source_with_includes(&path, &TokenizerOptions::default()).unwrap();
+        assert_eq!(
+            expanded,
+            ExpandedSource {
+                source: source.to_string(),
+                origins: LineOrigins::default(),
+            }
+        );
+        let debug = TokenizerOptions {
+            defines: vec![String::from("DEBUG")],
+            ..Default::default()
+        };
+        assert!(matches!(
+            read_source_with_includes(&path, &debug).unwrap_err(),
+            Error::FailedToReadFile(err) if err.filepath.ends_with("debug.aqasm")
+        ));
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -3,39 +3,387 @@ use std::fmt;
 
 #[derive(Debug, PartialEq)]
 pub enum RuntimeError {
-    ReadPastMemory,
-    OutOfBoundsRead(usize),
-    OutOfBoundsWrite(usize),
+    /// Program counter moved past the last byte of the program
+    ReadPastMemory(Box<ReadPastMemory>),
+    /// Attempt to read from a memory reference outside of the data region
+    OutOfBoundsRead(Box<OutOfBoundsRead>),
+    /// Attempt to write to a memory reference outside of the data region
+    OutOfBoundsWrite(Box<OutOfBoundsWrite>),
+    /// Byte in the program region that does not decode to an opcode
+    InvalidOpcode(Box<InvalidOpcode>),
+    /// Register operand outside of the range of available registers
+    InvalidRegister(Box<InvalidRegister>),
+    /// INPUT instruction executed after all input has been consumed
+    EndOfInput(Box<EndOfInput>),
+    /// INPUT read more invalid entries than it is allowed to
+    TooManyInvalidInputs(Box<TooManyInvalidInputs>),
+    /// Stack grew down until it would overwrite the program
+    StackOverflow(Box<StackOverflow>),
+    /// Value popped from the stack while it was empty
+    StackUnderflow(Box<StackUnderflow>),
+    /// Reading input or writing output failed
+    Io(Box<Io>),
+    /// DIV or MOD with a divisor of zero
+    DivideByZero(Box<DivideByZero>),
+    /// Program executed as many instructions as it was allowed to without halting
+    CycleLimitExceeded(Box<CycleLimitExceeded>),
+    /// Program wrote to the memory reference being watched with `--break-on-write`
+    WatchpointHit(Box<WatchpointHit>),
+    /// Another runtime error along with the line of source code that caused it
+    WithSourceLine(Box<WithSourceLine>),
+}
+
+impl RuntimeError {
+    /// Byte offset of the instruction that was executing when the error occured
+    pub fn program_counter(&self) -> u8 {
+        match self {
+            RuntimeError::ReadPastMemory(err) => err.program_counter,
+            RuntimeError::OutOfBoundsRead(err) => err.program_counter,
+            RuntimeError::OutOfBoundsWrite(err) => err.program_counter,
+            RuntimeError::InvalidOpcode(err) => err.program_counter,
+            RuntimeError::InvalidRegister(err) => err.program_counter,
+            RuntimeError::EndOfInput(err) => err.program_counter,
+            RuntimeError::TooManyInvalidInputs(err) => err.program_counter,
+            RuntimeError::StackOverflow(err) => err.program_counter,
+            RuntimeError::StackUnderflow(err) => err.program_counter,
+            RuntimeError::Io(err) => err.program_counter,
+            RuntimeError::DivideByZero(err) => err.program_counter,
+            RuntimeError::CycleLimitExceeded(err) => err.program_counter,
+            RuntimeError::WatchpointHit(err) => err.program_counter,
+            RuntimeError::WithSourceLine(err) => err.error.program_counter(),
+        }
+    }
 }
 
 impl std::error::Error for RuntimeError {}
 
 impl fmt::Display for RuntimeError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // The wrapped error already handles its own styling
+        if let RuntimeError::WithSourceLine(err) = self {
+            write!(f, "{}\n{color_red}{style_bold}", err.error)?;
+            if let Some(filepath) = &err.filepath {
+                write!(f, "{filepath}, ")?;
+            }
+            write!(f, "Line {}", err.line)?;
+            if !err.text.trim().is_empty() {
+                write!(f, " :: {}", err.text.trim())?;
+            }
+            return write!(f, "{color_reset}{style_reset}");
+        }
         write!(f, "{color_red}{style_bold}")?;
         match self {
-            RuntimeError::ReadPastMemory => write!(f, "Runtime Error :: Program read past of available memory (perhaps you forgot the 'HALT' instruction?)"),
-            RuntimeError::OutOfBoundsRead(idx) => write!(f, "Runtime Error :: Attempt to read out of bounds memory location {idx}"),
-            RuntimeError::OutOfBoundsWrite(idx) => write!(f, "Runtime Error :: Attempt to write to out of bounds memory location {idx}")
+            RuntimeError::ReadPastMemory(err) => write!(
+                f,
+                "Runtime Error at byte {} :: Program read past of available memory (perhaps you forgot the 'HALT' instruction?)",
+                err.program_counter
+            ),
+            RuntimeError::OutOfBoundsRead(err) => write!(
+                f,
+                "Runtime Error at byte {} :: Attempt to read out of bounds memory location {} (address {})",
+                err.program_counter, err.idx, err.address
+            ),
+            RuntimeError::OutOfBoundsWrite(err) => write!(
+                f,
+                "Runtime Error at byte {} :: Attempt to write to out of bounds memory location {} (address {})",
+                err.program_counter, err.idx, err.address
+            ),
+            RuntimeError::InvalidOpcode(err) => write!(
+                f,
+                "Runtime Error at byte {} :: Invalid opcode {}",
+                err.program_counter, err.opcode
+            ),
+            RuntimeError::InvalidRegister(err) => write!(
+                f,
+                "Runtime Error at byte {} :: Invalid register R{} (must be in range 0-12 inclusive)",
+                err.program_counter, err.register
+            ),
+            RuntimeError::EndOfInput(err) => write!(
+                f,
+                "Runtime Error at byte {} :: Expected input but reached the end of input",
+                err.program_counter
+            ),
+            RuntimeError::TooManyInvalidInputs(err) => write!(
+                f,
+                "Runtime Error at byte {} :: Gave up on input after {} invalid {}",
+                err.program_counter,
+                err.invalid_inputs,
+                if err.invalid_inputs == 1 {
+                    "entry"
+                } else {
+                    "entries"
+                }
+            ),
+            RuntimeError::StackOverflow(err) => write!(
+                f,
+                "Runtime Error at byte {} :: Stack overflow, the stack ran into the program",
+                err.program_counter
+            ),
+            RuntimeError::StackUnderflow(err) => write!(
+                f,
+                "Runtime Error at byte {} :: Stack underflow, nothing left on the stack to take",
+                err.program_counter
+            ),
+            RuntimeError::Io(err) => write!(
+                f,
+                "Runtime Error at byte {} :: Input/output failed: {}",
+                err.program_counter, err.reason
+            ),
+            RuntimeError::DivideByZero(err) => write!(
+                f,
+                "Runtime Error at byte {} :: Attempt to divide by zero",
+                err.program_counter
+            ),
+            RuntimeError::CycleLimitExceeded(err) => write!(
+                f,
+                "Runtime Error at byte {} :: Stopped after {} instructions without halting (is there an infinite loop?)",
+                err.program_counter, err.limit
+            ),
+            RuntimeError::WatchpointHit(err) => write!(
+                f,
+                "Runtime Error at byte {} :: Stopped at the first write to memory location {}, which wrote {}",
+                err.program_counter, err.idx, err.value
+            ),
+            RuntimeError::WithSourceLine(_) => unreachable!(),
         }?;
         write!(f, "{color_reset}{style_reset}")
     }
 }
 
+#[derive(Debug, PartialEq)]
+pub struct ReadPastMemory {
+    /// Byte offset of the instruction being executed
+    pub program_counter: u8,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct OutOfBoundsRead {
+    pub idx: usize,
+    /// Absolute address in memory, the data region offset plus the size of the program
+    pub address: usize,
+    /// Byte offset of the instruction being executed
+    pub program_counter: u8,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct OutOfBoundsWrite {
+    pub idx: usize,
+    /// Absolute address in memory, the data region offset plus the size of the program
+    pub address: usize,
+    /// Byte offset of the instruction being executed
+    pub program_counter: u8,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct InvalidOpcode {
+    pub opcode: u8,
+    /// Byte offset of the instruction being executed
+    pub program_counter: u8,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct InvalidRegister {
+    pub register: u8,
+    /// Byte offset of the instruction being executed
+    pub program_counter: u8,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct EndOfInput {
+    /// Byte offset of the instruction being executed
+    pub program_counter: u8,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct TooManyInvalidInputs {
+    /// Invalid entries read by the INPUT, one more than the retries allowed
+    pub invalid_inputs: u32,
+    /// Byte offset of the instruction being executed
+    pub program_counter: u8,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct StackOverflow {
+    /// Byte offset of the instruction being executed
+    pub program_counter: u8,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct StackUnderflow {
+    /// Byte offset of the instruction being executed
+    pub program_counter: u8,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct Io {
+    pub reason: String,
+    /// Byte offset of the instruction being executed
+    pub program_counter: u8,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct DivideByZero {
+    /// Byte offset of the instruction being executed
+    pub program_counter: u8,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct CycleLimitExceeded {
+    /// Number of instructions the program was allowed to execute
+    pub limit: u64,
+    /// Byte offset of the instruction that would have gone over the limit
+    pub program_counter: u8,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct WatchpointHit {
+    /// The watched memory reference, relative to the start of the data region
+    pub idx: u8,
+    pub value: u8,
+    /// Byte offset of the instruction being executed
+    pub program_counter: u8,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct WithSourceLine {
+    pub error: RuntimeError,
+    /// File the line is in, only known if the source was expanded from includes
+    pub filepath: Option<String>,
+    pub line: usize,
+    pub text: String,
+}
+
 #[cfg(test)]
 mod tests {
     use inline_colorization::{color_red, color_reset, style_bold, style_reset};
 
-    use super::RuntimeError;
+    use super::*;
 
     #[test]
     fn test_display_runtime_error() {
         for (input, expected) in [
-            (RuntimeError::ReadPastMemory, "Runtime Error :: Program read past of available memory (perhaps you forgot the 'HALT' instruction?)"),
-            (RuntimeError::OutOfBoundsRead(12), "Runtime Error :: Attempt to read out of bounds memory location 12"),
-            (RuntimeError::OutOfBoundsWrite(127), "Runtime Error :: Attempt to write to out of bounds memory location 127")
+            (
+                RuntimeError::ReadPastMemory(Box::new(ReadPastMemory { program_counter: 9 })),
+                "Runtime Error at byte 9 :: Program read past of available memory (perhaps you forgot the 'HALT' instruction?)",
+            ),
+            (
+                RuntimeError::OutOfBoundsRead(Box::new(OutOfBoundsRead {
+                    idx: 12,
+                    address: 267,
+                    program_counter: 3,
+                })),
+                "Runtime Error at byte 3 :: Attempt to read out of bounds memory location 12 (address 267)",
+            ),
+            (
+                RuntimeError::OutOfBoundsWrite(Box::new(OutOfBoundsWrite {
+                    idx: 127,
+                    address: 300,
+                    program_counter: 40,
+                })),
+                "Runtime Error at byte 40 :: Attempt to write to out of bounds memory location 127 (address 300)",
+            ),
+            (
+                RuntimeError::InvalidOpcode(Box::new(InvalidOpcode {
+                    opcode: 200,
+                    program_counter: 5,
+                })),
+                "Runtime Error at byte 5 :: Invalid opcode 200",
+            ),
+            (
+                RuntimeError::InvalidRegister(Box::new(InvalidRegister {
+                    register: 13,
+                    program_counter: 2,
+                })),
+                "Runtime Error at byte 2 :: Invalid register R13 (must be in range 0-12 inclusive)",
+            ),
+            (
+                RuntimeError::EndOfInput(Box::new(EndOfInput { program_counter: 7 })),
+                "Runtime Error at byte 7 :: Expected input but reached the end of input",
+            ),
+            (
+                RuntimeError::TooManyInvalidInputs(Box::new(TooManyInvalidInputs {
+                    invalid_inputs: 3,
+                    program_counter: 4,
+                })),
+                "Runtime Error at byte 4 :: Gave up on input after 3 invalid entries",
+            ),
+            (
+                RuntimeError::StackOverflow(Box::new(StackOverflow { program_counter: 12 })),
+                "Runtime Error at byte 12 :: Stack overflow, the stack ran into the program",
+            ),
+            (
+                RuntimeError::StackUnderflow(Box::new(StackUnderflow { program_counter: 6 })),
+                "Runtime Error at byte 6 :: Stack underflow, nothing left on the stack to take",
+            ),
+            (
+                RuntimeError::Io(Box::new(Io {
+                    reason: String::from("broken pipe"),
+                    program_counter: 0,
+                })),
+                "Runtime Error at byte 0 :: Input/output failed: broken pipe",
+            ),
+            (
+                RuntimeError::DivideByZero(Box::new(DivideByZero { program_counter: 11 })),
+                "Runtime Error at byte 11 :: Attempt to divide by zero",
+            ),
+            (
+                RuntimeError::CycleLimitExceeded(Box::new(CycleLimitExceeded {
+                    limit: 1000,
+                    program_counter: 4,
+                })),
+                "Runtime Error at byte 4 :: Stopped after 1000 instructions without halting (is there an infinite loop?)",
+            ),
+            (
+                RuntimeError::WatchpointHit(Box::new(WatchpointHit {
+                    idx: 3,
+                    value: 42,
+                    program_counter: 8,
+                })),
+                "Runtime Error at byte 8 :: Stopped at the first write to memory location 3, which wrote 42",
+            ),
         ] {
             assert_eq!(format!("{}", input), format!("{color_red}{style_bold}{expected}{color_reset}{style_reset}"));
         }
     }
+
+    #[test]
+    fn test_display_runtime_error_with_source_line() {
+        let error = RuntimeError::WithSourceLine(Box::new(WithSourceLine {
+            error: RuntimeError::OutOfBoundsRead(Box::new(OutOfBoundsRead {
+                idx: 253,
+                address: 258,
+                program_counter: 1,
+            })),
+            filepath: None,
+            line: 2,
+            text: String::from("    LDR R0, 253"),
+        }));
+        assert_eq!(
+            error.to_string(),
+            format!("{color_red}{style_bold}Runtime Error at byte 1 :: Attempt to read out of bounds memory location 253 (address 258){color_reset}{style_reset}\n{color_red}{style_bold}Line 2 :: LDR R0, 253{color_reset}{style_reset}")
+        );
+        assert_eq!(error.program_counter(), 1);
+
+        // Binaries loaded with a source map know the line but not its text
+        let error = RuntimeError::WithSourceLine(Box::new(WithSourceLine {
+            error: RuntimeError::ReadPastMemory(Box::new(ReadPastMemory { program_counter: 0 })),
+            filepath: None,
+            line: 7,
+            text: String::new(),
+        }));
+        assert!(error
+            .to_string()
+            .ends_with(&format!("{color_red}{style_bold}Line 7{color_reset}{style_reset}")));
+
+        // Lines expanded from includes name the file they were read from
+        let error = RuntimeError::WithSourceLine(Box::new(WithSourceLine {
+            error: RuntimeError::ReadPastMemory(Box::new(ReadPastMemory { program_counter: 0 })),
+            filepath: Some(String::from("lib.aqasm")),
+            line: 3,
+            text: String::from("B end"),
+        }));
+        assert!(error.to_string().ends_with(&format!(
+            "{color_red}{style_bold}lib.aqasm, Line 3 :: B end{color_reset}{style_reset}"
+        )));
+    }
 }
@@ -4,7 +4,7 @@
 /// For example:    
 /// MOV R5, R5 => MOV_REGISTER 5 5    
 /// MOP R5, #5 => MOV_LITERAL 5 5    
-#[allow(non_camel_case_types)]
+#[allow(non_camel_case_types, clippy::upper_case_acronyms)]
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
 pub enum RuntimeOpcode {
     NOP,
@@ -40,6 +40,22 @@ pub enum RuntimeOpcode {
     INPUT_REGISTER,
     INPUT_MEMORY,
     HALT,
+    CPY,
+    PRINTARR,
+    DIV_REGISTER,
+    DIV_LITERAL,
+    MOD_REGISTER,
+    MOD_LITERAL,
+    PUSH,
+    POP,
+    CALL,
+    RET,
+    BCS,
+    BCC,
+    ROL_REGISTER,
+    ROL_LITERAL,
+    ROR_REGISTER,
+    ROR_LITERAL,
 }
 
 impl TryFrom<u8> for RuntimeOpcode {
@@ -80,6 +96,22 @@ impl TryFrom<u8> for RuntimeOpcode {
             x if x == RuntimeOpcode::INPUT_REGISTER as u8 => Ok(RuntimeOpcode::INPUT_REGISTER),
             x if x == RuntimeOpcode::INPUT_MEMORY as u8 => Ok(RuntimeOpcode::INPUT_MEMORY),
             x if x == RuntimeOpcode::HALT as u8 => Ok(RuntimeOpcode::HALT),
+            x if x == RuntimeOpcode::CPY as u8 => Ok(RuntimeOpcode::CPY),
+            x if x == RuntimeOpcode::PRINTARR as u8 => Ok(RuntimeOpcode::PRINTARR),
+            x if x == RuntimeOpcode::DIV_REGISTER as u8 => Ok(RuntimeOpcode::DIV_REGISTER),
+            x if x == RuntimeOpcode::DIV_LITERAL as u8 => Ok(RuntimeOpcode::DIV_LITERAL),
+            x if x == RuntimeOpcode::MOD_REGISTER as u8 => Ok(RuntimeOpcode::MOD_REGISTER),
+            x if x == RuntimeOpcode::MOD_LITERAL as u8 => Ok(RuntimeOpcode::MOD_LITERAL),
+            x if x == RuntimeOpcode::PUSH as u8 => Ok(RuntimeOpcode::PUSH),
+            x if x == RuntimeOpcode::POP as u8 => Ok(RuntimeOpcode::POP),
+            x if x == RuntimeOpcode::CALL as u8 => Ok(RuntimeOpcode::CALL),
+            x if x == RuntimeOpcode::RET as u8 => Ok(RuntimeOpcode::RET),
+            x if x == RuntimeOpcode::BCS as u8 => Ok(RuntimeOpcode::BCS),
+            x if x == RuntimeOpcode::BCC as u8 => Ok(RuntimeOpcode::BCC),
+            x if x == RuntimeOpcode::ROL_REGISTER as u8 => Ok(RuntimeOpcode::ROL_REGISTER),
+            x if x == RuntimeOpcode::ROL_LITERAL as u8 => Ok(RuntimeOpcode::ROL_LITERAL),
+            x if x == RuntimeOpcode::ROR_REGISTER as u8 => Ok(RuntimeOpcode::ROR_REGISTER),
+            x if x == RuntimeOpcode::ROR_LITERAL as u8 => Ok(RuntimeOpcode::ROR_LITERAL),
             _ => Err(()),
         }
     }
@@ -125,11 +157,27 @@ mod tests {
             (30, Ok(RuntimeOpcode::INPUT_REGISTER)),
             (31, Ok(RuntimeOpcode::INPUT_MEMORY)),
             (32, Ok(RuntimeOpcode::HALT)),
+            (33, Ok(RuntimeOpcode::CPY)),
+            (34, Ok(RuntimeOpcode::PRINTARR)),
+            (35, Ok(RuntimeOpcode::DIV_REGISTER)),
+            (36, Ok(RuntimeOpcode::DIV_LITERAL)),
+            (37, Ok(RuntimeOpcode::MOD_REGISTER)),
+            (38, Ok(RuntimeOpcode::MOD_LITERAL)),
+            (39, Ok(RuntimeOpcode::PUSH)),
+            (40, Ok(RuntimeOpcode::POP)),
+            (41, Ok(RuntimeOpcode::CALL)),
+            (42, Ok(RuntimeOpcode::RET)),
+            (43, Ok(RuntimeOpcode::BCS)),
+            (44, Ok(RuntimeOpcode::BCC)),
+            (45, Ok(RuntimeOpcode::ROL_REGISTER)),
+            (46, Ok(RuntimeOpcode::ROL_LITERAL)),
+            (47, Ok(RuntimeOpcode::ROR_REGISTER)),
+            (48, Ok(RuntimeOpcode::ROR_LITERAL)),
         ] {
             assert_eq!(RuntimeOpcode::try_from(input), expected);
         }
 
-        for input in 33..=255 {
+        for input in 49..=255 {
             assert_eq!(RuntimeOpcode::try_from(input), Err(()));
         }
     }
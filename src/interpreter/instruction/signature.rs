@@ -13,11 +13,21 @@ pub static SIGNATURE_TREE: Lazy<SignatureTree> = Lazy::new(|| {
         &[SignatureArgument::Register, SignatureArgument::MemoryRef],
         RuntimeOpcode::LDR,
     );
+    tree.add_signature(
+        SourceOpcode::LDR,
+        &[SignatureArgument::Register, SignatureArgument::Label],
+        RuntimeOpcode::LDR,
+    );
     tree.add_signature(
         SourceOpcode::STR,
         &[SignatureArgument::Register, SignatureArgument::MemoryRef],
         RuntimeOpcode::STR,
     );
+    tree.add_signature(
+        SourceOpcode::STR,
+        &[SignatureArgument::Register, SignatureArgument::Label],
+        RuntimeOpcode::STR,
+    );
     tree.add_signature(
         SourceOpcode::ADD,
         &[
@@ -220,6 +230,130 @@ pub static SIGNATURE_TREE: Lazy<SignatureTree> = Lazy::new(|| {
         &[SignatureArgument::MemoryRef],
         RuntimeOpcode::INPUT_MEMORY,
     );
+    tree.add_signature(
+        SourceOpcode::CPY,
+        &[SignatureArgument::MemoryRef, SignatureArgument::MemoryRef],
+        RuntimeOpcode::CPY,
+    );
+    tree.add_signature(
+        SourceOpcode::PRINTARR,
+        &[SignatureArgument::MemoryRef, SignatureArgument::Literal],
+        RuntimeOpcode::PRINTARR,
+    );
+    tree.add_signature(
+        SourceOpcode::DIV,
+        &[
+            SignatureArgument::Register,
+            SignatureArgument::Register,
+            SignatureArgument::Literal,
+        ],
+        RuntimeOpcode::DIV_LITERAL,
+    );
+    tree.add_signature(
+        SourceOpcode::DIV,
+        &[
+            SignatureArgument::Register,
+            SignatureArgument::Register,
+            SignatureArgument::Register,
+        ],
+        RuntimeOpcode::DIV_REGISTER,
+    );
+    tree.add_signature(
+        SourceOpcode::MOD,
+        &[
+            SignatureArgument::Register,
+            SignatureArgument::Register,
+            SignatureArgument::Literal,
+        ],
+        RuntimeOpcode::MOD_LITERAL,
+    );
+    tree.add_signature(
+        SourceOpcode::MOD,
+        &[
+            SignatureArgument::Register,
+            SignatureArgument::Register,
+            SignatureArgument::Register,
+        ],
+        RuntimeOpcode::MOD_REGISTER,
+    );
+    tree.add_signature(
+        SourceOpcode::PUSH,
+        &[SignatureArgument::Register],
+        RuntimeOpcode::PUSH,
+    );
+    tree.add_signature(
+        SourceOpcode::POP,
+        &[SignatureArgument::Register],
+        RuntimeOpcode::POP,
+    );
+    tree.add_signature(
+        SourceOpcode::CALL,
+        &[SignatureArgument::Label],
+        RuntimeOpcode::CALL,
+    );
+    tree.add_signature(SourceOpcode::RET, &[], RuntimeOpcode::RET);
+    tree.add_signature(
+        SourceOpcode::BCS,
+        &[SignatureArgument::Label],
+        RuntimeOpcode::BCS,
+    );
+    tree.add_signature(
+        SourceOpcode::BCC,
+        &[SignatureArgument::Label],
+        RuntimeOpcode::BCC,
+    );
+    tree.add_signature(
+        SourceOpcode::ROL,
+        &[
+            SignatureArgument::Register,
+            SignatureArgument::Register,
+            SignatureArgument::Literal,
+        ],
+        RuntimeOpcode::ROL_LITERAL,
+    );
+    tree.add_signature(
+        SourceOpcode::ROL,
+        &[
+            SignatureArgument::Register,
+            SignatureArgument::Register,
+            SignatureArgument::Register,
+        ],
+        RuntimeOpcode::ROL_REGISTER,
+    );
+    tree.add_signature(
+        SourceOpcode::ROR,
+        &[
+            SignatureArgument::Register,
+            SignatureArgument::Register,
+            SignatureArgument::Literal,
+        ],
+        RuntimeOpcode::ROR_LITERAL,
+    );
+    tree.add_signature(
+        SourceOpcode::ROR,
+        &[
+            SignatureArgument::Register,
+            SignatureArgument::Register,
+            SignatureArgument::Register,
+        ],
+        RuntimeOpcode::ROR_REGISTER,
+    );
+    // Pseudo-instructions, the parser writes the instruction they expand to
+    tree.add_signature(
+        SourceOpcode::INC,
+        &[SignatureArgument::Register],
+        RuntimeOpcode::ADD_LITERAL,
+    );
+    tree.add_signature(
+        SourceOpcode::DEC,
+        &[SignatureArgument::Register],
+        RuntimeOpcode::SUB_LITERAL,
+    );
+    tree.add_signature(
+        SourceOpcode::LDA,
+        &[SignatureArgument::Register, SignatureArgument::Label],
+        RuntimeOpcode::MOV_LITERAL,
+    );
 
     tree
 });
@@ -243,6 +377,16 @@ impl fmt::Display for SignatureArgument {
     }
 }
 
+/// An operand of the wrong kind, found by [`SignatureTree::find_operand_mismatch`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct OperandMismatch {
+    /// Zero based position of the operand
+    pub index: usize,
+    /// Kinds of operand that would have been valid in this position
+    pub expected: Vec<SignatureArgument>,
+    pub found: SignatureArgument,
+}
+
 #[derive(Debug)]
 pub struct SignatureTreeNode {
     pub runtime_opcode: Option<RuntimeOpcode>,
@@ -258,11 +402,23 @@ impl SignatureTreeNode {
     }
 }
 
+impl Default for SignatureTreeNode {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 #[derive(Debug)]
 pub struct SignatureTree {
     pub root: HashMap<SourceOpcode, SignatureTreeNode>,
 }
 
+impl Default for SignatureTree {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl SignatureTree {
     pub fn new() -> Self {
         SignatureTree {
@@ -280,7 +436,7 @@ impl SignatureTree {
         let root_node = self
             .root
             .entry(source_opcode)
-            .or_insert_with(SignatureTreeNode::new);
+            .or_default();
 
         let mut current_node = root_node;
 
@@ -289,7 +445,7 @@ impl SignatureTree {
             current_node = current_node
                 .children
                 .entry(*arg)
-                .or_insert_with(SignatureTreeNode::new);
+                .or_default();
         }
 
         // Once all arguments are processed, set the runtime opcode at the leaf node
@@ -338,6 +494,83 @@ impl SignatureTree {
         combinations.sort();
         combinations
     }
+
+    /// Valid operand combinations for a source opcode in the order they should be shown
+    /// to users: fewest operands first, then registers before literals before memory
+    /// references before labels. Combinations with the same shape are only listed once.
+    pub fn get_suggested_signatures_for_source_opcode(
+        &self,
+        source_opcode: SourceOpcode,
+    ) -> Vec<Vec<SignatureArgument>> {
+        fn priority(argument: &SignatureArgument) -> u8 {
+            match argument {
+                SignatureArgument::Register => 0,
+                SignatureArgument::Literal => 1,
+                SignatureArgument::MemoryRef => 2,
+                SignatureArgument::Label => 3,
+            }
+        }
+
+        let mut signatures: Vec<Vec<SignatureArgument>> = self
+            .get_all_valid_operand_combinations_for_source_opcode(source_opcode)
+            .into_iter()
+            .map(|(_, signature)| signature)
+            .collect();
+        signatures.sort_by_key(|signature| {
+            (
+                signature.len(),
+                signature.iter().map(priority).collect::<Vec<u8>>(),
+            )
+        });
+        signatures.dedup();
+        signatures
+    }
+
+    /// Find the first operand that does not match any signature for a source opcode,
+    /// following the longest prefix of operands that does. Returns `None` if every
+    /// operand fits, even if there are too few or too many of them.
+    pub fn find_operand_mismatch(
+        &self,
+        source_opcode: SourceOpcode,
+        operands: &[Operand],
+    ) -> Option<OperandMismatch> {
+        let mut current = self.root.get(&source_opcode)?;
+        for (index, operand) in operands.iter().enumerate() {
+            let found = operand.get_signature_argument();
+            match current.children.get(&found) {
+                Some(child) => current = child,
+                None if current.children.is_empty() => return None,
+                None => {
+                    let mut expected: Vec<SignatureArgument> =
+                        current.children.keys().copied().collect();
+                    expected.sort();
+                    return Some(OperandMismatch {
+                        index,
+                        expected,
+                        found,
+                    });
+                }
+            }
+        }
+        None
+    }
+
+    /// Find the source opcode and operands that assemble into a runtime opcode, ignoring
+    /// pseudo-instructions
+    pub fn get_signature_for_runtime_opcode(
+        &self,
+        runtime_opcode: RuntimeOpcode,
+    ) -> Option<(SourceOpcode, Vec<SignatureArgument>)> {
+        self.root
+            .keys()
+            .filter(|opcode| !opcode.is_pseudo())
+            .find_map(|&source_opcode| {
+                self.get_all_valid_operand_combinations_for_source_opcode(source_opcode)
+                    .into_iter()
+                    .find(|(opcode, _)| *opcode == runtime_opcode)
+                    .map(|(_, arguments)| (source_opcode, arguments))
+            })
+    }
 }
 
 #[cfg(test)]
@@ -512,4 +745,94 @@ mod tests {
             ]
         );
     }
+
+    #[test]
+    fn test_get_signature_for_runtime_opcode() {
+        assert_eq!(
+            SIGNATURE_TREE.get_signature_for_runtime_opcode(RuntimeOpcode::ADD_LITERAL),
+            Some((
+                SourceOpcode::ADD,
+                vec![
+                    SignatureArgument::Register,
+                    SignatureArgument::Register,
+                    SignatureArgument::Literal,
+                ]
+            ))
+        );
+        assert_eq!(
+            SIGNATURE_TREE.get_signature_for_runtime_opcode(RuntimeOpcode::HALT),
+            Some((SourceOpcode::HALT, vec![]))
+        );
+    }
+
+    #[test]
+    fn test_find_operand_mismatch() {
+        // ADD R0, 5, #1
+        assert_eq!(
+            SIGNATURE_TREE.find_operand_mismatch(
+                SourceOpcode::ADD,
+                &[Operand::Register(0), Operand::MemoryRef(5), Operand::Literal(1)]
+            ),
+            Some(OperandMismatch {
+                index: 1,
+                expected: vec![SignatureArgument::Register],
+                found: SignatureArgument::MemoryRef,
+            })
+        );
+        // ADD R0, R1, 5
+        assert_eq!(
+            SIGNATURE_TREE.find_operand_mismatch(
+                SourceOpcode::ADD,
+                &[Operand::Register(0), Operand::Register(1), Operand::MemoryRef(5)]
+            ),
+            Some(OperandMismatch {
+                index: 2,
+                expected: vec![SignatureArgument::Register, SignatureArgument::Literal],
+                found: SignatureArgument::MemoryRef,
+            })
+        );
+        // Too many or too few operands is not a mismatch
+        assert_eq!(
+            SIGNATURE_TREE.find_operand_mismatch(SourceOpcode::HALT, &[Operand::Register(0)]),
+            None
+        );
+        assert_eq!(
+            SIGNATURE_TREE.find_operand_mismatch(SourceOpcode::ADD, &[Operand::Register(0)]),
+            None
+        );
+    }
+
+    #[test]
+    fn test_get_suggested_signatures_for_source_opcode() {
+        use SignatureArgument::*;
+        assert_eq!(
+            SIGNATURE_TREE.get_suggested_signatures_for_source_opcode(SourceOpcode::MOV),
+            vec![vec![Register, Register], vec![Register, Literal]]
+        );
+        assert_eq!(
+            SIGNATURE_TREE.get_suggested_signatures_for_source_opcode(SourceOpcode::ADD),
+            vec![
+                vec![Register, Register, Register],
+                vec![Register, Register, Literal]
+            ]
+        );
+        assert_eq!(
+            SIGNATURE_TREE.get_suggested_signatures_for_source_opcode(SourceOpcode::PRINT),
+            vec![vec![Register], vec![MemoryRef]]
+        );
+
+        // Shapes shared by multiple runtime opcodes are listed once
+        let mut tree = SignatureTree::new();
+        tree.add_signature(
+            SourceOpcode::CMP,
+            &[Register, Literal],
+            RuntimeOpcode::CMP_LITERAL,
+        );
+        tree.add_signature(SourceOpcode::CMP, &[Register], RuntimeOpcode::CMP_REGISTER);
+        tree.add_signature(SourceOpcode::CMP, &[Register, Literal], RuntimeOpcode::NOP);
+        assert_eq!(
+            tree.get_suggested_signatures_for_source_opcode(SourceOpcode::CMP),
+            vec![vec![Register], vec![Register, Literal]]
+        );
+    }
 }
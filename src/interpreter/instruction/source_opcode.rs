@@ -1,7 +1,7 @@
 use std::{fmt, str::FromStr};
 
 /// Source opcodes are the opcode literals found in source files
-#[allow(non_camel_case_types)]
+#[allow(non_camel_case_types, clippy::upper_case_acronyms)]
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum SourceOpcode {
     NOP,
@@ -25,6 +25,21 @@ pub enum SourceOpcode {
     PRINT,
     INPUT,
     HALT,
+    CPY,
+    PRINTARR,
+    DIV,
+    MOD,
+    PUSH,
+    POP,
+    CALL,
+    RET,
+    BCS,
+    BCC,
+    ROL,
+    ROR,
+    INC,
+    DEC,
+    LDA,
 }
 
 impl fmt::Display for SourceOpcode {
@@ -51,10 +66,93 @@ impl fmt::Display for SourceOpcode {
             SourceOpcode::PRINT => write!(f, "PRINT"),
             SourceOpcode::INPUT => write!(f, "INPUT"),
             SourceOpcode::HALT => write!(f, "HALT"),
+            SourceOpcode::CPY => write!(f, "CPY"),
+            SourceOpcode::PRINTARR => write!(f, "PRINTARR"),
+            SourceOpcode::DIV => write!(f, "DIV"),
+            SourceOpcode::MOD => write!(f, "MOD"),
+            SourceOpcode::PUSH => write!(f, "PUSH"),
+            SourceOpcode::POP => write!(f, "POP"),
+            SourceOpcode::CALL => write!(f, "CALL"),
+            SourceOpcode::RET => write!(f, "RET"),
+            SourceOpcode::BCS => write!(f, "BCS"),
+            SourceOpcode::BCC => write!(f, "BCC"),
+            SourceOpcode::ROL => write!(f, "ROL"),
+            SourceOpcode::ROR => write!(f, "ROR"),
+            SourceOpcode::INC => write!(f, "INC"),
+            SourceOpcode::DEC => write!(f, "DEC"),
+            SourceOpcode::LDA => write!(f, "LDA"),
         }
     }
 }
 
+impl SourceOpcode {
+    /// One line description of what the instruction does
+    pub fn description(&self) -> &'static str {
+        match self {
+            SourceOpcode::NOP => "Do nothing",
+            SourceOpcode::LDR => "Load the value at a memory reference into a register",
+            SourceOpcode::STR => "Store the value in a register at a memory reference",
+            SourceOpcode::ADD => "Add an operand to a register, storing the result in a register",
+            SourceOpcode::SUB => "Subtract an operand from a register, storing the result in a register",
+            SourceOpcode::MOV => "Copy an operand into a register",
+            SourceOpcode::CMP => "Compare a register with an operand, for use by conditional branches",
+            SourceOpcode::B => "Branch to a label",
+            SourceOpcode::BEQ => "Branch to a label if the last comparison was equal",
+            SourceOpcode::BNE => "Branch to a label if the last comparison was not equal",
+            SourceOpcode::BGT => "Branch to a label if the last comparison was greater than",
+            SourceOpcode::BLT => "Branch to a label if the last comparison was less than",
+            SourceOpcode::AND => "Bitwise AND a register with an operand, storing the result in a register",
+            SourceOpcode::ORR => "Bitwise OR a register with an operand, storing the result in a register",
+            SourceOpcode::EOR => "Bitwise XOR a register with an operand, storing the result in a register",
+            SourceOpcode::MVN => "Bitwise NOT an operand, storing the result in a register",
+            SourceOpcode::LSL => "Shift a register left by an operand, storing the result in a register",
+            SourceOpcode::LSR => "Shift a register right by an operand, storing the result in a register",
+            SourceOpcode::PRINT => "Print the value of a register or memory reference",
+            SourceOpcode::INPUT => "Read a number into a register or memory reference",
+            SourceOpcode::HALT => "Terminate the program",
+            SourceOpcode::CPY => "Copy the value at one memory reference to another",
+            SourceOpcode::PRINTARR => "Print a number of values starting at a memory reference on one line",
+            SourceOpcode::DIV => "Divide a register by an operand, storing the quotient rounded down in a register",
+            SourceOpcode::MOD => "Divide a register by an operand, storing the remainder in a register",
+            SourceOpcode::PUSH => "Push the value of a register onto the stack",
+            SourceOpcode::POP => "Pop the value on top of the stack into a register",
+            SourceOpcode::CALL => "Push the return address onto the stack and branch to a label",
+            SourceOpcode::RET => "Pop a return address off the stack and branch to it",
+            SourceOpcode::BCS => "Branch to a label if the last ADD or SUB carried or borrowed",
+            SourceOpcode::BCC => "Branch to a label if the last ADD or SUB did not carry or borrow",
+            SourceOpcode::ROL => "Rotate a register left by an operand, storing it in a register",
+            SourceOpcode::ROR => "Rotate a register right by an operand, storing it in a register",
+            SourceOpcode::INC => "Add 1 to a register, shorthand for ADD Rn, Rn, #1",
+            SourceOpcode::DEC => "Subtract 1 from a register, shorthand for SUB Rn, Rn, #1",
+            SourceOpcode::LDA => "Load the address of a label into a register",
+        }
+    }
+
+    /// Pseudo-instructions have no runtime opcode of their own, and are expanded into
+    /// another instruction when parsed
+    pub fn is_pseudo(&self) -> bool {
+        matches!(
+            self,
+            SourceOpcode::INC | SourceOpcode::DEC | SourceOpcode::LDA
+        )
+    }
+
+    /// Whether the instruction branches to a label
+    pub fn is_branch(&self) -> bool {
+        matches!(
+            self,
+            SourceOpcode::B
+                | SourceOpcode::BEQ
+                | SourceOpcode::BNE
+                | SourceOpcode::BGT
+                | SourceOpcode::BLT
+                | SourceOpcode::BCS
+                | SourceOpcode::BCC
+                | SourceOpcode::CALL
+        )
+    }
+}
+
 impl FromStr for SourceOpcode {
     type Err = ();
     fn from_str(input: &str) -> Result<SourceOpcode, Self::Err> {
@@ -80,6 +178,21 @@ impl FromStr for SourceOpcode {
             "HALT" => Ok(SourceOpcode::HALT),
             "PRINT" => Ok(SourceOpcode::PRINT),
             "INPUT" => Ok(SourceOpcode::INPUT),
+            "CPY" => Ok(SourceOpcode::CPY),
+            "PRINTARR" => Ok(SourceOpcode::PRINTARR),
+            "DIV" => Ok(SourceOpcode::DIV),
+            "MOD" => Ok(SourceOpcode::MOD),
+            "PUSH" => Ok(SourceOpcode::PUSH),
+            "POP" => Ok(SourceOpcode::POP),
+            "CALL" => Ok(SourceOpcode::CALL),
+            "RET" => Ok(SourceOpcode::RET),
+            "BCS" => Ok(SourceOpcode::BCS),
+            "BCC" => Ok(SourceOpcode::BCC),
+            "ROL" => Ok(SourceOpcode::ROL),
+            "ROR" => Ok(SourceOpcode::ROR),
+            "INC" => Ok(SourceOpcode::INC),
+            "DEC" => Ok(SourceOpcode::DEC),
+            "LDA" => Ok(SourceOpcode::LDA),
             _ => Err(()),
         }
     }
@@ -116,6 +229,21 @@ mod tests {
             ("HALT", Ok(SourceOpcode::HALT)),
             ("PRINT", Ok(SourceOpcode::PRINT)),
             ("INPUT", Ok(SourceOpcode::INPUT)),
+            ("CPY", Ok(SourceOpcode::CPY)),
+            ("PRINTARR", Ok(SourceOpcode::PRINTARR)),
+            ("DIV", Ok(SourceOpcode::DIV)),
+            ("MOD", Ok(SourceOpcode::MOD)),
+            ("PUSH", Ok(SourceOpcode::PUSH)),
+            ("POP", Ok(SourceOpcode::POP)),
+            ("CALL", Ok(SourceOpcode::CALL)),
+            ("RET", Ok(SourceOpcode::RET)),
+            ("BCS", Ok(SourceOpcode::BCS)),
+            ("BCC", Ok(SourceOpcode::BCC)),
+            ("ROL", Ok(SourceOpcode::ROL)),
+            ("ROR", Ok(SourceOpcode::ROR)),
+            ("INC", Ok(SourceOpcode::INC)),
+            ("DEC", Ok(SourceOpcode::DEC)),
+            ("LDA", Ok(SourceOpcode::LDA)),
             // lowercase commands shouldn't work
             ("nop", Err(())),
             ("input", Err(())),
@@ -156,6 +284,20 @@ mod tests {
             (SourceOpcode::PRINT, "PRINT"),
             (SourceOpcode::INPUT, "INPUT"),
             (SourceOpcode::HALT, "HALT"),
+            (SourceOpcode::CPY, "CPY"),
+            (SourceOpcode::PRINTARR, "PRINTARR"),
+            (SourceOpcode::DIV, "DIV"),
+            (SourceOpcode::MOD, "MOD"),
+            (SourceOpcode::PUSH, "PUSH"),
+            (SourceOpcode::POP, "POP"),
+            (SourceOpcode::CALL, "CALL"),
+            (SourceOpcode::RET, "RET"),
+            (SourceOpcode::BCS, "BCS"),
+            (SourceOpcode::BCC, "BCC"),
+            (SourceOpcode::ROL, "ROL"),
+            (SourceOpcode::ROR, "ROR"),
+            (SourceOpcode::INC, "INC"),
+            (SourceOpcode::DEC, "DEC"),
         ];
 
         for (opcode, expected) in test_cases.iter() {
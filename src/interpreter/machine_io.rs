@@ -0,0 +1,233 @@
+use super::{format_value, InputMode};
+use std::{
+    fmt,
+    io::{self, BufRead, Write},
+};
+
+/// Where a running program takes its input from and sends its output to
+pub trait MachineIo {
+    /// Read the next value for INPUT, returning `None` once the input has been exhausted.
+    /// An entry that is not a valid value is reported by returning [`InvalidInput`] as an
+    /// [`io::ErrorKind::InvalidData`] error, and the interpreter will read again.
+    fn read_u8(&mut self) -> io::Result<Option<u8>>;
+    /// Output a value from PRINT
+    fn write_value(&mut self, value: u8) -> io::Result<()>;
+    /// Output the values from PRINTARR, by default as if each was printed on its own
+    fn write_values(&mut self, values: &[u8]) -> io::Result<()> {
+        values.iter().try_for_each(|&value| self.write_value(value))
+    }
+}
+
+impl<T: MachineIo + ?Sized> MachineIo for &mut T {
+    fn read_u8(&mut self) -> io::Result<Option<u8>> {
+        (**self).read_u8()
+    }
+
+    fn write_value(&mut self, value: u8) -> io::Result<()> {
+        (**self).write_value(value)
+    }
+
+    fn write_values(&mut self, values: &[u8]) -> io::Result<()> {
+        (**self).write_values(values)
+    }
+}
+
+/// Input entry that could not be read as a value
+#[derive(Debug)]
+pub struct InvalidInput {
+    pub text: String,
+    /// How the entry was read
+    pub mode: InputMode,
+}
+
+impl InvalidInput {
+    /// Whether an error returned by [`MachineIo::read_u8`] is an invalid entry
+    pub fn is_invalid_input(err: &io::Error) -> bool {
+        err.get_ref()
+            .is_some_and(|inner| inner.is::<InvalidInput>())
+    }
+}
+
+impl std::error::Error for InvalidInput {}
+
+impl fmt::Display for InvalidInput {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.mode {
+            InputMode::Number => write!(f, "'{}' is not a number in the range 0-255", self.text),
+            InputMode::Char => write!(
+                f,
+                "'{}' does not start with a character with a code in the range 0-255",
+                self.text
+            ),
+        }
+    }
+}
+
+/// Options that change how [`StreamIo`] reads input and writes output
+#[derive(Debug, Clone, Default)]
+pub struct StreamOptions {
+    /// End output lines with "\r\n" instead of "\n"
+    pub crlf_output: bool,
+    /// Print values as signed two's complement, so 251 is printed as -5
+    pub signed_display: bool,
+    /// How INPUT turns a line of input into a value
+    pub input_mode: InputMode,
+    /// Text written before each line of output, so it stands out in a combined log
+    pub print_prefix: String,
+}
+
+/// [`MachineIo`] over a line based text stream. Each value is read from its own line of
+/// input according to the [`InputMode`], and each value is printed on its own line of
+/// output.
+#[derive(Debug)]
+pub struct StreamIo<R: BufRead, W: Write> {
+    reader: R,
+    writer: W,
+    options: StreamOptions,
+}
+
+impl<R: BufRead, W: Write> StreamIo<R, W> {
+    pub fn new(reader: R, writer: W) -> Self {
+        Self {
+            reader,
+            writer,
+            options: StreamOptions::default(),
+        }
+    }
+
+    pub fn with_options(mut self, options: StreamOptions) -> Self {
+        self.options = options;
+        self
+    }
+
+    /// Read a line of input, returning `None` once the input has been exhausted
+    pub fn read_line(&mut self) -> io::Result<Option<String>> {
+        let mut input = String::new();
+        if self.reader.read_line(&mut input)? == 0 {
+            return Ok(None);
+        }
+        Ok(Some(input.trim_end().to_string()))
+    }
+
+    pub fn write_line(&mut self, output: &str) -> io::Result<()> {
+        let newline: &[u8] = if self.options.crlf_output {
+            b"\r\n"
+        } else {
+            b"\n"
+        };
+        self.writer
+            .write_all(self.options.print_prefix.as_bytes())?;
+        self.writer.write_all(output.as_bytes())?;
+        self.writer.write_all(newline)?;
+        self.writer.flush()
+    }
+}
+
+impl<R: BufRead, W: Write> MachineIo for StreamIo<R, W> {
+    fn read_u8(&mut self) -> io::Result<Option<u8>> {
+        if self.options.input_mode == InputMode::Char {
+            // An empty line is read as the newline character itself
+            let mut input = Vec::new();
+            if self.reader.read_until(b'\n', &mut input)? == 0 {
+                return Ok(None);
+            }
+            let input = String::from_utf8_lossy(&input);
+            return match input.chars().next().map(u8::try_from) {
+                Some(Ok(value)) => Ok(Some(value)),
+                _ => Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    InvalidInput {
+                        text: input.trim_end().to_string(),
+                        mode: InputMode::Char,
+                    },
+                )),
+            };
+        }
+        let Some(input) = self.read_line()? else {
+            return Ok(None);
+        };
+        match input.trim().parse() {
+            Ok(value) => Ok(Some(value)),
+            Err(_) => Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                InvalidInput {
+                    text: input,
+                    mode: InputMode::Number,
+                },
+            )),
+        }
+    }
+
+    fn write_value(&mut self, value: u8) -> io::Result<()> {
+        self.write_line(&format_value(value, self.options.signed_display))
+    }
+
+    /// Values are printed on a single line, separated by spaces
+    fn write_values(&mut self, values: &[u8]) -> io::Result<()> {
+        let values: Vec<String> = values
+            .iter()
+            .map(|&value| format_value(value, self.options.signed_display))
+            .collect();
+        self.write_line(&values.join(" "))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stream_io_read_u8() {
+        let mut io = StreamIo::new(&b"12\nnope\n 7 \n256\n"[..], io::sink());
+        assert_eq!(io.read_u8().unwrap(), Some(12));
+        assert!(InvalidInput::is_invalid_input(&io.read_u8().unwrap_err()));
+        assert_eq!(io.read_u8().unwrap(), Some(7));
+        assert!(InvalidInput::is_invalid_input(&io.read_u8().unwrap_err()));
+        assert_eq!(io.read_u8().unwrap(), None);
+    }
+
+    #[test]
+    fn test_stream_io_read_char() {
+        let input = "A\n\n\u{e9}t\u{e9}\n\u{20ac}\n";
+        let mut io = StreamIo::new(input.as_bytes(), io::sink()).with_options(StreamOptions {
+            input_mode: InputMode::Char,
+            ..Default::default()
+        });
+        assert_eq!(io.read_u8().unwrap(), Some(b'A'));
+        assert_eq!(io.read_u8().unwrap(), Some(b'\n'));
+        // The first character, rather than the first byte of its UTF-8 encoding
+        assert_eq!(io.read_u8().unwrap(), Some(0xE9));
+        let err = io.read_u8().unwrap_err();
+        assert!(InvalidInput::is_invalid_input(&err));
+        assert_eq!(
+            err.to_string(),
+            "'\u{20ac}' does not start with a character with a code in the range 0-255"
+        );
+        assert_eq!(io.read_u8().unwrap(), None);
+    }
+
+    #[test]
+    fn test_stream_io_write_value() {
+        let mut output = Vec::new();
+        let mut io = StreamIo::new(&b""[..], &mut output).with_options(StreamOptions {
+            crlf_output: true,
+            signed_display: true,
+            ..Default::default()
+        });
+        io.write_value(255).unwrap();
+        io.write_value(5).unwrap();
+        assert_eq!(output, b"-1\r\n5\r\n");
+    }
+
+    #[test]
+    fn test_stream_io_print_prefix() {
+        let mut output = Vec::new();
+        let mut io = StreamIo::new(&b""[..], &mut output).with_options(StreamOptions {
+            print_prefix: String::from("OUT: "),
+            ..Default::default()
+        });
+        io.write_value(5).unwrap();
+        io.write_values(&[1, 2, 3]).unwrap();
+        assert_eq!(output, b"OUT: 5\nOUT: 1 2 3\n");
+    }
+}
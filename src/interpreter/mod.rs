@@ -2,25 +2,164 @@ mod error;
 pub use error::*;
 use instruction::runtime_opcode::RuntimeOpcode;
 pub mod instruction;
-
-use std::io::{self, BufRead, BufReader, Write};
+mod machine_io;
+pub use machine_io::*;
+
+use crate::{
+    analysis::decode_instruction,
+    disassembler::format_instruction,
+    include::LineOrigins,
+    parser::LineTable,
+    profile::{CostModel, Profile},
+};
+use std::{
+    fmt,
+    io::{self, BufRead, BufReader, Write},
+    str::FromStr,
+};
 
 // We get a predefined amount of registers allocated
 pub const REGISTER_COUNT: u8 = 13;
 
+/// Largest program that can be loaded, so that there is always at least one byte of data
+/// memory and the program size fits in a u8
+pub const MAX_PROGRAM_BYTES: u8 = u8::MAX;
+
+/// Source code a program was assembled from, used to point runtime errors at the
+/// line that caused them
+#[derive(Debug, Clone, Copy)]
+pub struct ProgramSource<'a> {
+    pub source: &'a str,
+    pub line_table: &'a LineTable,
+    /// Where each line of the source was read from, if it was expanded from includes
+    pub origins: &'a LineOrigins,
+}
+
+/// Where trace lines are written as the program runs, see [`Interpreter::with_trace`]
+struct TraceWriter<'a>(&'a mut dyn Write);
+
+impl fmt::Debug for TraceWriter<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("TraceWriter")
+    }
+}
+
+impl ProgramSource<'_> {
+    /// Find the line number and text of the instruction at a byte offset. The text is
+    /// empty if only the line table is known, such as for a binary with a source map.
+    pub fn get_line(&self, program_counter: u8) -> Option<(usize, &str)> {
+        let (_, &line) = self.line_table.range(..=program_counter).next_back()?;
+        let text = self.source.lines().nth(line.checked_sub(1)?).unwrap_or("");
+        Some((line, text))
+    }
+}
+
+/// Options that change how a program behaves while running
+#[derive(Debug, Clone, Default)]
+pub struct InterpreterOptions {
+    /// How input is read and output is written, only used with [`StreamIo`]
+    pub stream: StreamOptions,
+    /// Invalid entries a single INPUT reads again after before failing, instead of reading
+    /// forever. 0 fails on the first invalid entry.
+    pub input_retries: Option<u32>,
+    /// Stop the program the first time it writes to this memory reference
+    pub break_on_write: Option<u8>,
+    /// Stop the program once it has executed this many instructions without halting
+    pub cycle_limit: Option<u64>,
+}
+
+/// Whether the program can carry on after executing some instructions
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum StepResult {
+    /// The program has not halted yet
+    Continue,
+    /// The last instruction executed was a HALT
+    Halted,
+}
+
+/// How INPUT turns a line of input into a value
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum InputMode {
+    /// Parse the line as a decimal number, skipping lines that are not one
+    #[default]
+    Number,
+    /// Store the code of the first character of the line, skipping lines where it is
+    /// above 255
+    Char,
+}
+
+impl fmt::Display for InputMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            InputMode::Number => write!(f, "number"),
+            InputMode::Char => write!(f, "char"),
+        }
+    }
+}
+
+impl FromStr for InputMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "number" => Ok(InputMode::Number),
+            "char" => Ok(InputMode::Char),
+            _ => Err(format!("unknown input mode '{s}', expected 'number' or 'char'")),
+        }
+    }
+}
+
+/// Format a byte for display, as signed two's complement if `signed` is set
+pub fn format_value(value: u8, signed: bool) -> String {
+    if signed {
+        (value as i8).to_string()
+    } else {
+        value.to_string()
+    }
+}
+
 #[derive(Debug)]
-pub struct Interpreter<'a, R: BufRead, W: Write> {
+pub struct Interpreter<'a, IO: MachineIo> {
     memory: &'a mut [u8; 256],
     registers: &'a mut [u8; REGISTER_COUNT as usize],
     program_bytes: u8,
     program_counter: u8,
+    /// Byte offset of the instruction currently being executed
+    instruction_start: u8,
     comparison_result: u8,
     underflow: bool,
-    reader: R,
-    writer: W,
+    /// Set when the last ADD overflowed or the last SUB borrowed
+    carry: bool,
+    /// Number of instructions executed so far
+    instruction_count: u64,
+    /// Data cells that have been written to, indexed relative to the data region
+    written_cells: [bool; 256],
+    /// Address of the next free byte on the stack, which starts at the end of memory and
+    /// grows down towards the program
+    stack_pointer: u8,
+    /// Most values the stack has held at once
+    max_stack_depth: u8,
+    /// Set once a HALT has executed, after which running or stepping does nothing
+    halted: bool,
+    /// Only collected when profiling is enabled
+    profile: Option<Profile>,
+    /// Byte offset of the last CMP executed, which set the comparison flags
+    last_cmp_pc: Option<u8>,
+    /// Whether the last conditional branch executed was taken
+    last_branch_taken: Option<bool>,
+    /// Written to with one line per executed instruction when tracing is enabled
+    trace: Option<TraceWriter<'a>>,
+    /// Invalid input entries allowed for each INPUT, unlimited if `None`
+    input_retries: Option<u32>,
+    /// Memory reference that stops the program as soon as it is written to
+    break_on_write: Option<u8>,
+    /// Instructions allowed before the program is stopped, unlimited if `None`
+    cycle_limit: Option<u64>,
+    source: Option<ProgramSource<'a>>,
+    io: IO,
 }
 
-impl<'a> Interpreter<'a, BufReader<io::Stdin>, io::Stdout> {
+impl<'a> Interpreter<'a, StreamIo<BufReader<io::Stdin>, io::Stdout>> {
     pub fn interpret(
         memory: &'a mut [u8; 256],
         registers: &'a mut [u8; REGISTER_COUNT as usize],
@@ -32,7 +171,7 @@ impl<'a> Interpreter<'a, BufReader<io::Stdin>, io::Stdout> {
     }
 } 
 
-impl<'a, R: BufRead, W: Write> Interpreter<'a, R, W> {
+impl<'a, R: BufRead, W: Write> Interpreter<'a, StreamIo<R, W>> {
     pub fn interpret_custom_io(
         memory: &'a mut [u8; 256],
         registers: &'a mut [u8; REGISTER_COUNT as usize],
@@ -40,102 +179,418 @@ impl<'a, R: BufRead, W: Write> Interpreter<'a, R, W> {
         reader: R,
         writer: W,
     ) -> Result<Self, RuntimeError> {
-        let mut interpreter = Interpreter {
+        let mut interpreter =
+            Interpreter::new(memory, registers, program_bytes, StreamIo::new(reader, writer));
+        interpreter.run()?;
+        Ok(interpreter)
+    }
+}
+
+impl<'a, IO: MachineIo> Interpreter<'a, IO> {
+    /// Create an interpreter for a loaded program without running it
+    pub fn new(
+        memory: &'a mut [u8; 256],
+        registers: &'a mut [u8; REGISTER_COUNT as usize],
+        program_bytes: u8,
+        io: IO,
+    ) -> Self {
+        Interpreter {
             memory,
             registers,
             program_bytes,
             program_counter: 0,
+            instruction_start: 0,
             comparison_result: 0,
             underflow: false,
-            reader,
-            writer,
-        };
+            carry: false,
+            instruction_count: 0,
+            written_cells: [false; 256],
+            stack_pointer: u8::MAX,
+            max_stack_depth: 0,
+            halted: false,
+            profile: None,
+            last_cmp_pc: None,
+            last_branch_taken: None,
+            trace: None,
+            input_retries: None,
+            break_on_write: None,
+            cycle_limit: None,
+            source: None,
+            io,
+        }
+    }
 
-        interpreter.internal_interpret()?;
-        Ok(interpreter)
+    /// Attach the source code the program was assembled from, so that runtime errors
+    /// include the offending line
+    pub fn with_source(mut self, source: ProgramSource<'a>) -> Self {
+        self.source = Some(source);
+        self
     }
 
-    pub fn read_line(&mut self) -> String {
-        let mut input = String::new();
-        self.reader
-            .read_line(&mut input)
-            .expect("Failed to read line");
-        input.trim_end().to_string()
+    /// Start with the comparison flags already set, as if a CMP had run, so conditional
+    /// branches can be exercised on their own
+    pub fn with_flags(mut self, comparison_result: u8, underflow: bool) -> Self {
+        self.comparison_result = comparison_result;
+        self.underflow = underflow;
+        self
     }
 
-    pub fn write_line(&mut self, output: &str) {
-        self.writer
-            .write_all(output.as_bytes())
-            .expect("Failed to write line");
-        self.writer
-            .write_all(b"\n")
-            .expect("Failed to write newline");
-        self.writer.flush().expect("Failed to flush writer");
+    /// Count instructions and cycles by opcode while running
+    pub fn with_profile(mut self, cost_model: CostModel) -> Self {
+        self.profile = Some(Profile::new(cost_model));
+        self
     }
 
-    fn internal_interpret(&mut self) -> Result<(), RuntimeError> {
-        loop {
-            let instruction = self.read_next_memory_address()?;
+    pub fn profile(&self) -> Option<&Profile> {
+        self.profile.as_ref()
+    }
 
-            let opcode: RuntimeOpcode = match instruction.try_into() {
-                Ok(opcode) => opcode,
-                Err(_) => panic!(
-                    "Invalid opcode found while running program, please report as bug to author!"
-                ),
-            };
+    /// Write a line to `writer` for every instruction executed while running. The writer
+    /// is flushed before each PRINT, PRINTARR and INPUT so the trace keeps up with the
+    /// program's output, and again once the program stops.
+    pub fn with_trace(mut self, writer: &'a mut dyn Write) -> Self {
+        self.trace = Some(TraceWriter(writer));
+        self
+    }
+
+    /// Fail with [`RuntimeError::TooManyInvalidInputs`] once a single INPUT has read more
+    /// than `retries` invalid entries
+    pub fn with_input_retries(mut self, retries: u32) -> Self {
+        self.input_retries = Some(retries);
+        self
+    }
+
+    /// Stop with [`RuntimeError::WatchpointHit`] the first time the program writes to the
+    /// memory reference `idx`, relative to the start of the data region
+    pub fn with_break_on_write(mut self, idx: u8) -> Self {
+        self.break_on_write = Some(idx);
+        self
+    }
+
+    /// Fail with [`RuntimeError::CycleLimitExceeded`] instead of executing any more
+    /// instructions once `limit` have been executed. The HALT that ends the program is not
+    /// counted, so a program that halts after exactly `limit` instructions succeeds.
+    pub fn with_cycle_limit(mut self, limit: u64) -> Self {
+        self.cycle_limit = Some(limit);
+        self
+    }
+
+    /// Number of instructions executed so far, including the final HALT
+    pub fn instruction_count(&self) -> u64 {
+        self.instruction_count
+    }
+
+    /// Number of distinct data cells written to so far
+    pub fn written_cell_count(&self) -> usize {
+        self.written_cells.iter().filter(|&&written| written).count()
+    }
+
+    /// Address the next PUSH writes to, 255 while the stack is empty
+    pub fn stack_pointer(&self) -> u8 {
+        self.stack_pointer
+    }
+
+    /// Deepest the stack has grown so far, in bytes. Stays at 0 for programs that never
+    /// use the stack.
+    pub fn max_stack_depth(&self) -> u8 {
+        self.max_stack_depth
+    }
+
+    /// Byte offset of the next instruction to execute
+    pub fn program_counter(&self) -> u8 {
+        self.program_counter
+    }
 
-            match opcode {
-                RuntimeOpcode::NOP => {}
-                RuntimeOpcode::LDR => self.interpret_ldr()?,
-                RuntimeOpcode::STR => self.interpret_str()?,
-                RuntimeOpcode::ADD_REGISTER => self.interpret_add_register()?,
-                RuntimeOpcode::ADD_LITERAL => self.interpret_add_literal()?,
-                RuntimeOpcode::SUB_REGISTER => self.interpret_sub_register()?,
-                RuntimeOpcode::SUB_LITERAL => self.interpret_sub_literal()?,
-                RuntimeOpcode::MOV_REGISTER => self.interpret_mov_register()?,
-                RuntimeOpcode::MOV_LITERAL => self.interpret_mov_literal()?,
-                RuntimeOpcode::CMP_REGISTER => self.interpret_cmp_register()?,
-                RuntimeOpcode::CMP_LITERAL => self.interpret_cmp_literal()?,
-                RuntimeOpcode::B => self.interpret_b()?,
-                RuntimeOpcode::BEQ => self.interpret_beq()?,
-                RuntimeOpcode::BNE => self.interpret_bne()?,
-                RuntimeOpcode::BGT => self.interpret_bgt()?,
-                RuntimeOpcode::BLT => self.interpret_blt()?,
-                RuntimeOpcode::AND_REGISTER => self.interpret_and_register()?,
-                RuntimeOpcode::AND_LITERAL => self.interpret_and_literal()?,
-                RuntimeOpcode::ORR_REGISTER => self.interpret_orr_register()?,
-                RuntimeOpcode::ORR_LITERAL => self.interpret_orr_literal()?,
-                RuntimeOpcode::EOR_REGISTER => self.interpret_eor_register()?,
-                RuntimeOpcode::EOR_LITERAL => self.interpret_eor_literal()?,
-                RuntimeOpcode::MVN_REGISTER => self.interpret_mvn_register()?,
-                RuntimeOpcode::MVN_LITERAL => self.interpret_mvn_literal()?,
-                RuntimeOpcode::LSL_REGISTER => self.interpret_lsl_register()?,
-                RuntimeOpcode::LSL_LITERAL => self.interpret_lsl_literal()?,
-                RuntimeOpcode::LSR_REGISTER => self.interpret_lsr_register()?,
-                RuntimeOpcode::LSR_LITERAL => self.interpret_lsr_literal()?,
-                RuntimeOpcode::PRINT_REGISTER => self.interpret_print_register()?,
-                RuntimeOpcode::PRINT_MEMORY => self.interpret_print_memory()?,
-                RuntimeOpcode::INPUT_REGISTER => self.interpret_input_register()?,
-                RuntimeOpcode::INPUT_MEMORY => self.interpret_input_memory()?,
-                RuntimeOpcode::HALT => break,
+    pub fn registers(&self) -> &[u8; REGISTER_COUNT as usize] {
+        self.registers
+    }
+
+    pub fn memory(&self) -> &[u8; 256] {
+        self.memory
+    }
+
+    /// First operand minus the second operand of the last CMP, wrapping on underflow.
+    /// Zero means the operands were equal.
+    ///
+    /// ```
+    /// use aqa_asm::interpreter::{
+    ///     instruction::runtime_opcode::RuntimeOpcode, Interpreter, StreamIo, REGISTER_COUNT,
+    /// };
+    /// use std::io;
+    ///
+    /// // CMP R0, #3 then HALT, with R0 holding 5
+    /// let mut memory = [0; 256];
+    /// memory[..4].copy_from_slice(&[RuntimeOpcode::CMP_LITERAL as u8, 0, 3, RuntimeOpcode::HALT as u8]);
+    /// let mut registers = [0; REGISTER_COUNT as usize];
+    /// registers[0] = 5;
+    /// let mut interpreter =
+    ///     Interpreter::new(&mut memory, &mut registers, 4, StreamIo::new(io::empty(), io::sink()));
+    /// interpreter.run().unwrap();
+    /// assert_eq!(interpreter.comparison_result(), 2);
+    /// assert!(!interpreter.underflow());
+    /// assert_eq!(interpreter.program_counter(), 4);
+    /// ```
+    pub fn comparison_result(&self) -> u8 {
+        self.comparison_result
+    }
+
+    /// Whether the second operand of the last CMP was larger than the first
+    pub fn underflow(&self) -> bool {
+        self.underflow
+    }
+
+    /// Whether the last ADD overflowed past 255 or the last SUB went below 0
+    pub fn carry(&self) -> bool {
+        self.carry
+    }
+
+    /// Run the program until it halts
+    pub fn run(&mut self) -> Result<(), RuntimeError> {
+        if self.halted {
+            return Ok(());
+        }
+        let result = self.internal_interpret();
+        // A failed flush is only reported if the program itself succeeded
+        result
+            .and(self.flush_trace())
+            .map_err(|err| self.attach_source_line(err))
+    }
+
+    /// Execute a single instruction. Once the program has halted, nothing more is executed
+    /// and every step returns [`StepResult::Halted`].
+    pub fn step(&mut self) -> Result<StepResult, RuntimeError> {
+        if self.halted {
+            return Ok(StepResult::Halted);
+        }
+        self.internal_step()
+            .map_err(|err| self.attach_source_line(err))
+    }
+
+    /// Execute up to `n` instructions, stopping early if the program halts
+    pub fn run_n(&mut self, n: u64) -> Result<StepResult, RuntimeError> {
+        if self.halted {
+            return Ok(StepResult::Halted);
+        }
+        for _ in 0..n {
+            if self.step()? == StepResult::Halted {
+                return Ok(StepResult::Halted);
             }
         }
+        Ok(StepResult::Continue)
+    }
+
+    fn attach_source_line(&self, err: RuntimeError) -> RuntimeError {
+        let Some(source) = self.source else {
+            return err;
+        };
+        let Some((line, text)) = source.get_line(err.program_counter()) else {
+            return err;
+        };
+        let (filepath, line) = match source.origins.get(line) {
+            Some(origin) => (Some(origin.filepath.clone()), origin.line),
+            None => (None, line),
+        };
+        RuntimeError::WithSourceLine(Box::new(WithSourceLine {
+            error: err,
+            filepath,
+            line,
+            text: text.to_string(),
+        }))
+    }
+
+    fn io_error(&self, err: io::Error) -> RuntimeError {
+        RuntimeError::Io(Box::new(Io {
+            reason: err.to_string(),
+            program_counter: self.instruction_start,
+        }))
+    }
+
+    fn flush_trace(&mut self) -> Result<(), RuntimeError> {
+        let Some(trace) = &mut self.trace else {
+            return Ok(());
+        };
+        let result = trace.0.flush();
+        result.map_err(|err| self.io_error(err))
+    }
+
+    fn internal_interpret(&mut self) -> Result<(), RuntimeError> {
+        while self.internal_step()? == StepResult::Continue {}
         Ok(())
     }
 
-    fn read_next_memory_address(&mut self) -> Result<u8, RuntimeError> {
-        if self.program_counter >= self.program_bytes {
-            return Err(RuntimeError::ReadPastMemory);
+    fn internal_step(&mut self) -> Result<StepResult, RuntimeError> {
+        self.instruction_start = self.program_counter;
+        let instruction = self.read_next_memory_address()?;
+
+        let opcode: RuntimeOpcode = match instruction.try_into() {
+            Ok(opcode) => opcode,
+            Err(_) => {
+                return Err(RuntimeError::InvalidOpcode(Box::new(InvalidOpcode {
+                    opcode: instruction,
+                    program_counter: self.instruction_start,
+                })))
+            }
+        };
+        if opcode != RuntimeOpcode::HALT && Some(self.instruction_count) == self.cycle_limit {
+            return Err(RuntimeError::CycleLimitExceeded(Box::new(
+                CycleLimitExceeded {
+                    limit: self.instruction_count,
+                    program_counter: self.instruction_start,
+                },
+            )));
         }
+        self.instruction_count += 1;
+        if let Some(profile) = &mut self.profile {
+            profile.record(opcode);
+        }
+        // The trace shows the registers as they were when the instruction started
+        let registers = self.trace.is_some().then_some(*self.registers);
+        // Catch the trace up before the program prints or waits for input
+        let does_io = matches!(
+            opcode,
+            RuntimeOpcode::PRINT_REGISTER
+                | RuntimeOpcode::PRINT_MEMORY
+                | RuntimeOpcode::PRINTARR
+                | RuntimeOpcode::INPUT_REGISTER
+                | RuntimeOpcode::INPUT_MEMORY
+        );
+        if does_io {
+            self.flush_trace()?;
+        }
+
+        match opcode {
+            RuntimeOpcode::NOP => {}
+            RuntimeOpcode::LDR => self.interpret_ldr()?,
+            RuntimeOpcode::STR => self.interpret_str()?,
+            RuntimeOpcode::ADD_REGISTER => self.interpret_add_register()?,
+            RuntimeOpcode::ADD_LITERAL => self.interpret_add_literal()?,
+            RuntimeOpcode::SUB_REGISTER => self.interpret_sub_register()?,
+            RuntimeOpcode::SUB_LITERAL => self.interpret_sub_literal()?,
+            RuntimeOpcode::MOV_REGISTER => self.interpret_mov_register()?,
+            RuntimeOpcode::MOV_LITERAL => self.interpret_mov_literal()?,
+            RuntimeOpcode::CMP_REGISTER => self.interpret_cmp_register()?,
+            RuntimeOpcode::CMP_LITERAL => self.interpret_cmp_literal()?,
+            RuntimeOpcode::B => self.interpret_b()?,
+            RuntimeOpcode::BEQ => self.interpret_beq()?,
+            RuntimeOpcode::BNE => self.interpret_bne()?,
+            RuntimeOpcode::BGT => self.interpret_bgt()?,
+            RuntimeOpcode::BLT => self.interpret_blt()?,
+            RuntimeOpcode::AND_REGISTER => self.interpret_and_register()?,
+            RuntimeOpcode::AND_LITERAL => self.interpret_and_literal()?,
+            RuntimeOpcode::ORR_REGISTER => self.interpret_orr_register()?,
+            RuntimeOpcode::ORR_LITERAL => self.interpret_orr_literal()?,
+            RuntimeOpcode::EOR_REGISTER => self.interpret_eor_register()?,
+            RuntimeOpcode::EOR_LITERAL => self.interpret_eor_literal()?,
+            RuntimeOpcode::MVN_REGISTER => self.interpret_mvn_register()?,
+            RuntimeOpcode::MVN_LITERAL => self.interpret_mvn_literal()?,
+            RuntimeOpcode::LSL_REGISTER => self.interpret_lsl_register()?,
+            RuntimeOpcode::LSL_LITERAL => self.interpret_lsl_literal()?,
+            RuntimeOpcode::LSR_REGISTER => self.interpret_lsr_register()?,
+            RuntimeOpcode::LSR_LITERAL => self.interpret_lsr_literal()?,
+            RuntimeOpcode::PRINT_REGISTER => self.interpret_print_register()?,
+            RuntimeOpcode::PRINT_MEMORY => self.interpret_print_memory()?,
+            RuntimeOpcode::INPUT_REGISTER => self.interpret_input_register()?,
+            RuntimeOpcode::INPUT_MEMORY => self.interpret_input_memory()?,
+            RuntimeOpcode::HALT => {}
+            RuntimeOpcode::CPY => self.interpret_cpy()?,
+            RuntimeOpcode::PRINTARR => self.interpret_printarr()?,
+            RuntimeOpcode::DIV_REGISTER => self.interpret_div_register()?,
+            RuntimeOpcode::DIV_LITERAL => self.interpret_div_literal()?,
+            RuntimeOpcode::MOD_REGISTER => self.interpret_mod_register()?,
+            RuntimeOpcode::MOD_LITERAL => self.interpret_mod_literal()?,
+            RuntimeOpcode::PUSH => self.interpret_push()?,
+            RuntimeOpcode::POP => self.interpret_pop()?,
+            RuntimeOpcode::CALL => self.interpret_call()?,
+            RuntimeOpcode::RET => self.interpret_ret()?,
+            RuntimeOpcode::BCS => self.interpret_bcs()?,
+            RuntimeOpcode::BCC => self.interpret_bcc()?,
+            RuntimeOpcode::ROL_REGISTER => self.interpret_rol_register()?,
+            RuntimeOpcode::ROL_LITERAL => self.interpret_rol_literal()?,
+            RuntimeOpcode::ROR_REGISTER => self.interpret_ror_register()?,
+            RuntimeOpcode::ROR_LITERAL => self.interpret_ror_literal()?,
+        }
+        let line = registers.map(|registers| self.trace_line(opcode, &registers));
+        if let (Some(trace), Some(line)) = (&mut self.trace, line) {
+            let result = writeln!(trace.0, "{line}");
+            result.map_err(|err| self.io_error(err))?;
+        }
+        if opcode == RuntimeOpcode::HALT {
+            self.halted = true;
+            return Ok(StepResult::Halted);
+        }
+        Ok(StepResult::Continue)
+    }
+
+    /// Describe the instruction that was just executed, followed by the registers before
+    /// it ran. Conditional branches also show the comparison flags, which CMP set them and
+    /// whether the branch was taken.
+    fn trace_line(&self, opcode: RuntimeOpcode, registers: &[u8]) -> String {
+        let instruction = decode_instruction(&self.memory[..], self.instruction_start)
+            .expect("an executed instruction can be decoded");
+        let mut line = format!(
+            "{:>3}: {}",
+            self.instruction_start,
+            format_instruction(&instruction, None)
+        );
+        // The branch target may also be the next instruction, so the program counter
+        // alone does not say whether the branch was taken
+        let taken = || match self.last_branch_taken {
+            Some(true) => "taken",
+            _ => "not taken",
+        };
+        match opcode {
+            RuntimeOpcode::BEQ | RuntimeOpcode::BNE | RuntimeOpcode::BGT | RuntimeOpcode::BLT => {
+                let set_by = match self.last_cmp_pc {
+                    Some(pc) => format!("set by CMP at byte {pc}"),
+                    None => String::from("no CMP executed yet"),
+                };
+                line.push_str(&format!(
+                    " (comparison result {}, underflow {}, {}, {})",
+                    self.comparison_result,
+                    self.underflow,
+                    set_by,
+                    taken()
+                ));
+            }
+            RuntimeOpcode::BCS | RuntimeOpcode::BCC => {
+                line.push_str(&format!(" (carry {}, {})", self.carry, taken()));
+            }
+            _ => {}
+        }
+        let registers: Vec<String> = registers
+            .iter()
+            .enumerate()
+            .map(|(idx, value)| format!("R{idx}={value}"))
+            .collect();
+        line.push_str(&format!(" [{}]", registers.join(" ")));
+        line
+    }
+
+    fn read_next_memory_address(&mut self) -> Result<u8, RuntimeError> {
+        // Reading the byte at index 255 would wrap the program counter back to 0
+        let next = match self.program_counter.checked_add(1) {
+            Some(next) if self.program_counter < self.program_bytes => next,
+            _ => {
+                return Err(RuntimeError::ReadPastMemory(Box::new(ReadPastMemory {
+                    program_counter: self.instruction_start,
+                })))
+            }
+        };
         let result = self.memory[self.program_counter as usize];
-        self.program_counter += 1;
+        self.program_counter = next;
         Ok(result)
     }
 
     fn read_memory_address(&self, idx: u8) -> Result<u8, RuntimeError> {
         let new_address = match self.program_bytes.checked_add(idx) {
             Some(new) => new,
-            None => return Err(RuntimeError::OutOfBoundsRead(idx as usize)),
+            None => {
+                return Err(RuntimeError::OutOfBoundsRead(Box::new(OutOfBoundsRead {
+                    idx: idx as usize,
+                    address: self.program_bytes as usize + idx as usize,
+                    program_counter: self.instruction_start,
+                })))
+            }
         };
         Ok(self.memory[new_address as usize])
     }
@@ -143,95 +598,151 @@ impl<'a, R: BufRead, W: Write> Interpreter<'a, R, W> {
     fn write_memory_address(&mut self, val: u8, idx: u8) -> Result<(), RuntimeError> {
         let new_address = match self.program_bytes.checked_add(idx) {
             Some(new) => new,
-            None => return Err(RuntimeError::OutOfBoundsWrite(idx as usize)),
+            None => {
+                return Err(RuntimeError::OutOfBoundsWrite(Box::new(OutOfBoundsWrite {
+                    idx: idx as usize,
+                    address: self.program_bytes as usize + idx as usize,
+                    program_counter: self.instruction_start,
+                })))
+            }
         };
-        self.memory[new_address as usize] = val; 
+        self.memory[new_address as usize] = val;
+        self.written_cells[idx as usize] = true;
+        if self.break_on_write == Some(idx) {
+            return Err(RuntimeError::WatchpointHit(Box::new(WatchpointHit {
+                idx,
+                value: val,
+                program_counter: self.instruction_start,
+            })));
+        }
         Ok(())
     }
 
-    fn take_u8_input(&mut self) -> u8 {
+    fn take_u8_input(&mut self) -> Result<u8, RuntimeError> {
+        let mut invalid_inputs = 0;
         loop {
-            let input = self.read_line();
-            if let Ok(val) = input.trim().parse() {
-                return val;
+            match self.io.read_u8() {
+                Ok(Some(value)) => return Ok(value),
+                Ok(None) => {
+                    return Err(RuntimeError::EndOfInput(Box::new(EndOfInput {
+                        program_counter: self.instruction_start,
+                    })))
+                }
+                Err(err) if InvalidInput::is_invalid_input(&err) => {
+                    invalid_inputs += 1;
+                    if self.input_retries.is_some_and(|retries| invalid_inputs > retries) {
+                        return Err(RuntimeError::TooManyInvalidInputs(Box::new(
+                            TooManyInvalidInputs {
+                                invalid_inputs,
+                                program_counter: self.instruction_start,
+                            },
+                        )));
+                    }
+                }
+                Err(err) => return Err(self.io_error(err)),
             }
         }
     }
 
-    fn interpret_ldr(&mut self) -> Result<(), RuntimeError> {
+    fn output_value(&mut self, value: u8) -> Result<(), RuntimeError> {
+        self.io.write_value(value).map_err(|err| self.io_error(err))
+    }
+
+    fn read_next_register(&mut self) -> Result<usize, RuntimeError> {
         let register = self.read_next_memory_address()?;
+        if register >= REGISTER_COUNT {
+            return Err(RuntimeError::InvalidRegister(Box::new(InvalidRegister {
+                register,
+                program_counter: self.instruction_start,
+            })));
+        }
+        Ok(register as usize)
+    }
+
+    fn interpret_ldr(&mut self) -> Result<(), RuntimeError> {
+        let register = self.read_next_register()?;
         let memory_ref = self.read_next_memory_address()?;
-        self.registers[register as usize] = self.read_memory_address(memory_ref)?;
+        self.registers[register] = self.read_memory_address(memory_ref)?;
         Ok(())
     }
 
     fn interpret_str(&mut self) -> Result<(), RuntimeError> {
-        let register = self.read_next_memory_address()?;
+        let register = self.read_next_register()?;
         let memory_ref = self.read_next_memory_address()?;
-        self.write_memory_address(self.registers[register as usize], memory_ref)?;
+        self.write_memory_address(self.registers[register], memory_ref)?;
         Ok(())
     }
 
     fn interpret_add_register(&mut self) -> Result<(), RuntimeError> {
-        let register_store = self.read_next_memory_address()? as usize;
-        let register_operand_1 = self.registers[self.read_next_memory_address()? as usize];
-        let register_operand_2 = self.registers[self.read_next_memory_address()? as usize];
-        self.registers[register_store] = register_operand_1.wrapping_add(register_operand_2);
+        let register_store = self.read_next_register()?;
+        let register_operand_1 = self.registers[self.read_next_register()?];
+        let register_operand_2 = self.registers[self.read_next_register()?];
+        let (result, carry) = register_operand_1.overflowing_add(register_operand_2);
+        self.registers[register_store] = result;
+        self.carry = carry;
         Ok(())
     }
 
     fn interpret_add_literal(&mut self) -> Result<(), RuntimeError> {
-        let register_store = self.read_next_memory_address()? as usize;
-        let register_operand_1 = self.registers[self.read_next_memory_address()? as usize];
+        let register_store = self.read_next_register()?;
+        let register_operand_1 = self.registers[self.read_next_register()?];
         let literal_operand_2 = self.read_next_memory_address()?;
-        self.registers[register_store] = register_operand_1.wrapping_add(literal_operand_2);
+        let (result, carry) = register_operand_1.overflowing_add(literal_operand_2);
+        self.registers[register_store] = result;
+        self.carry = carry;
         Ok(())
     }
 
     fn interpret_sub_register(&mut self) -> Result<(), RuntimeError> {
-        let register_store = self.read_next_memory_address()? as usize;
-        let register_operand_1 = self.registers[self.read_next_memory_address()? as usize];
-        let register_operand_2 = self.registers[self.read_next_memory_address()? as usize];
-        self.registers[register_store] = register_operand_1.wrapping_sub(register_operand_2);
+        let register_store = self.read_next_register()?;
+        let register_operand_1 = self.registers[self.read_next_register()?];
+        let register_operand_2 = self.registers[self.read_next_register()?];
+        let (result, carry) = register_operand_1.overflowing_sub(register_operand_2);
+        self.registers[register_store] = result;
+        self.carry = carry;
         Ok(())
     }
 
     fn interpret_sub_literal(&mut self) -> Result<(), RuntimeError> {
-        let register_store = self.read_next_memory_address()? as usize;
-        let register_operand_1 = self.registers[self.read_next_memory_address()? as usize];
+        let register_store = self.read_next_register()?;
+        let register_operand_1 = self.registers[self.read_next_register()?];
         let literal_operand_2 = self.read_next_memory_address()?;
-        self.registers[register_store] = register_operand_1.wrapping_sub(literal_operand_2);
+        let (result, carry) = register_operand_1.overflowing_sub(literal_operand_2);
+        self.registers[register_store] = result;
+        self.carry = carry;
         Ok(())
     }
 
     fn interpret_mov_register(&mut self) -> Result<(), RuntimeError> {
-        let register_store = self.read_next_memory_address()? as usize;
-        let register_operand = self.read_next_memory_address()? as usize;
+        let register_store = self.read_next_register()?;
+        let register_operand = self.read_next_register()?;
         self.registers[register_store] = self.registers[register_operand];
         Ok(())
     }
 
     fn interpret_mov_literal(&mut self) -> Result<(), RuntimeError> {
-        let register_store = self.read_next_memory_address()? as usize;
+        let register_store = self.read_next_register()?;
         let literal_operand = self.read_next_memory_address()?;
         self.registers[register_store] = literal_operand;
         Ok(())
     }
 
     fn interpret_cmp_register(&mut self) -> Result<(), RuntimeError> {
-        let register_operand_1 = self.read_next_memory_address()? as usize;
-        let register_operand_2 = self.read_next_memory_address()? as usize;
+        let register_operand_1 = self.read_next_register()?;
+        let register_operand_2 = self.read_next_register()?;
         self.underflow = self.registers[register_operand_2] > self.registers[register_operand_1];
         self.comparison_result =
             self.registers[register_operand_1].wrapping_sub(self.registers[register_operand_2]);
+        self.last_cmp_pc = Some(self.instruction_start);
         Ok(())
     }
 
     fn interpret_cmp_literal(&mut self) -> Result<(), RuntimeError> {
-        let register_idx = self.read_next_memory_address()? as usize;
+        let register_idx = self.read_next_register()?;
         let literal = self.read_next_memory_address()?;
         self.underflow = literal > self.registers[register_idx];
         self.comparison_result = self.registers[register_idx].wrapping_sub(literal);
+        self.last_cmp_pc = Some(self.instruction_start);
         Ok(())
     }
 
@@ -242,166 +753,358 @@ impl<'a, R: BufRead, W: Write> Interpreter<'a, R, W> {
     }
 
     fn interpret_beq(&mut self) -> Result<(), RuntimeError> {
-        if self.comparison_result == 0 {
+        let taken = self.comparison_result == 0;
+        self.last_branch_taken = Some(taken);
+        if taken {
             let idx_to_branch_too = self.read_next_memory_address()?;
             self.program_counter = idx_to_branch_too;
         } else {
-            self.program_counter += 1;
+            // Skip over the branch target
+            self.read_next_memory_address()?;
         }
         Ok(())
     }
 
     fn interpret_bne(&mut self) -> Result<(), RuntimeError> {
-        if self.comparison_result != 0 {
+        let taken = self.comparison_result != 0;
+        self.last_branch_taken = Some(taken);
+        if taken {
             let idx_to_branch_too = self.read_next_memory_address()?;
             self.program_counter = idx_to_branch_too;
         } else {
-            self.program_counter += 1;
+            // Skip over the branch target
+            self.read_next_memory_address()?;
         }
         Ok(())
     }
 
     fn interpret_bgt(&mut self) -> Result<(), RuntimeError> {
-        if self.comparison_result != 0 && !self.underflow {
+        let taken = self.comparison_result != 0 && !self.underflow;
+        self.last_branch_taken = Some(taken);
+        if taken {
             let idx_to_branch_too = self.read_next_memory_address()?;
             self.program_counter = idx_to_branch_too;
         } else {
-            self.program_counter += 1;
+            // Skip over the branch target
+            self.read_next_memory_address()?;
         }
         Ok(())
     }
 
     fn interpret_blt(&mut self) -> Result<(), RuntimeError> {
-        if self.underflow {
+        let taken = self.underflow;
+        self.last_branch_taken = Some(taken);
+        if taken {
+            let idx_to_branch_too = self.read_next_memory_address()?;
+            self.program_counter = idx_to_branch_too;
+        } else {
+            // Skip over the branch target
+            self.read_next_memory_address()?;
+        }
+        Ok(())
+    }
+
+    fn interpret_bcs(&mut self) -> Result<(), RuntimeError> {
+        let taken = self.carry;
+        self.last_branch_taken = Some(taken);
+        if taken {
+            let idx_to_branch_too = self.read_next_memory_address()?;
+            self.program_counter = idx_to_branch_too;
+        } else {
+            // Skip over the branch target
+            self.read_next_memory_address()?;
+        }
+        Ok(())
+    }
+
+    fn interpret_bcc(&mut self) -> Result<(), RuntimeError> {
+        let taken = !self.carry;
+        self.last_branch_taken = Some(taken);
+        if taken {
             let idx_to_branch_too = self.read_next_memory_address()?;
             self.program_counter = idx_to_branch_too;
         } else {
-            self.program_counter += 1;
+            // Skip over the branch target
+            self.read_next_memory_address()?;
         }
         Ok(())
     }
 
     fn interpret_and_register(&mut self) -> Result<(), RuntimeError> {
-        let register_store = self.read_next_memory_address()? as usize;
-        let register_operand_1 = self.registers[self.read_next_memory_address()? as usize];
-        let register_operand_2 = self.registers[self.read_next_memory_address()? as usize];
+        let register_store = self.read_next_register()?;
+        let register_operand_1 = self.registers[self.read_next_register()?];
+        let register_operand_2 = self.registers[self.read_next_register()?];
         self.registers[register_store] = register_operand_1 & register_operand_2;
         Ok(())
     }
 
     fn interpret_and_literal(&mut self) -> Result<(), RuntimeError> {
-        let register_store = self.read_next_memory_address()? as usize;
-        let register_operand_1 = self.registers[self.read_next_memory_address()? as usize];
+        let register_store = self.read_next_register()?;
+        let register_operand_1 = self.registers[self.read_next_register()?];
         let literal = self.read_next_memory_address()?;
         self.registers[register_store] = register_operand_1 & literal;
         Ok(())
     }
 
     fn interpret_orr_register(&mut self) -> Result<(), RuntimeError> {
-        let register_store = self.read_next_memory_address()? as usize;
-        let register_operand_1 = self.registers[self.read_next_memory_address()? as usize];
-        let register_operand_2 = self.registers[self.read_next_memory_address()? as usize];
+        let register_store = self.read_next_register()?;
+        let register_operand_1 = self.registers[self.read_next_register()?];
+        let register_operand_2 = self.registers[self.read_next_register()?];
         self.registers[register_store] = register_operand_1 | register_operand_2;
         Ok(())
     }
 
     fn interpret_orr_literal(&mut self) -> Result<(), RuntimeError> {
-        let register_store = self.read_next_memory_address()? as usize;
-        let register_operand_1 = self.registers[self.read_next_memory_address()? as usize];
+        let register_store = self.read_next_register()?;
+        let register_operand_1 = self.registers[self.read_next_register()?];
         let literal = self.read_next_memory_address()?;
         self.registers[register_store] = register_operand_1 | literal;
         Ok(())
     }
 
     fn interpret_eor_register(&mut self) -> Result<(), RuntimeError> {
-        let register_store = self.read_next_memory_address()? as usize;
-        let register_operand_1 = self.registers[self.read_next_memory_address()? as usize];
-        let register_operand_2 = self.registers[self.read_next_memory_address()? as usize];
+        let register_store = self.read_next_register()?;
+        let register_operand_1 = self.registers[self.read_next_register()?];
+        let register_operand_2 = self.registers[self.read_next_register()?];
         self.registers[register_store] = register_operand_1 ^ register_operand_2;
         Ok(())
     }
 
     fn interpret_eor_literal(&mut self) -> Result<(), RuntimeError> {
-        let register_store = self.read_next_memory_address()? as usize;
-        let register_operand_1 = self.registers[self.read_next_memory_address()? as usize];
+        let register_store = self.read_next_register()?;
+        let register_operand_1 = self.registers[self.read_next_register()?];
         let literal = self.read_next_memory_address()?;
         self.registers[register_store] = register_operand_1 ^ literal;
         Ok(())
     }
 
     fn interpret_mvn_register(&mut self) -> Result<(), RuntimeError> {
-        let register_store = self.read_next_memory_address()? as usize;
-        let register_operand = self.registers[self.read_next_memory_address()? as usize];
+        let register_store = self.read_next_register()?;
+        let register_operand = self.registers[self.read_next_register()?];
         self.registers[register_store] = !register_operand;
         Ok(())
     }
 
     fn interpret_mvn_literal(&mut self) -> Result<(), RuntimeError> {
-        let register_store = self.read_next_memory_address()? as usize;
+        let register_store = self.read_next_register()?;
         let literal = self.read_next_memory_address()?;
         self.registers[register_store] = !literal;
         Ok(())
     }
 
     fn interpret_lsl_register(&mut self) -> Result<(), RuntimeError> {
-        let register_store = self.read_next_memory_address()? as usize;
-        let register_operand_1 = self.registers[self.read_next_memory_address()? as usize];
-        let register_operand_2 = self.registers[self.read_next_memory_address()? as usize];
+        let register_store = self.read_next_register()?;
+        let register_operand_1 = self.registers[self.read_next_register()?];
+        let register_operand_2 = self.registers[self.read_next_register()?];
         self.registers[register_store] = register_operand_1.wrapping_shl(register_operand_2 as u32);
         Ok(())
     }
 
     fn interpret_lsl_literal(&mut self) -> Result<(), RuntimeError> {
-        let register_store = self.read_next_memory_address()? as usize;
-        let register_operand_1 = self.registers[self.read_next_memory_address()? as usize];
+        let register_store = self.read_next_register()?;
+        let register_operand_1 = self.registers[self.read_next_register()?];
         let literal_operand_2 = self.read_next_memory_address()?;
         self.registers[register_store] = register_operand_1.wrapping_shl(literal_operand_2 as u32);
         Ok(())
     }
 
     fn interpret_lsr_register(&mut self) -> Result<(), RuntimeError> {
-        let register_store = self.read_next_memory_address()? as usize;
-        let register_operand_1 = self.registers[self.read_next_memory_address()? as usize];
-        let register_operand_2 = self.registers[self.read_next_memory_address()? as usize];
+        let register_store = self.read_next_register()?;
+        let register_operand_1 = self.registers[self.read_next_register()?];
+        let register_operand_2 = self.registers[self.read_next_register()?];
         self.registers[register_store] = register_operand_1.wrapping_shr(register_operand_2 as u32);
         Ok(())
     }
 
     fn interpret_lsr_literal(&mut self) -> Result<(), RuntimeError> {
-        let register_store = self.read_next_memory_address()? as usize;
-        let register_operand_1 = self.registers[self.read_next_memory_address()? as usize];
+        let register_store = self.read_next_register()?;
+        let register_operand_1 = self.registers[self.read_next_register()?];
         let literal_operand_2 = self.read_next_memory_address()?;
         self.registers[register_store] = register_operand_1.wrapping_shr(literal_operand_2 as u32);
         Ok(())
     }
 
+    fn interpret_rol_register(&mut self) -> Result<(), RuntimeError> {
+        let register_store = self.read_next_register()?;
+        let register_operand_1 = self.registers[self.read_next_register()?];
+        let register_operand_2 = self.registers[self.read_next_register()?];
+        self.registers[register_store] =
+            register_operand_1.rotate_left((register_operand_2 % 8) as u32);
+        Ok(())
+    }
+
+    fn interpret_rol_literal(&mut self) -> Result<(), RuntimeError> {
+        let register_store = self.read_next_register()?;
+        let register_operand_1 = self.registers[self.read_next_register()?];
+        let literal_operand_2 = self.read_next_memory_address()?;
+        self.registers[register_store] =
+            register_operand_1.rotate_left((literal_operand_2 % 8) as u32);
+        Ok(())
+    }
+
+    fn interpret_ror_register(&mut self) -> Result<(), RuntimeError> {
+        let register_store = self.read_next_register()?;
+        let register_operand_1 = self.registers[self.read_next_register()?];
+        let register_operand_2 = self.registers[self.read_next_register()?];
+        self.registers[register_store] =
+            register_operand_1.rotate_right((register_operand_2 % 8) as u32);
+        Ok(())
+    }
+
+    fn interpret_ror_literal(&mut self) -> Result<(), RuntimeError> {
+        let register_store = self.read_next_register()?;
+        let register_operand_1 = self.registers[self.read_next_register()?];
+        let literal_operand_2 = self.read_next_memory_address()?;
+        self.registers[register_store] =
+            register_operand_1.rotate_right((literal_operand_2 % 8) as u32);
+        Ok(())
+    }
+
+    fn interpret_div_register(&mut self) -> Result<(), RuntimeError> {
+        let register_store = self.read_next_register()?;
+        let register_operand_1 = self.registers[self.read_next_register()?];
+        let register_operand_2 = self.registers[self.read_next_register()?];
+        self.registers[register_store] = self.divide(register_operand_1, register_operand_2)?.0;
+        Ok(())
+    }
+
+    fn interpret_div_literal(&mut self) -> Result<(), RuntimeError> {
+        let register_store = self.read_next_register()?;
+        let register_operand_1 = self.registers[self.read_next_register()?];
+        let literal_operand_2 = self.read_next_memory_address()?;
+        self.registers[register_store] = self.divide(register_operand_1, literal_operand_2)?.0;
+        Ok(())
+    }
+
+    fn interpret_mod_register(&mut self) -> Result<(), RuntimeError> {
+        let register_store = self.read_next_register()?;
+        let register_operand_1 = self.registers[self.read_next_register()?];
+        let register_operand_2 = self.registers[self.read_next_register()?];
+        self.registers[register_store] = self.divide(register_operand_1, register_operand_2)?.1;
+        Ok(())
+    }
+
+    fn interpret_mod_literal(&mut self) -> Result<(), RuntimeError> {
+        let register_store = self.read_next_register()?;
+        let register_operand_1 = self.registers[self.read_next_register()?];
+        let literal_operand_2 = self.read_next_memory_address()?;
+        self.registers[register_store] = self.divide(register_operand_1, literal_operand_2)?.1;
+        Ok(())
+    }
+
+    /// Quotient and remainder of an unsigned division
+    fn divide(&self, dividend: u8, divisor: u8) -> Result<(u8, u8), RuntimeError> {
+        if divisor == 0 {
+            return Err(RuntimeError::DivideByZero(Box::new(DivideByZero {
+                program_counter: self.instruction_start,
+            })));
+        }
+        Ok((dividend / divisor, dividend % divisor))
+    }
+
+    fn interpret_push(&mut self) -> Result<(), RuntimeError> {
+        let register = self.read_next_register()?;
+        self.push_stack(self.registers[register])
+    }
+
+    fn interpret_pop(&mut self) -> Result<(), RuntimeError> {
+        let register = self.read_next_register()?;
+        self.registers[register] = self.pop_stack()?;
+        Ok(())
+    }
+
+    fn interpret_call(&mut self) -> Result<(), RuntimeError> {
+        let idx_to_branch_too = self.read_next_memory_address()?;
+        // The program counter is now the address just after the CALL
+        self.push_stack(self.program_counter)?;
+        self.program_counter = idx_to_branch_too;
+        Ok(())
+    }
+
+    fn interpret_ret(&mut self) -> Result<(), RuntimeError> {
+        self.program_counter = self.pop_stack()?;
+        Ok(())
+    }
+
+    fn push_stack(&mut self, value: u8) -> Result<(), RuntimeError> {
+        // The stack may use every free byte of data memory, but never the program
+        if self.stack_pointer < self.program_bytes || self.stack_pointer == 0 {
+            return Err(RuntimeError::StackOverflow(Box::new(StackOverflow {
+                program_counter: self.instruction_start,
+            })));
+        }
+        self.memory[self.stack_pointer as usize] = value;
+        self.stack_pointer -= 1;
+        self.max_stack_depth = self.max_stack_depth.max(u8::MAX - self.stack_pointer);
+        Ok(())
+    }
+
+    fn pop_stack(&mut self) -> Result<u8, RuntimeError> {
+        if self.stack_pointer == u8::MAX {
+            return Err(RuntimeError::StackUnderflow(Box::new(StackUnderflow {
+                program_counter: self.instruction_start,
+            })));
+        }
+        self.stack_pointer += 1;
+        Ok(self.memory[self.stack_pointer as usize])
+    }
+
     fn interpret_print_register(&mut self) -> Result<(), RuntimeError> {
-        let register = self.read_next_memory_address()?;
-        self.write_line(&format!("{}", self.registers[register as usize]));
+        let register = self.read_next_register()?;
+        let value = self.registers[register];
+        self.output_value(value)?;
         Ok(())
     }
 
     fn interpret_print_memory(&mut self) -> Result<(), RuntimeError> {
         let memory_ref = self.read_next_memory_address()?;
-        self.write_line(&format!(
-            "{}",
-            self.read_memory_address(memory_ref)?
-        ));
+        let value = self.read_memory_address(memory_ref)?;
+        self.output_value(value)?;
         Ok(())
     }
 
     fn interpret_input_register(&mut self) -> Result<(), RuntimeError> {
-        let register = self.read_next_memory_address()?;
-        self.registers[register as usize] = self.take_u8_input();
+        let register = self.read_next_register()?;
+        self.registers[register] = self.take_u8_input()?;
         Ok(())
     }
 
     fn interpret_input_memory(&mut self) -> Result<(), RuntimeError> {
         let memory_ref = self.read_next_memory_address()?;
-        let value = self.take_u8_input();
+        let value = self.take_u8_input()?;
         self.write_memory_address(value, memory_ref)?;
         Ok(())
     }
+
+    fn interpret_cpy(&mut self) -> Result<(), RuntimeError> {
+        let memory_ref_store = self.read_next_memory_address()?;
+        let memory_ref_operand = self.read_next_memory_address()?;
+        let value = self.read_memory_address(memory_ref_operand)?;
+        self.write_memory_address(value, memory_ref_store)?;
+        Ok(())
+    }
+
+    fn interpret_printarr(&mut self) -> Result<(), RuntimeError> {
+        let memory_ref = self.read_next_memory_address()?;
+        let count = self.read_next_memory_address()?;
+        // Read every value first, so nothing is printed if the array overruns memory
+        let mut values = Vec::with_capacity(count as usize);
+        for offset in 0..count {
+            let value = match memory_ref.checked_add(offset) {
+                Some(idx) => self.read_memory_address(idx)?,
+                None => {
+                    let idx = memory_ref as usize + offset as usize;
+                    return Err(RuntimeError::OutOfBoundsRead(Box::new(OutOfBoundsRead {
+                        idx,
+                        address: self.program_bytes as usize + idx,
+                        program_counter: self.instruction_start,
+                    })));
+                }
+            };
+            values.push(value);
+        }
+        self.io.write_values(&values).map_err(|err| self.io_error(err))
+    }
 }
 
 #[cfg(test)]
@@ -413,7 +1116,7 @@ mod tests {
 
     fn load_test_program(program: &[u8]) -> [u8; 256] {
         let mut memory = [0; 256];
-        memory[..program.len()].copy_from_slice(&program);
+        memory[..program.len()].copy_from_slice(program);
         memory
     }
 
@@ -421,9 +1124,9 @@ mod tests {
     fn test_nop() {
         let program = [RuntimeOpcode::NOP as u8, RuntimeOpcode::HALT as u8];
         let mut memory = load_test_program(&program);
-        let memory_copy = memory.clone();
+        let memory_copy = memory;
         let mut registers = [0; REGISTER_COUNT as usize];
-        let registers_copy = registers.clone();
+        let registers_copy = registers;
         Interpreter::interpret(&mut memory, &mut registers, program.len() as u8).unwrap();
         assert_eq!(memory, memory_copy);
         assert_eq!(registers, registers_copy);
@@ -522,7 +1225,7 @@ mod tests {
         let interpreter =
             Interpreter::interpret(&mut memory, &mut registers, program.len() as u8).unwrap();
         assert_eq!(interpreter.comparison_result, 0);
-        assert_eq!(interpreter.underflow, false);
+        assert!(!interpreter.underflow);
 
         // Comparison of contents of register 0 and 1 (both have values of 0)
         let program = &[
@@ -535,7 +1238,7 @@ mod tests {
         let interpreter =
             Interpreter::interpret(&mut memory, &mut registers, program.len() as u8).unwrap();
         assert_eq!(interpreter.comparison_result, 0);
-        assert_eq!(interpreter.underflow, false);
+        assert!(!interpreter.underflow);
     }
 
     #[test]
@@ -553,7 +1256,7 @@ mod tests {
         let interpreter =
             Interpreter::interpret(&mut memory, &mut registers, program.len() as u8).unwrap();
         assert_eq!(interpreter.comparison_result, 5);
-        assert_eq!(interpreter.underflow, false);
+        assert!(!interpreter.underflow);
 
         let program = &[
             RuntimeOpcode::CMP_REGISTER as u8,
@@ -565,7 +1268,7 @@ mod tests {
         let interpreter =
             Interpreter::interpret(&mut memory, &mut registers, program.len() as u8).unwrap();
         assert_eq!(interpreter.comparison_result, 5);
-        assert_eq!(interpreter.underflow, false);
+        assert!(!interpreter.underflow);
     }
 
     #[test]
@@ -583,7 +1286,7 @@ mod tests {
         let interpreter =
             Interpreter::interpret(&mut memory, &mut registers, program.len() as u8).unwrap();
         assert_eq!(interpreter.comparison_result, 251);
-        assert_eq!(interpreter.underflow, true);
+        assert!(interpreter.underflow);
 
         // Comaparison of register 0 (value of 0) and register 1 (value of 5)
         let program = &[
@@ -596,7 +1299,7 @@ mod tests {
         let interpreter =
             Interpreter::interpret(&mut memory, &mut registers, program.len() as u8).unwrap();
         assert_eq!(interpreter.comparison_result, 251);
-        assert_eq!(interpreter.underflow, true);
+        assert!(interpreter.underflow);
     }
 
     #[test]
@@ -631,9 +1334,23 @@ mod tests {
             memory: &mut memory,
             registers: &mut registers,
             program_counter: 0,
+            instruction_start: 0,
             underflow: false,
-            reader: BufReader::new(io::stdin()),
-            writer: io::stdout(),
+            carry: false,
+            instruction_count: 0,
+            written_cells: [false; 256],
+            stack_pointer: u8::MAX,
+            max_stack_depth: 0,
+            halted: false,
+            profile: None,
+            last_cmp_pc: None,
+            last_branch_taken: None,
+            trace: None,
+            input_retries: None,
+            break_on_write: None,
+            cycle_limit: None,
+            source: None,
+            io: StreamIo::new(BufReader::new(io::stdin()), io::stdout()),
         };
         interpreter.internal_interpret().unwrap();
         assert_eq!(interpreter.program_counter, 4);
@@ -662,9 +1379,23 @@ mod tests {
             memory: &mut memory,
             registers: &mut registers,
             program_counter: 0,
+            instruction_start: 0,
             underflow: false,
-            reader: BufReader::new(io::stdin()),
-            writer: io::stdout(),
+            carry: false,
+            instruction_count: 0,
+            written_cells: [false; 256],
+            stack_pointer: u8::MAX,
+            max_stack_depth: 0,
+            halted: false,
+            profile: None,
+            last_cmp_pc: None,
+            last_branch_taken: None,
+            trace: None,
+            input_retries: None,
+            break_on_write: None,
+            cycle_limit: None,
+            source: None,
+            io: StreamIo::new(BufReader::new(io::stdin()), io::stdout()),
         };
         interpreter.internal_interpret().unwrap();
         assert_eq!(interpreter.program_counter, 4);
@@ -693,9 +1424,23 @@ mod tests {
             memory: &mut memory,
             registers: &mut registers,
             program_counter: 0,
+            instruction_start: 0,
             underflow: false,
-            reader: BufReader::new(io::stdin()),
-            writer: io::stdout(),
+            carry: false,
+            instruction_count: 0,
+            written_cells: [false; 256],
+            stack_pointer: u8::MAX,
+            max_stack_depth: 0,
+            halted: false,
+            profile: None,
+            last_cmp_pc: None,
+            last_branch_taken: None,
+            trace: None,
+            input_retries: None,
+            break_on_write: None,
+            cycle_limit: None,
+            source: None,
+            io: StreamIo::new(BufReader::new(io::stdin()), io::stdout()),
         };
         interpreter.internal_interpret().unwrap();
         assert_eq!(interpreter.program_counter, 4);
@@ -731,9 +1476,23 @@ mod tests {
             memory: &mut memory,
             registers: &mut registers,
             program_counter: 0,
+            instruction_start: 0,
             underflow: true,
-            reader: BufReader::new(io::stdin()),
-            writer: io::stdout(),
+            carry: false,
+            instruction_count: 0,
+            written_cells: [false; 256],
+            stack_pointer: u8::MAX,
+            max_stack_depth: 0,
+            halted: false,
+            profile: None,
+            last_cmp_pc: None,
+            last_branch_taken: None,
+            trace: None,
+            input_retries: None,
+            break_on_write: None,
+            cycle_limit: None,
+            source: None,
+            io: StreamIo::new(BufReader::new(io::stdin()), io::stdout()),
         };
         interpreter.internal_interpret().unwrap();
         assert_eq!(interpreter.program_counter, 4);
@@ -752,6 +1511,40 @@ mod tests {
         assert_eq!(interpreter.program_counter, 3);
     }
 
+    #[test]
+    fn test_carry() {
+        let (add, sub) = (RuntimeOpcode::ADD_LITERAL, RuntimeOpcode::SUB_LITERAL);
+        let (bcs, bcc) = (RuntimeOpcode::BCS, RuntimeOpcode::BCC);
+        // Adds or subtracts 1 from the starting value, then branches to the second HALT
+        for (start, opcode, branch, carry, taken) in [
+            (255, add, bcs, true, true),
+            (100, add, bcs, false, false),
+            (100, add, bcc, false, true),
+            (0, sub, bcs, true, true),
+            (0, sub, bcc, true, false),
+        ] {
+            let program = [
+                RuntimeOpcode::MOV_LITERAL as u8,
+                0,
+                start,
+                opcode as u8,
+                0,
+                0,
+                1,
+                branch as u8,
+                10,
+                RuntimeOpcode::HALT as u8,
+                RuntimeOpcode::HALT as u8,
+            ];
+            let mut memory = load_test_program(&program);
+            let mut registers = [0; REGISTER_COUNT as usize];
+            let interpreter =
+                Interpreter::interpret(&mut memory, &mut registers, program.len() as u8).unwrap();
+            assert_eq!(interpreter.carry(), carry);
+            assert_eq!(interpreter.program_counter(), if taken { 11 } else { 10 });
+        }
+    }
+
     #[test]
     fn test_and() {
         let program = [
@@ -885,55 +1678,270 @@ mod tests {
     }
 
     #[test]
-    fn test_interpret_print_register() {
-        // Setup
+    fn test_rol_ror() {
         let program = [
-            RuntimeOpcode::PRINT_REGISTER as u8,
+            RuntimeOpcode::ROL_LITERAL as u8,
+            0,
             0,
+            1,
+            RuntimeOpcode::ROR_REGISTER as u8,
+            1,
+            1,
+            2,
+            RuntimeOpcode::ROL_LITERAL as u8,
+            3,
+            3,
+            9,
             RuntimeOpcode::HALT as u8,
         ];
         let mut memory = load_test_program(&program);
         let mut registers = [0; REGISTER_COUNT as usize];
-        registers[0] = 42;
-
-        // Create a mock output writer
-        let inputs = &[];
-        let mut output: Vec<u8> = Vec::new();
-
-        let reader = BufReader::new(Cursor::new(inputs));
-        let writer = Cursor::new(&mut output);
-
-        Interpreter::interpret_custom_io(
-            &mut memory,
-            &mut registers,
-            program.len() as u8,
-            reader,
-            writer,
-        )
-        .unwrap();
-
-        // Check
-        let output_str = String::from_utf8(output).unwrap();
-        assert_eq!(output_str.trim(), "42");
+        registers[0] = 0b10000001;
+        registers[1] = 0b10000001;
+        registers[2] = 1;
+        registers[3] = 0b10000001;
+        Interpreter::interpret(&mut memory, &mut registers, program.len() as u8).unwrap();
+        assert_eq!(registers[0], 0b00000011);
+        assert_eq!(registers[1], 0b11000000);
+        // The amount is taken modulo 8
+        assert_eq!(registers[3], 0b00000011);
     }
 
     #[test]
-    fn test_print_memory() {
-        // Setup
+    fn test_div_mod() {
         let program = [
-            RuntimeOpcode::PRINT_MEMORY as u8,
+            RuntimeOpcode::DIV_REGISTER as u8,
+            2,
             0,
+            1,
+            RuntimeOpcode::MOD_REGISTER as u8,
+            3,
+            0,
+            1,
+            RuntimeOpcode::DIV_LITERAL as u8,
+            4,
+            0,
+            10,
+            RuntimeOpcode::MOD_LITERAL as u8,
+            5,
+            0,
+            10,
             RuntimeOpcode::HALT as u8,
         ];
         let mut memory = load_test_program(&program);
-        memory[program.len()] = 42;
         let mut registers = [0; REGISTER_COUNT as usize];
+        registers[0] = 35;
+        registers[1] = 6;
+        Interpreter::interpret(&mut memory, &mut registers, program.len() as u8).unwrap();
+        assert_eq!(registers[2], 5);
+        assert_eq!(registers[3], 5);
+        assert_eq!(registers[4], 3);
+        assert_eq!(registers[5], 5);
+    }
 
-        // Create a mock output writer
-        let inputs = &[];
-        let mut output: Vec<u8> = Vec::new();
-
-        let reader = BufReader::new(Cursor::new(inputs));
+    #[test]
+    fn test_divide_by_zero() {
+        for opcode in [RuntimeOpcode::DIV_REGISTER, RuntimeOpcode::MOD_REGISTER] {
+            let program = [
+                RuntimeOpcode::MOV_LITERAL as u8,
+                0,
+                35,
+                opcode as u8,
+                2,
+                0,
+                1,
+                RuntimeOpcode::HALT as u8,
+            ];
+            let mut memory = load_test_program(&program);
+            let mut registers = [0; REGISTER_COUNT as usize];
+            assert_eq!(
+                Interpreter::interpret(&mut memory, &mut registers, program.len() as u8)
+                    .unwrap_err(),
+                RuntimeError::DivideByZero(Box::new(DivideByZero { program_counter: 3 }))
+            );
+        }
+    }
+
+    #[test]
+    fn test_push_pop() {
+        let program = [
+            RuntimeOpcode::PUSH as u8,
+            0,
+            RuntimeOpcode::PUSH as u8,
+            1,
+            RuntimeOpcode::POP as u8,
+            2,
+            RuntimeOpcode::POP as u8,
+            3,
+            RuntimeOpcode::HALT as u8,
+        ];
+        let mut memory = load_test_program(&program);
+        let mut registers = [0; REGISTER_COUNT as usize];
+        registers[0] = 10;
+        registers[1] = 20;
+        let interpreter =
+            Interpreter::interpret(&mut memory, &mut registers, program.len() as u8).unwrap();
+        assert_eq!(interpreter.stack_pointer(), 255);
+        assert_eq!(interpreter.max_stack_depth(), 2);
+        // Values come back off the stack in the opposite order they went on
+        assert_eq!(registers[2], 20);
+        assert_eq!(registers[3], 10);
+        assert_eq!(memory[254..], [20, 10]);
+    }
+
+    #[test]
+    fn test_stack_overflow_and_underflow() {
+        // Pushes forever, until the stack reaches the 4 bytes of program
+        let program = [RuntimeOpcode::PUSH as u8, 0, RuntimeOpcode::B as u8, 0];
+        let mut memory = load_test_program(&program);
+        let mut registers = [0; REGISTER_COUNT as usize];
+        let mut interpreter = Interpreter::new(
+            &mut memory,
+            &mut registers,
+            program.len() as u8,
+            StreamIo::new(io::empty(), io::sink()),
+        );
+        assert_eq!(
+            interpreter.run(),
+            Err(RuntimeError::StackOverflow(Box::new(StackOverflow {
+                program_counter: 0
+            })))
+        );
+        assert_eq!(interpreter.max_stack_depth(), 252);
+        assert_eq!(memory[..program.len()], program);
+
+        let program = [RuntimeOpcode::POP as u8, 0, RuntimeOpcode::HALT as u8];
+        let mut memory = load_test_program(&program);
+        let mut registers = [0; REGISTER_COUNT as usize];
+        assert_eq!(
+            Interpreter::interpret(&mut memory, &mut registers, program.len() as u8).unwrap_err(),
+            RuntimeError::StackUnderflow(Box::new(StackUnderflow { program_counter: 0 }))
+        );
+    }
+
+    #[test]
+    fn test_call_ret() {
+        // The subroutine at byte 11 adds 1 to R0, and is called from two places
+        let program = [
+            RuntimeOpcode::MOV_LITERAL as u8,
+            0,
+            5,
+            RuntimeOpcode::CALL as u8,
+            11,
+            RuntimeOpcode::MOV_REGISTER as u8,
+            1,
+            0,
+            RuntimeOpcode::CALL as u8,
+            11,
+            RuntimeOpcode::HALT as u8,
+            RuntimeOpcode::ADD_LITERAL as u8,
+            0,
+            0,
+            1,
+            RuntimeOpcode::RET as u8,
+        ];
+        let mut memory = load_test_program(&program);
+        let mut registers = [0; REGISTER_COUNT as usize];
+        let interpreter =
+            Interpreter::interpret(&mut memory, &mut registers, program.len() as u8).unwrap();
+        assert_eq!(interpreter.stack_pointer(), u8::MAX);
+        assert_eq!(interpreter.max_stack_depth(), 1);
+        assert_eq!(interpreter.instruction_count(), 9);
+        assert_eq!(registers[0], 7);
+        assert_eq!(registers[1], 6);
+
+        let program = [RuntimeOpcode::RET as u8];
+        let mut memory = load_test_program(&program);
+        let mut registers = [0; REGISTER_COUNT as usize];
+        assert_eq!(
+            Interpreter::interpret(&mut memory, &mut registers, program.len() as u8).unwrap_err(),
+            RuntimeError::StackUnderflow(Box::new(StackUnderflow { program_counter: 0 }))
+        );
+    }
+
+    #[test]
+    fn test_recursive_call_max_stack_depth() {
+        // The subroutine at byte 6 calls itself until R0 counts down to 0, so with R0 = 4
+        // there are 5 return addresses on the stack at the deepest point
+        let program = [
+            RuntimeOpcode::MOV_LITERAL as u8,
+            0,
+            4,
+            RuntimeOpcode::CALL as u8,
+            6,
+            RuntimeOpcode::HALT as u8,
+            RuntimeOpcode::CMP_LITERAL as u8,
+            0,
+            0,
+            RuntimeOpcode::BEQ as u8,
+            17,
+            RuntimeOpcode::SUB_LITERAL as u8,
+            0,
+            0,
+            1,
+            RuntimeOpcode::CALL as u8,
+            6,
+            RuntimeOpcode::RET as u8,
+        ];
+        let mut memory = load_test_program(&program);
+        let mut registers = [0; REGISTER_COUNT as usize];
+        let interpreter =
+            Interpreter::interpret(&mut memory, &mut registers, program.len() as u8).unwrap();
+        assert_eq!(interpreter.max_stack_depth(), 5);
+        assert_eq!(interpreter.stack_pointer(), u8::MAX);
+        assert_eq!(registers[0], 0);
+    }
+
+    #[test]
+    fn test_interpret_print_register() {
+        // Setup
+        let program = [
+            RuntimeOpcode::PRINT_REGISTER as u8,
+            0,
+            RuntimeOpcode::HALT as u8,
+        ];
+        let mut memory = load_test_program(&program);
+        let mut registers = [0; REGISTER_COUNT as usize];
+        registers[0] = 42;
+
+        // Create a mock output writer
+        let inputs = &[];
+        let mut output: Vec<u8> = Vec::new();
+
+        let reader = BufReader::new(Cursor::new(inputs));
+        let writer = Cursor::new(&mut output);
+
+        Interpreter::interpret_custom_io(
+            &mut memory,
+            &mut registers,
+            program.len() as u8,
+            reader,
+            writer,
+        )
+        .unwrap();
+
+        // Check
+        let output_str = String::from_utf8(output).unwrap();
+        assert_eq!(output_str.trim(), "42");
+    }
+
+    #[test]
+    fn test_print_memory() {
+        // Setup
+        let program = [
+            RuntimeOpcode::PRINT_MEMORY as u8,
+            0,
+            RuntimeOpcode::HALT as u8,
+        ];
+        let mut memory = load_test_program(&program);
+        memory[program.len()] = 42;
+        let mut registers = [0; REGISTER_COUNT as usize];
+
+        // Create a mock output writer
+        let inputs = &[];
+        let mut output: Vec<u8> = Vec::new();
+
+        let reader = BufReader::new(Cursor::new(inputs));
         let writer = Cursor::new(&mut output);
 
         Interpreter::interpret_custom_io(
@@ -1014,13 +2022,39 @@ mod tests {
         assert_eq!(memory[program.len()], 150); // Ensure memory at address 0 has the input value
     }
 
+    #[test]
+    fn test_cpy() {
+        let program = [RuntimeOpcode::CPY as u8, 1, 0, RuntimeOpcode::HALT as u8];
+        let mut memory = load_test_program(&program);
+        memory[program.len()] = 42;
+        let mut registers = [0; REGISTER_COUNT as usize];
+        Interpreter::interpret(&mut memory, &mut registers, program.len() as u8).unwrap();
+        assert_eq!(memory[program.len()], 42);
+        assert_eq!(memory[program.len() + 1], 42);
+    }
+
+    #[test]
+    fn test_cpy_out_of_bounds() {
+        let program = [RuntimeOpcode::CPY as u8, 0, 253, RuntimeOpcode::HALT as u8];
+        let mut memory = load_test_program(&program);
+        let mut registers = [0; REGISTER_COUNT as usize];
+        assert_eq!(
+            Interpreter::interpret(&mut memory, &mut registers, program.len() as u8).unwrap_err(),
+            RuntimeError::OutOfBoundsRead(Box::new(OutOfBoundsRead {
+                idx: 253,
+                address: 257,
+                program_counter: 0,
+            }))
+        );
+    }
+
     #[test]
     fn test_halt() {
         let program = [RuntimeOpcode::HALT as u8];
         let mut memory = load_test_program(&program);
-        let memory_copy = memory.clone();
+        let memory_copy = memory;
         let mut registers = [0; REGISTER_COUNT as usize];
-        let registers_copy = registers.clone();
+        let registers_copy = registers;
         let interpreter =
             Interpreter::interpret(&mut memory, &mut registers, program.len() as u8).unwrap();
         assert_eq!(*interpreter.memory, memory_copy);
@@ -1035,7 +2069,11 @@ mod tests {
         let mut registers = [0; REGISTER_COUNT as usize];
         assert_eq!(
             Interpreter::interpret(&mut memory, &mut registers, program.len() as u8).unwrap_err(),
-            RuntimeError::OutOfBoundsRead(253)
+            RuntimeError::OutOfBoundsRead(Box::new(OutOfBoundsRead {
+                idx: 253,
+                address: 256,
+                program_counter: 0,
+            }))
         );
     }
 
@@ -1046,7 +2084,11 @@ mod tests {
         let mut registers = [0; REGISTER_COUNT as usize];
         assert_eq!(
             Interpreter::interpret(&mut memory, &mut registers, program.len() as u8).unwrap_err(),
-            RuntimeError::OutOfBoundsWrite(253)
+            RuntimeError::OutOfBoundsWrite(Box::new(OutOfBoundsWrite {
+                idx: 253,
+                address: 256,
+                program_counter: 0,
+            }))
         );
     }
 
@@ -1057,8 +2099,68 @@ mod tests {
         let mut registers = [0; REGISTER_COUNT as usize];
         assert_eq!(
             Interpreter::interpret(&mut memory, &mut registers, program.len() as u8).unwrap_err(),
-            RuntimeError::ReadPastMemory
+            RuntimeError::ReadPastMemory(Box::new(ReadPastMemory { program_counter: 0 }))
+        )
+    }
+
+    #[test]
+    fn test_read_past_memory_reports_program_counter() {
+        let program = [RuntimeOpcode::NOP as u8, RuntimeOpcode::MOV_LITERAL as u8, 0];
+        let mut memory = load_test_program(&program);
+        let mut registers = [0; REGISTER_COUNT as usize];
+        assert_eq!(
+            Interpreter::interpret(&mut memory, &mut registers, program.len() as u8).unwrap_err(),
+            RuntimeError::ReadPastMemory(Box::new(ReadPastMemory { program_counter: 1 }))
+        )
+    }
+
+    #[test]
+    fn test_out_of_bounds_reports_program_counter() {
+        let program = [RuntimeOpcode::NOP as u8, RuntimeOpcode::STR as u8, 0, 253];
+        let mut memory = load_test_program(&program);
+        let mut registers = [0; REGISTER_COUNT as usize];
+        assert_eq!(
+            Interpreter::interpret(&mut memory, &mut registers, program.len() as u8).unwrap_err(),
+            RuntimeError::OutOfBoundsWrite(Box::new(OutOfBoundsWrite {
+                idx: 253,
+                address: 257,
+                program_counter: 1,
+            }))
+        );
+    }
+
+    #[test]
+    fn test_runtime_error_reports_source_line() {
+        let source = "NOP\nLDR R0, 253\nHALT\n";
+        let program = [RuntimeOpcode::NOP as u8, RuntimeOpcode::LDR as u8, 0, 253, RuntimeOpcode::HALT as u8];
+        let line_table = LineTable::from([(0, 1), (1, 2), (4, 3)]);
+        let origins = LineOrigins::default();
+        let mut memory = load_test_program(&program);
+        let mut registers = [0; REGISTER_COUNT as usize];
+        let mut interpreter = Interpreter::new(
+            &mut memory,
+            &mut registers,
+            program.len() as u8,
+            StreamIo::new(BufReader::new(io::stdin()), io::stdout()),
         )
+        .with_source(ProgramSource {
+            source,
+            line_table: &line_table,
+            origins: &origins,
+        });
+        assert_eq!(
+            interpreter.run().unwrap_err(),
+            RuntimeError::WithSourceLine(Box::new(WithSourceLine {
+                error: RuntimeError::OutOfBoundsRead(Box::new(OutOfBoundsRead {
+                    idx: 253,
+                    address: 258,
+                    program_counter: 1,
+                })),
+                filepath: None,
+                line: 2,
+                text: String::from("LDR R0, 253"),
+            }))
+        );
     }
 
     #[test]
@@ -1068,16 +2170,547 @@ mod tests {
         let mut registers = [0; REGISTER_COUNT as usize];
         assert_eq!(
             Interpreter::interpret(&mut memory, &mut registers, program.len() as u8).unwrap_err(),
-            RuntimeError::ReadPastMemory
+            RuntimeError::ReadPastMemory(Box::new(ReadPastMemory { program_counter: 0 }))
         )
     }
 
     #[test]
-    #[should_panic]
+    fn test_nop_to_end_of_memory() {
+        // A crafted image filling all of memory must not wrap around and loop forever
+        for program_bytes in [u8::MAX, 0] {
+            let mut memory = [RuntimeOpcode::NOP as u8; 256];
+            let mut registers = [0; REGISTER_COUNT as usize];
+            let mut interpreter = Interpreter::new(
+                &mut memory,
+                &mut registers,
+                program_bytes,
+                StreamIo::new(BufReader::new(io::stdin()), io::sink()),
+            );
+            let err = interpreter.run().unwrap_err();
+            assert!(matches!(err, RuntimeError::ReadPastMemory(_)));
+            assert_eq!(interpreter.instruction_count(), program_bytes as u64);
+        }
+    }
+
+    #[test]
+    fn test_crlf_output() {
+        let program = [RuntimeOpcode::MOV_LITERAL as u8, 0, 7, RuntimeOpcode::PRINT_REGISTER as u8, 0, RuntimeOpcode::HALT as u8];
+        for (crlf_output, expected) in [(false, "7\n"), (true, "7\r\n")] {
+            let mut memory = load_test_program(&program);
+            let mut registers = [0; REGISTER_COUNT as usize];
+            let mut output: Vec<u8> = Vec::new();
+            Interpreter::new(
+                &mut memory,
+                &mut registers,
+                program.len() as u8,
+                StreamIo::new(BufReader::new(io::stdin()), Cursor::new(&mut output)).with_options(
+                    StreamOptions {
+                        crlf_output,
+                        ..Default::default()
+                    },
+                ),
+            )
+            .run()
+            .unwrap();
+            assert_eq!(String::from_utf8(output).unwrap(), expected);
+        }
+    }
+
+    #[test]
+    fn test_signed_display() {
+        let program = [RuntimeOpcode::MOV_LITERAL as u8, 0, 251, RuntimeOpcode::PRINT_REGISTER as u8, 0, RuntimeOpcode::HALT as u8];
+        for (signed_display, expected) in [(false, "251\n"), (true, "-5\n")] {
+            let mut memory = load_test_program(&program);
+            let mut registers = [0; REGISTER_COUNT as usize];
+            let mut output: Vec<u8> = Vec::new();
+            Interpreter::new(
+                &mut memory,
+                &mut registers,
+                program.len() as u8,
+                StreamIo::new(BufReader::new(io::stdin()), Cursor::new(&mut output)).with_options(
+                    StreamOptions {
+                        signed_display,
+                        ..Default::default()
+                    },
+                ),
+            )
+            .run()
+            .unwrap();
+            assert_eq!(String::from_utf8(output).unwrap(), expected);
+        }
+        assert_eq!(format_value(128, true), "-128");
+        assert_eq!(format_value(127, true), "127");
+    }
+
+    #[test]
     fn test_invalid_opcode() {
-        let program = [45];
+        let program = [u8::MAX];
+        let mut memory = load_test_program(&program);
+        let mut registers = [0; REGISTER_COUNT as usize];
+        assert_eq!(
+            Interpreter::interpret(&mut memory, &mut registers, program.len() as u8).unwrap_err(),
+            RuntimeError::InvalidOpcode(Box::new(InvalidOpcode {
+                opcode: u8::MAX,
+                program_counter: 0,
+            }))
+        );
+    }
+
+    #[test]
+    fn test_invalid_register() {
+        let program = [RuntimeOpcode::MOV_LITERAL as u8, 13, 5, RuntimeOpcode::HALT as u8];
+        let mut memory = load_test_program(&program);
+        let mut registers = [0; REGISTER_COUNT as usize];
+        assert_eq!(
+            Interpreter::interpret(&mut memory, &mut registers, program.len() as u8).unwrap_err(),
+            RuntimeError::InvalidRegister(Box::new(InvalidRegister {
+                register: 13,
+                program_counter: 0,
+            }))
+        );
+    }
+
+    #[test]
+    fn test_input_end_of_input() {
+        let program = [
+            RuntimeOpcode::INPUT_REGISTER as u8,
+            0,
+            RuntimeOpcode::HALT as u8,
+        ];
         let mut memory = load_test_program(&program);
         let mut registers = [0; REGISTER_COUNT as usize];
-        Interpreter::interpret(&mut memory, &mut registers, program.len() as u8).unwrap_err();
+        let reader = BufReader::new(Cursor::new(b"not a number\n"));
+        let result = Interpreter::interpret_custom_io(
+            &mut memory,
+            &mut registers,
+            program.len() as u8,
+            reader,
+            io::sink(),
+        );
+        assert_eq!(
+            result.unwrap_err(),
+            RuntimeError::EndOfInput(Box::new(EndOfInput { program_counter: 0 }))
+        );
+    }
+
+    #[test]
+    fn test_branch_not_taken_at_end_of_program() {
+        let program = [RuntimeOpcode::BEQ as u8];
+        let mut memory = load_test_program(&program);
+        let mut registers = [0; REGISTER_COUNT as usize];
+        let mut interpreter = Interpreter::new(
+            &mut memory,
+            &mut registers,
+            program.len() as u8,
+            StreamIo::new(BufReader::new(io::stdin()), io::sink()),
+        );
+        interpreter.comparison_result = 1;
+        assert_eq!(
+            interpreter.run().unwrap_err(),
+            RuntimeError::ReadPastMemory(Box::new(ReadPastMemory { program_counter: 0 }))
+        );
+    }
+
+    #[test]
+    fn test_written_cell_count() {
+        // Writing the same cell twice only counts it once
+        let program = [
+            RuntimeOpcode::STR as u8,
+            0,
+            0,
+            RuntimeOpcode::STR as u8,
+            0,
+            0,
+            RuntimeOpcode::CPY as u8,
+            1,
+            5,
+            RuntimeOpcode::HALT as u8,
+        ];
+        let mut memory = load_test_program(&program);
+        let mut registers = [0; REGISTER_COUNT as usize];
+        let interpreter =
+            Interpreter::interpret(&mut memory, &mut registers, program.len() as u8).unwrap();
+        assert_eq!(interpreter.written_cell_count(), 2);
+    }
+
+    #[test]
+    fn test_trace_branch_comparison() {
+        let program = [
+            RuntimeOpcode::MOV_LITERAL as u8,
+            0,
+            3,
+            RuntimeOpcode::CMP_LITERAL as u8,
+            0,
+            3,
+            RuntimeOpcode::BEQ as u8,
+            9,
+            RuntimeOpcode::NOP as u8,
+            // 3 + 255 carries, so the BCC falls through to the BCS which branches
+            RuntimeOpcode::ADD_LITERAL as u8,
+            0,
+            0,
+            255,
+            RuntimeOpcode::BCC as u8,
+            17,
+            RuntimeOpcode::BCS as u8,
+            18,
+            RuntimeOpcode::NOP as u8,
+            RuntimeOpcode::HALT as u8,
+        ];
+        let mut memory = load_test_program(&program);
+        let mut registers = [0; REGISTER_COUNT as usize];
+        let mut trace = Vec::new();
+        Interpreter::new(
+            &mut memory,
+            &mut registers,
+            program.len() as u8,
+            StreamIo::new(&b""[..], io::sink()),
+        )
+        .with_trace(&mut trace)
+        .run()
+        .unwrap();
+        let before = [
+            " [R0=0", " [R0=3", " [R0=3", " [R0=3", " [R0=2", " [R0=2", " [R0=2",
+        ];
+        let rest = " R1=0 R2=0 R3=0 R4=0 R5=0 R6=0 R7=0 R8=0 R9=0 R10=0 R11=0 R12=0]";
+        let expected: Vec<String> = [
+            "  0: MOV R0, #3",
+            "  3: CMP R0, #3",
+            "  6: BEQ 9 (comparison result 0, underflow false, set by CMP at byte 3, taken)",
+            "  9: ADD R0, R0, #255",
+            " 13: BCC 17 (carry true, not taken)",
+            " 15: BCS 18 (carry true, taken)",
+            " 18: HALT",
+        ]
+        .iter()
+        .zip(before)
+        .map(|(instruction, registers)| format!("{instruction}{registers}{rest}"))
+        .collect();
+        assert_eq!(String::from_utf8(trace).unwrap().lines().collect::<Vec<_>>(), expected);
+    }
+
+    #[test]
+    fn test_trace_branch_to_next_instruction() {
+        // Falling through and branching both end up at the next instruction
+        let program = [
+            RuntimeOpcode::MOV_LITERAL as u8,
+            0,
+            1,
+            RuntimeOpcode::CMP_LITERAL as u8,
+            0,
+            2,
+            RuntimeOpcode::BEQ as u8,
+            8,
+            RuntimeOpcode::BCS as u8,
+            10,
+            RuntimeOpcode::HALT as u8,
+        ];
+        let mut memory = load_test_program(&program);
+        let mut registers = [0; REGISTER_COUNT as usize];
+        let mut trace = Vec::new();
+        Interpreter::new(
+            &mut memory,
+            &mut registers,
+            program.len() as u8,
+            StreamIo::new(&b""[..], io::sink()),
+        )
+        .with_trace(&mut trace)
+        .run()
+        .unwrap();
+        let trace = String::from_utf8(trace).unwrap();
+        let lines: Vec<&str> = trace.lines().collect();
+        assert!(lines[2].starts_with(
+            "  6: BEQ 8 (comparison result 255, underflow true, set by CMP at byte 3, not taken)"
+        ));
+        assert!(lines[3].starts_with("  8: BCS 10 (carry false, not taken)"));
+    }
+
+    #[test]
+    fn test_printarr() {
+        let program = [RuntimeOpcode::PRINTARR as u8, 1, 3, RuntimeOpcode::HALT as u8];
+        let mut memory = load_test_program(&program);
+        memory[program.len()..program.len() + 5].copy_from_slice(&[9, 1, 2, 250, 7]);
+        let mut registers = [0; REGISTER_COUNT as usize];
+        let mut output = Vec::new();
+        Interpreter::interpret_custom_io(
+            &mut memory,
+            &mut registers,
+            program.len() as u8,
+            BufReader::new(Cursor::new("")),
+            &mut output,
+        )
+        .unwrap();
+        assert_eq!(output, b"1 2 250\n");
+    }
+
+    #[test]
+    fn test_printarr_overrun() {
+        // The program takes 4 bytes, so the data region ends at offset 251
+        let program = [RuntimeOpcode::PRINTARR as u8, 250, 3, RuntimeOpcode::HALT as u8];
+        let mut memory = load_test_program(&program);
+        let mut registers = [0; REGISTER_COUNT as usize];
+        let mut output = Vec::new();
+        let err = Interpreter::interpret_custom_io(
+            &mut memory,
+            &mut registers,
+            program.len() as u8,
+            BufReader::new(Cursor::new("")),
+            &mut output,
+        )
+        .unwrap_err();
+        assert_eq!(
+            err,
+            RuntimeError::OutOfBoundsRead(Box::new(OutOfBoundsRead {
+                idx: 252,
+                address: 256,
+                program_counter: 0,
+            }))
+        );
+        assert!(output.is_empty());
+    }
+
+    #[test]
+    fn test_input_retries() {
+        let program = [
+            RuntimeOpcode::INPUT_REGISTER as u8,
+            0,
+            RuntimeOpcode::HALT as u8,
+        ];
+        // Four invalid entries before a valid one
+        for (retries, expected) in [
+            (
+                0,
+                Err(RuntimeError::TooManyInvalidInputs(Box::new(
+                    TooManyInvalidInputs {
+                        invalid_inputs: 1,
+                        program_counter: 0,
+                    },
+                ))),
+            ),
+            (
+                3,
+                Err(RuntimeError::TooManyInvalidInputs(Box::new(
+                    TooManyInvalidInputs {
+                        invalid_inputs: 4,
+                        program_counter: 0,
+                    },
+                ))),
+            ),
+            (4, Ok(())),
+        ] {
+            let mut memory = load_test_program(&program);
+            let mut registers = [0; REGISTER_COUNT as usize];
+            let reader = BufReader::new(Cursor::new(b"one\ntwo\n\n300\n5\n"));
+            let result = Interpreter::new(
+                &mut memory,
+                &mut registers,
+                program.len() as u8,
+                StreamIo::new(reader, io::sink()),
+            )
+            .with_input_retries(retries)
+            .run();
+            assert_eq!(result, expected);
+        }
+    }
+
+    #[test]
+    fn test_break_on_write() {
+        let program = [
+            RuntimeOpcode::MOV_LITERAL as u8,
+            0,
+            7,
+            RuntimeOpcode::STR as u8,
+            0,
+            1,
+            RuntimeOpcode::STR as u8,
+            0,
+            2,
+            RuntimeOpcode::MOV_LITERAL as u8,
+            0,
+            9,
+            RuntimeOpcode::STR as u8,
+            0,
+            2,
+            RuntimeOpcode::HALT as u8,
+        ];
+        let mut memory = load_test_program(&program);
+        let mut registers = [0; REGISTER_COUNT as usize];
+        let mut interpreter = Interpreter::new(
+            &mut memory,
+            &mut registers,
+            program.len() as u8,
+            StreamIo::new(io::empty(), io::sink()),
+        )
+        .with_break_on_write(2);
+        assert_eq!(
+            interpreter.run(),
+            Err(RuntimeError::WatchpointHit(Box::new(WatchpointHit {
+                idx: 2,
+                value: 7,
+                program_counter: 6,
+            })))
+        );
+        assert_eq!(interpreter.instruction_count(), 3);
+        assert_eq!(memory[program.len() + 2], 7);
+    }
+
+    #[test]
+    fn test_cycle_limit() {
+        // A branch to itself never halts
+        let program = [RuntimeOpcode::B as u8, 0];
+        let mut memory = load_test_program(&program);
+        let mut registers = [0; REGISTER_COUNT as usize];
+        let mut interpreter = Interpreter::new(
+            &mut memory,
+            &mut registers,
+            program.len() as u8,
+            StreamIo::new(io::empty(), io::sink()),
+        )
+        .with_cycle_limit(1000);
+        assert_eq!(
+            interpreter.run(),
+            Err(RuntimeError::CycleLimitExceeded(Box::new(
+                CycleLimitExceeded {
+                    limit: 1000,
+                    program_counter: 0,
+                }
+            )))
+        );
+        assert_eq!(interpreter.instruction_count(), 1000);
+
+        // The HALT is not counted towards the limit
+        let program = [
+            RuntimeOpcode::NOP as u8,
+            RuntimeOpcode::NOP as u8,
+            RuntimeOpcode::HALT as u8,
+        ];
+        let mut memory = load_test_program(&program);
+        let mut registers = [0; REGISTER_COUNT as usize];
+        let mut interpreter = Interpreter::new(
+            &mut memory,
+            &mut registers,
+            program.len() as u8,
+            StreamIo::new(io::empty(), io::sink()),
+        )
+        .with_cycle_limit(2);
+        assert_eq!(interpreter.run(), Ok(()));
+    }
+
+    #[test]
+    fn test_step() {
+        let program = [
+            RuntimeOpcode::MOV_LITERAL as u8,
+            0,
+            7,
+            RuntimeOpcode::STR as u8,
+            0,
+            1,
+            RuntimeOpcode::B as u8,
+            9,
+            RuntimeOpcode::NOP as u8,
+            RuntimeOpcode::HALT as u8,
+        ];
+        let mut memory = load_test_program(&program);
+        let mut registers = [0; REGISTER_COUNT as usize];
+        let mut interpreter = Interpreter::new(
+            &mut memory,
+            &mut registers,
+            program.len() as u8,
+            StreamIo::new(io::empty(), io::sink()),
+        );
+        assert_eq!(interpreter.program_counter(), 0);
+        assert_eq!(interpreter.step(), Ok(StepResult::Continue));
+        assert_eq!(interpreter.program_counter(), 3);
+        assert_eq!(interpreter.registers()[0], 7);
+        assert_eq!(interpreter.step(), Ok(StepResult::Continue));
+        assert_eq!(interpreter.program_counter(), 6);
+        assert_eq!(interpreter.memory()[program.len() + 1], 7);
+        assert_eq!(interpreter.step(), Ok(StepResult::Continue));
+        assert_eq!(interpreter.program_counter(), 9);
+        assert_eq!(interpreter.step(), Ok(StepResult::Halted));
+        assert_eq!(interpreter.program_counter(), 10);
+        // Stepping after the HALT does not execute anything past it
+        assert_eq!(interpreter.step(), Ok(StepResult::Halted));
+        assert_eq!(interpreter.program_counter(), 10);
+        assert_eq!(interpreter.instruction_count(), 4);
+        assert_eq!(interpreter.run_n(5), Ok(StepResult::Halted));
+        assert_eq!(interpreter.instruction_count(), 4);
+    }
+
+    #[test]
+    fn test_run_n() {
+        let program = [
+            RuntimeOpcode::MOV_LITERAL as u8,
+            0,
+            1,
+            RuntimeOpcode::ADD_LITERAL as u8,
+            0,
+            0,
+            1,
+            RuntimeOpcode::ADD_LITERAL as u8,
+            0,
+            0,
+            1,
+            RuntimeOpcode::ADD_LITERAL as u8,
+            0,
+            0,
+            1,
+            RuntimeOpcode::ADD_LITERAL as u8,
+            0,
+            0,
+            1,
+            RuntimeOpcode::HALT as u8,
+        ];
+        let mut memory = load_test_program(&program);
+        let mut registers = [0; REGISTER_COUNT as usize];
+        let mut interpreter = Interpreter::new(
+            &mut memory,
+            &mut registers,
+            program.len() as u8,
+            StreamIo::new(io::empty(), io::sink()),
+        );
+        assert_eq!(interpreter.run_n(3), Ok(StepResult::Continue));
+        assert_eq!(interpreter.instruction_count(), 3);
+        assert_eq!(interpreter.program_counter, 11);
+        assert_eq!(interpreter.registers[0], 3);
+        assert_eq!(interpreter.run_n(0), Ok(StepResult::Continue));
+        assert_eq!(interpreter.instruction_count(), 3);
+        assert_eq!(interpreter.run_n(10), Ok(StepResult::Halted));
+        assert_eq!(interpreter.instruction_count(), 6);
+        assert_eq!(interpreter.run_n(0), Ok(StepResult::Halted));
+        assert_eq!(registers[0], 5);
+    }
+
+    #[test]
+    fn test_input_mode() {
+        let program = [
+            RuntimeOpcode::INPUT_REGISTER as u8,
+            0,
+            RuntimeOpcode::INPUT_MEMORY as u8,
+            0,
+            RuntimeOpcode::HALT as u8,
+        ];
+        for (input_mode, expected_register, expected_memory) in
+            [(InputMode::Number, 7, 42), (InputMode::Char, b'7', b'A')]
+        {
+            let mut memory = load_test_program(&program);
+            let mut registers = [0; REGISTER_COUNT as usize];
+            let reader = BufReader::new(Cursor::new(b"7\nA\n42\n"));
+            Interpreter::new(
+                &mut memory,
+                &mut registers,
+                program.len() as u8,
+                StreamIo::new(reader, io::sink()).with_options(StreamOptions {
+                    input_mode,
+                    ..Default::default()
+                }),
+            )
+            .run()
+            .unwrap();
+            assert_eq!(registers[0], expected_register);
+            assert_eq!(memory[program.len()], expected_memory);
+        }
+        assert_eq!("char".parse(), Ok(InputMode::Char));
+        assert!("text".parse::<InputMode>().is_err());
     }
 }
@@ -0,0 +1,17 @@
+// wasm_bindgen generates unsafe glue code for exported functions
+#![cfg_attr(not(feature = "wasm"), forbid(unsafe_code))]
+
+pub mod analysis;
+pub mod disassembler;
+mod error;
+pub mod formats;
+pub mod include;
+pub mod interpreter;
+mod machine;
+pub mod parser;
+pub mod playground;
+pub mod profile;
+pub mod tokenizer;
+
+pub use error::*;
+pub use machine::*;
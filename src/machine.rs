@@ -0,0 +1,447 @@
+use crate::{
+    analysis::{self, Warning},
+    disassembler::{disassemble_with_labels, DisassembleError},
+    formats::SourceMap,
+    include::LineOrigins,
+    interpreter::{
+        Interpreter, InterpreterOptions, MachineIo, ProgramSource, StreamIo, REGISTER_COUNT,
+    },
+    parser::{LineTable, Parser, SymbolTable},
+    profile::{CostModel, Profile},
+    tokenizer::{Tokenizer, TokenizerOptions},
+    Error,
+};
+use std::{
+    collections::HashMap,
+    fmt,
+    io::{BufRead, Write},
+};
+
+/// Options used when assembling a program into a [`Machine`]
+#[derive(Debug, Clone)]
+pub struct MachineOptions {
+    /// Width of tabs, used for column numbers in errors
+    pub tabsize: u8,
+    /// Treat ';' as the start of a comment instead of an instruction delimeter
+    pub semicolon_comments: bool,
+    /// Names that are defined for '.if NAME' directives
+    pub defines: Vec<String>,
+    /// Fail to assemble with [`Error::Warnings`] if the program has any warnings
+    pub deny_warnings: bool,
+    /// Where each line of the source was read from if it was expanded from includes, so
+    /// that warnings and runtime errors name the right file and line
+    pub line_origins: LineOrigins,
+}
+
+impl MachineOptions {
+    /// Options to tokenize the source with, which includes are found with too
+    pub fn tokenizer_options(&self) -> TokenizerOptions {
+        TokenizerOptions {
+            tabsize: self.tabsize,
+            semicolon_comments: self.semicolon_comments,
+            defines: self.defines.clone(),
+            ..Default::default()
+        }
+    }
+}
+
+impl Default for MachineOptions {
+    fn default() -> Self {
+        Self {
+            tabsize: 4,
+            semicolon_comments: false,
+            defines: Vec::new(),
+            deny_warnings: false,
+            line_origins: LineOrigins::default(),
+        }
+    }
+}
+
+/// A machine owns an assembled program along with the memory and registers it runs
+/// on, hiding the tokenizer -> parser -> interpreter pipeline behind a single type.
+///
+/// ```
+/// use aqa_asm::{Machine, MachineOptions};
+/// use std::io::Cursor;
+///
+/// let mut machine = Machine::from_source("MOV R0, #5\nHALT\n", MachineOptions::default()).unwrap();
+/// machine.run(Cursor::new(""), Vec::new()).unwrap();
+/// assert_eq!(machine.registers()[0], 5);
+/// ```
+#[derive(Debug)]
+pub struct Machine {
+    memory: [u8; 256],
+    /// Memory as it was when the program was loaded, restored by [`Machine::reset`]
+    initial_memory: [u8; 256],
+    registers: [u8; REGISTER_COUNT as usize],
+    /// Registers as they are when the program starts, restored by [`Machine::reset`]
+    initial_registers: [u8; REGISTER_COUNT as usize],
+    program_bytes: u8,
+    source: String,
+    line_table: LineTable,
+    /// Where each line of `source` was read from, if it was expanded from includes
+    line_origins: LineOrigins,
+    symbols: SymbolTable,
+    interpreter_options: InterpreterOptions,
+    cost_model: Option<CostModel>,
+    /// Where each run is traced to, only set once [`Machine::with_trace`] is called
+    trace: Option<TraceWriter>,
+}
+
+/// Where a [`Machine`] writes its trace, see [`Machine::with_trace`]
+struct TraceWriter(Box<dyn Write>);
+
+impl fmt::Debug for TraceWriter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("TraceWriter")
+    }
+}
+
+impl Machine {
+    /// Tokenize and parse source code, loading the resulting program into memory
+    pub fn from_source(source: &str, options: MachineOptions) -> Result<Self, Error> {
+        let tokenizer = Tokenizer::tokenize_with_options(source, options.tokenizer_options())?;
+        let (memory, program_bytes, line_table, symbols) =
+            Parser::parse_with_symbols(tokenizer.tokens)?;
+        let machine = Self {
+            memory,
+            initial_memory: memory,
+            registers: [0; REGISTER_COUNT as usize],
+            initial_registers: [0; REGISTER_COUNT as usize],
+            program_bytes,
+            source: source.to_string(),
+            line_table,
+            line_origins: options.line_origins.clone(),
+            symbols,
+            interpreter_options: InterpreterOptions::default(),
+            cost_model: None,
+            trace: None,
+        };
+        if options.deny_warnings {
+            let warnings = machine.warnings();
+            if !warnings.is_empty() {
+                return Err(Error::Warnings(warnings));
+            }
+        }
+        Ok(machine)
+    }
+
+    /// Load an already assembled program. Runtime errors will not include source lines.
+    pub fn from_image(memory: [u8; 256], program_bytes: u8) -> Self {
+        Self {
+            memory,
+            initial_memory: memory,
+            registers: [0; REGISTER_COUNT as usize],
+            initial_registers: [0; REGISTER_COUNT as usize],
+            program_bytes,
+            source: String::new(),
+            line_table: LineTable::new(),
+            line_origins: LineOrigins::default(),
+            symbols: SymbolTable::new(),
+            interpreter_options: InterpreterOptions::default(),
+            cost_model: None,
+            trace: None,
+        }
+    }
+
+    /// Use the line and symbol tables of a source map for an image loaded without its
+    /// source, so that runtime errors report the line they happened on
+    pub fn with_source_map(mut self, source_map: SourceMap) -> Self {
+        self.line_table = source_map.line_table;
+        self.symbols = source_map.symbols;
+        self
+    }
+
+    /// Line and symbol tables of the loaded program
+    pub fn source_map(&self) -> SourceMap {
+        SourceMap {
+            line_table: self.line_table.clone(),
+            symbols: self.symbols.clone(),
+        }
+    }
+
+    /// Set the options used when running the program
+    pub fn with_interpreter_options(mut self, options: InterpreterOptions) -> Self {
+        self.interpreter_options = options;
+        self
+    }
+
+    /// Start the program with these register values instead of zero
+    pub fn with_registers(mut self, registers: [u8; REGISTER_COUNT as usize]) -> Self {
+        self.registers = registers;
+        self.initial_registers = registers;
+        self
+    }
+
+    /// Profile the program when it is run, see [`RunResult::profile`]
+    pub fn with_cost_model(mut self, cost_model: CostModel) -> Self {
+        self.cost_model = Some(cost_model);
+        self
+    }
+
+    /// Write a line to `writer` for every instruction executed when the program is run, as
+    /// described in [`Interpreter::with_trace`]
+    pub fn with_trace(mut self, writer: impl Write + 'static) -> Self {
+        self.trace = Some(TraceWriter(Box::new(writer)));
+        self
+    }
+
+    /// Run the loaded program until it halts, reading input from `reader` and writing
+    /// output to `writer`
+    pub fn run<R: BufRead, W: Write>(&mut self, reader: R, writer: W) -> Result<RunResult, Error> {
+        let io = StreamIo::new(reader, writer).with_options(self.interpreter_options.stream.clone());
+        self.run_with_io(io)
+    }
+
+    /// Run the loaded program until it halts using custom I/O. The stream options in the
+    /// interpreter options only apply to [`StreamIo`], so they are not used here.
+    pub fn run_with_io<IO: MachineIo>(&mut self, io: IO) -> Result<RunResult, Error> {
+        let mut interpreter = Interpreter::new(
+            &mut self.memory,
+            &mut self.registers,
+            self.program_bytes,
+            io,
+        )
+        .with_source(ProgramSource {
+            source: &self.source,
+            line_table: &self.line_table,
+            origins: &self.line_origins,
+        });
+        if let Some(cost_model) = &self.cost_model {
+            interpreter = interpreter.with_profile(cost_model.clone());
+        }
+        if let Some(retries) = self.interpreter_options.input_retries {
+            interpreter = interpreter.with_input_retries(retries);
+        }
+        if let Some(idx) = self.interpreter_options.break_on_write {
+            interpreter = interpreter.with_break_on_write(idx);
+        }
+        if let Some(limit) = self.interpreter_options.cycle_limit {
+            interpreter = interpreter.with_cycle_limit(limit);
+        }
+        if let Some(trace) = &mut self.trace {
+            interpreter = interpreter.with_trace(trace.0.as_mut());
+        }
+        interpreter.run()?;
+        let instruction_count = interpreter.instruction_count();
+        let data_bytes_written = interpreter.written_cell_count();
+        let profile = interpreter.profile().cloned();
+        Ok(RunResult {
+            memory: self.memory,
+            data_memory: self.memory[self.program_bytes as usize..].to_vec(),
+            registers: self.registers,
+            program_bytes: self.program_bytes,
+            instruction_count,
+            data_bytes_written,
+            profile,
+        })
+    }
+
+    /// Restore memory and registers to how they were when the program was loaded, so that
+    /// it can be run again without assembling it again
+    pub fn reset(&mut self) {
+        self.memory = self.initial_memory;
+        self.registers = self.initial_registers;
+    }
+
+    /// Statically check the loaded program for likely mistakes. Only programs assembled
+    /// from source are checked. The program is checked as it was loaded, so running it
+    /// first makes no difference.
+    pub fn warnings(&self) -> Vec<Warning> {
+        analysis::analyse(&self.initial_memory, self.program_bytes, &self.line_table)
+            .into_iter()
+            .map(|warning| warning.in_file(&self.line_origins))
+            .collect()
+    }
+
+    pub fn registers(&self) -> &[u8; REGISTER_COUNT as usize] {
+        &self.registers
+    }
+
+    pub fn memory(&self) -> &[u8; 256] {
+        &self.memory
+    }
+
+    /// The assembled program bytes
+    pub fn program(&self) -> &[u8] {
+        &self.memory[..self.program_bytes as usize]
+    }
+
+    /// Amount of bytes occupied by the program, the data region starts directly after
+    pub fn program_bytes(&self) -> u8 {
+        self.program_bytes
+    }
+}
+
+/// State of the machine after a program has halted
+#[derive(Debug, Clone, PartialEq)]
+pub struct RunResult {
+    /// All 256 bytes of memory, the program followed by the data region
+    pub memory: [u8; 256],
+    /// The data region, every byte after the program
+    pub data_memory: Vec<u8>,
+    pub registers: [u8; REGISTER_COUNT as usize],
+    /// Amount of bytes occupied by the program
+    pub program_bytes: u8,
+    /// Number of instructions executed, including the final HALT
+    pub instruction_count: u64,
+    /// Number of distinct data cells the program wrote to
+    pub data_bytes_written: usize,
+    /// Only present if the machine was given a cost model
+    pub profile: Option<Profile>,
+}
+
+impl RunResult {
+    /// Every register in decimal, followed by a hex dump of the data region with 16 bytes
+    /// per line, each line starting with the address of its first byte
+    pub fn dump_state(&self) -> String {
+        let mut output = String::new();
+        for (idx, value) in self.registers.iter().enumerate() {
+            output.push_str(&format!("R{idx} = {value}\n"));
+        }
+        for (line, bytes) in self.data_memory.chunks(16).enumerate() {
+            let address = self.program_bytes as usize + line * 16;
+            let hex: Vec<String> = bytes.iter().map(|byte| format!("{byte:02X}")).collect();
+            output.push_str(&format!("{address:02X}: {}\n", hex.join(" ")));
+        }
+        output
+    }
+}
+
+/// An assembled program on its own, without the machine state needed to run it.
+///
+/// ```
+/// use aqa_asm::CompiledProgram;
+///
+/// let program: CompiledProgram = "MOV R0, #5\nHALT\n".try_into().unwrap();
+/// assert_eq!(program.len(), 4);
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct CompiledProgram {
+    memory: [u8; 256],
+    program_bytes: u8,
+    symbols: SymbolTable,
+}
+
+impl CompiledProgram {
+    /// The assembled program, without the data region after it
+    pub fn bytes(&self) -> &[u8] {
+        &self.memory[..self.program_bytes as usize]
+    }
+
+    /// Amount of bytes occupied by the program
+    pub fn len(&self) -> usize {
+        self.program_bytes as usize
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.program_bytes == 0
+    }
+
+    /// All of memory as it is when the program is loaded
+    pub fn memory(&self) -> &[u8; 256] {
+        &self.memory
+    }
+
+    /// Byte offset a label refers to. This is the offset of the next instruction after
+    /// the label, or the size of the program if no instructions follow it.
+    pub fn label_address(&self, name: &str) -> Option<u8> {
+        self.symbols.get(name).copied()
+    }
+
+    /// Write the program as source code that assembles back to the same memory, with a
+    /// label synthesised for every branch target and the initial data after '.data'
+    pub fn to_source(&self) -> Result<String, DisassembleError> {
+        disassemble_with_labels(&self.memory, self.program_bytes, Some(&HashMap::new()))
+    }
+}
+
+impl CompiledProgram {
+    /// Tokenize and parse source code
+    fn assemble(source: &str, options: &MachineOptions) -> Result<Self, Error> {
+        let tokenizer = Tokenizer::tokenize_with_options(source, options.tokenizer_options())?;
+        let (memory, program_bytes, _, symbols) = Parser::parse_with_symbols(tokenizer.tokens)?;
+        Ok(Self {
+            memory,
+            program_bytes,
+            symbols,
+        })
+    }
+}
+
+impl TryFrom<&str> for CompiledProgram {
+    type Error = Error;
+
+    /// Tokenize and parse source code using the default [`MachineOptions`]
+    fn try_from(source: &str) -> Result<Self, Error> {
+        CompiledProgram::assemble(source, &MachineOptions::default())
+    }
+}
+
+/// Where the program ends and the data region begins in memory
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MemoryLayout {
+    /// Address just past the last byte of the program
+    pub code_end: u8,
+    /// Address of memory reference 0
+    pub data_start: u8,
+    /// Amount of bytes in the data region
+    pub data_capacity: u16,
+}
+
+impl MemoryLayout {
+    /// Layout of memory when a program of `program_bytes` bytes is loaded
+    pub fn new(program_bytes: u8) -> Self {
+        Self {
+            code_end: program_bytes,
+            data_start: program_bytes,
+            data_capacity: 256 - program_bytes as u16,
+        }
+    }
+}
+
+/// Assemble source code, returning the program along with where it places the boundary
+/// between code and data
+pub fn assemble_layout(
+    source: &str,
+    tabsize: u8,
+) -> Result<(CompiledProgram, MemoryLayout), Error> {
+    let options = MachineOptions {
+        tabsize,
+        ..Default::default()
+    };
+    let program = CompiledProgram::assemble(source, &options)?;
+    let layout = MemoryLayout::new(program.program_bytes);
+    Ok((program, layout))
+}
+
+/// Everything a program produced when run through [`try_run`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct Output {
+    /// Bytes the program wrote with PRINT
+    pub stdout: Vec<u8>,
+    pub registers: [u8; REGISTER_COUNT as usize],
+    pub memory: [u8; 256],
+}
+
+/// Instructions [`try_run`] executes before stopping a program that has not halted
+pub const TRY_RUN_CYCLE_LIMIT: u64 = 1_000_000;
+
+/// Assemble and run untrusted source code against a fixed input. Any problem with the
+/// program is reported as an [`Error`], this function never panics. A program that has not
+/// halted after [`TRY_RUN_CYCLE_LIMIT`] instructions is stopped with
+/// [`crate::interpreter::RuntimeError::CycleLimitExceeded`], so it always returns.
+pub fn try_run(source: &str, input: &[u8]) -> Result<Output, Error> {
+    let mut machine = Machine::from_source(source, MachineOptions::default())?
+        .with_interpreter_options(InterpreterOptions {
+            cycle_limit: Some(TRY_RUN_CYCLE_LIMIT),
+            ..Default::default()
+        });
+    let mut stdout = Vec::new();
+    machine.run(input, &mut stdout)?;
+    Ok(Output {
+        stdout,
+        registers: machine.registers,
+        memory: machine.memory,
+    })
+}
@@ -1,37 +1,226 @@
 #![forbid(unsafe_code)]
 
-mod interpreter;
-mod parser;
-mod tokenizer;
-
+use aqa_asm::{
+    disassembler,
+    formats::{self, SourceMap},
+    include::{self, ExpandedSource, LineOrigins},
+    interpreter::{
+        instruction::{
+            runtime_opcode::RuntimeOpcode, signature::SIGNATURE_TREE, source_opcode::SourceOpcode,
+        },
+        InputMode, InterpreterOptions, StreamOptions, REGISTER_COUNT,
+    },
+    profile::{CostModel, Profile},
+    Error, FailedToReadFile, FailedToWriteFile, Machine, MachineOptions, MemoryLayout,
+    OutputMismatch, RunResult,
+};
 use clap::Parser as ClapParser;
 use inline_colorization::{color_green, color_red, color_reset, style_bold, style_reset};
-use interpreter::{Interpreter, REGISTER_COUNT};
-use parser::Parser;
 use std::{
     fs,
-    io::{self, BufRead, BufReader, Write},
+    io::{self, BufRead, BufWriter, Read, Write},
+    path::{Path, PathBuf},
+    process,
+    str::FromStr,
 };
-use tokenizer::Tokenizer;
 
 /// An interpreter for the AQA assembly language
 #[derive(ClapParser, Debug)]
 #[command(version, about, long_about = None)]
 struct Args {
-    /// The name of the file to process
-    #[arg(index = 1)]
-    filepath: String,
+    /// The files to process, or '-' to read source code from stdin, in which case INPUT
+    /// reads the values given with --input. When there is more than one, each is run in
+    /// turn on a fresh machine and a summary is printed at the end
+    #[arg(index = 1, required_unless_present_any = ["explain", "list_isa"])]
+    filepaths: Vec<String>,
 
     /// Width of tabs
     #[arg(short, long, default_value_t = 4)]
     tabsize: u8,
+
+    /// Print the valid signatures and a description of an instruction, then exit
+    #[arg(long, value_name = "OPCODE")]
+    explain: Option<String>,
+
+    /// Print every instruction form and the runtime opcode it assembles to as
+    /// tab-separated values, then exit
+    #[arg(long)]
+    list_isa: bool,
+
+    /// Treat ';' as the start of a comment, so only newlines separate instructions
+    #[arg(long)]
+    semicolon_comments: bool,
+
+    /// Define a name so that lines between '.if NAME' and '.endif' are assembled, can be
+    /// repeated
+    #[arg(short = 'D', long, value_name = "NAME")]
+    define: Vec<String>,
+
+    /// Only print the program's own output and errors, without status messages
+    #[arg(short, long)]
+    quiet: bool,
+
+    /// Write the assembled program to a file instead of running it
+    #[arg(short, long, value_name = "FILE", visible_alias = "assemble-only")]
+    output: Option<String>,
+
+    /// How to write the assembled program: "binary" for raw bytes, or "json" for the
+    /// instructions, labels and bytes for front-ends that show programs structurally
+    #[arg(
+        long,
+        value_name = "FORMAT",
+        default_value_t = OutputFormat::Binary,
+        requires = "output",
+        conflicts_with = "binary"
+    )]
+    format: OutputFormat,
+
+    /// Prepend a header to the assembled program so it can be validated when loaded
+    #[arg(long, requires = "output")]
+    with_header: bool,
+
+    /// Also write a source map next to the output file, so that runtime errors in the
+    /// assembled program can be traced back to source lines
+    #[arg(long, requires = "output")]
+    with_map: bool,
+
+    /// Treat the file as an assembled program rather than source code. A source map with
+    /// the same name and a .map extension is used if it exists
+    #[arg(short, long)]
+    binary: bool,
+
+    /// End lines printed by the program with "\r\n" instead of "\n"
+    #[arg(long)]
+    crlf_output: bool,
+
+    /// Print values as signed two's complement, so 251 is printed as -5
+    #[arg(long)]
+    signed_display: bool,
+
+    /// How INPUT reads a line: "number" parses it, "char" stores its first character
+    #[arg(long, value_name = "MODE", default_value_t = InputMode::Number)]
+    input_mode: InputMode,
+
+    /// Invalid entries to ask again after for a single INPUT before failing, instead of
+    /// asking forever. 0 fails on the first invalid entry
+    #[arg(long, value_name = "N")]
+    input_retries: Option<u32>,
+
+    /// Text to write before each line the program prints, such as "OUT: "
+    #[arg(long, value_name = "TEXT", default_value = "")]
+    print_prefix: String,
+
+    /// Stop the program the first time it writes to this memory reference, and report
+    /// the instruction and the value written
+    #[arg(long, value_name = "ADDR")]
+    break_on_write: Option<u8>,
+
+    /// Write all 256 bytes of memory to a file once the program halts, including any
+    /// changes it made to itself
+    #[arg(long, value_name = "FILE", conflicts_with = "output")]
+    dump_image: Option<String>,
+
+    /// Print every register and a hex dump of the data region to stdout once the program
+    /// halts
+    #[arg(long)]
+    dump_state: bool,
+
+    /// Stop the program with an error once it has executed this many instructions without
+    /// halting, to catch infinite loops. 0 means no limit
+    #[arg(long, value_name = "N", default_value_t = 10_000_000)]
+    max_cycles: u64,
+
+    /// Treat warnings as errors, and fail on the first invalid INPUT entry unless
+    /// --input-retries is given
+    #[arg(long)]
+    strict: bool,
+
+    /// Write a JSON report of instructions and cycles executed by opcode to a file
+    #[arg(long, value_name = "FILE")]
+    profile_json: Option<String>,
+
+    /// Cycle cost of each runtime opcode used for --profile-json, one "OPCODE cycles" per line
+    #[arg(long, value_name = "FILE", requires = "profile_json")]
+    cost_model: Option<String>,
+
+    /// Print every instruction executed to stderr as the program runs, along with the
+    /// registers before it ran. Conditional branches also show the comparison flags and the
+    /// CMP that set them
+    #[arg(long)]
+    trace: bool,
+
+    /// Fill the registers with non-zero values before running, to show up programs that
+    /// rely on registers starting at zero
+    #[arg(long)]
+    garbage_registers: bool,
+
+    /// Seed for the values used by --garbage-registers
+    #[arg(long, value_name = "N", requires = "garbage_registers")]
+    seed: Option<u64>,
+
+    /// Whitespace separated values for INPUT to read instead of stdin, can be repeated
+    #[arg(short, long, value_name = "VALUES")]
+    input: Vec<String>,
+
+    /// Compare the program's output with the contents of a file instead of printing it,
+    /// failing at the first line that differs
+    #[arg(long, value_name = "FILE", conflicts_with = "output")]
+    expect: Option<String>,
+
+    /// Assemble the program and print how much memory it uses, without running it
+    #[arg(long, conflicts_with = "output")]
+    count_only: bool,
+
+    /// Print the assembled program as source code, with branch targets as byte offsets,
+    /// without running it. Useful for reading a --binary image
+    #[arg(long, conflicts_with_all = ["output", "annotate", "decode"])]
+    disassemble: bool,
+
+    /// Print the assembled program as hex, one instruction per line with the instruction
+    /// it encodes in a comment, without running it
+    #[arg(long, conflicts_with = "output")]
+    annotate: bool,
+
+    /// Print how every byte of the program decodes into instructions, without running it.
+    /// Bytes that are not a valid instruction are shown as .byte, which helps find the
+    /// problem with a corrupt --binary image
+    #[arg(long, conflicts_with_all = ["output", "annotate"])]
+    decode: bool,
 }
 
-/// prints bold and green
-macro_rules! good_print {
-    ($($arg:tt)*) => {
+/// What --output writes
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum OutputFormat {
+    Binary,
+    Json,
+}
+
+impl std::fmt::Display for OutputFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            OutputFormat::Binary => write!(f, "binary"),
+            OutputFormat::Json => write!(f, "json"),
+        }
+    }
+}
+
+impl FromStr for OutputFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "binary" => Ok(OutputFormat::Binary),
+            "json" => Ok(OutputFormat::Json),
+            _ => Err(format!("unknown format '{s}', expected 'binary' or 'json'")),
+        }
+    }
+}
+
+/// writes bold and green, a status message failing to write is not worth stopping for
+macro_rules! good_write {
+    ($dst:expr, $($arg:tt)*) => {
         {
-            println!("{style_bold}{color_green}{}{color_reset}{style_reset}", format!($($arg)*))
+            let _ = writeln!($dst, "{style_bold}{color_green}{}{color_reset}{style_reset}", format!($($arg)*));
         }
     };
 }
@@ -45,68 +234,515 @@ macro_rules! bad_print {
     };
 }
 
+/// Read a program from a file, either as source code or as an assembled binary
+fn load_machine(filepath: &str, binary: bool, options: MachineOptions) -> Result<Machine, Error> {
+    let read_error = |err: io::Error| {
+        Error::FailedToReadFile(Box::new(FailedToReadFile {
+            filepath: filepath.to_string(),
+            reason: err.to_string(),
+        }))
+    };
+
+    if binary {
+        check_not_directory(filepath)?;
+        let bytes = fs::read(filepath).map_err(read_error)?;
+        let (memory, program_bytes) = formats::load_image(&bytes)?;
+        let machine = Machine::from_image(memory, program_bytes);
+        let map_path = source_map_path(filepath);
+        if !map_path.exists() {
+            return Ok(machine);
+        }
+        let map_bytes = fs::read(&map_path).map_err(|err| {
+            Error::FailedToReadFile(Box::new(FailedToReadFile {
+                filepath: map_path.display().to_string(),
+                reason: err.to_string(),
+            }))
+        })?;
+        Ok(machine.with_source_map(SourceMap::parse(&map_bytes)?))
+    } else {
+        // Tokenize, parse and load the instructions into memory
+        let ExpandedSource { source, origins } = read_source(filepath, &options)?;
+        let tabsize = options.tabsize;
+        let options = MachineOptions {
+            line_origins: origins.clone(),
+            ..options
+        };
+        Machine::from_source(&source, options)
+            .map_err(|err| err.with_source(&source, tabsize, &origins))
+    }
+}
+
+/// Read source code from a file, along with the files it includes, or from stdin
+fn read_source(filepath: &str, options: &MachineOptions) -> Result<ExpandedSource, Error> {
+    if filepath == STDIN_FILEPATH {
+        return Ok(ExpandedSource {
+            source: read_source_from(io::stdin().lock())?,
+            origins: LineOrigins::default(),
+        });
+    }
+    check_not_directory(filepath)?;
+    include::read_source_with_includes(Path::new(filepath), &options.tokenizer_options())
+        .map_err(|err| explain_read_error(filepath, err))
+}
+
+fn check_not_directory(filepath: &str) -> Result<(), Error> {
+    if Path::new(filepath).is_dir() {
+        return Err(Error::FailedToReadFile(Box::new(FailedToReadFile {
+            filepath: filepath.to_string(),
+            reason: String::from("path is a directory"),
+        })));
+    }
+    Ok(())
+}
+
+/// Replace the OS's reason for failing to read a source file with a friendlier one when
+/// the file is not text, as that usually means a binary image was passed without --binary
+fn explain_read_error(filepath: &str, err: Error) -> Error {
+    match err {
+        Error::FailedToReadFile(mut err)
+            if err.filepath == filepath
+                && fs::read(filepath).is_ok_and(|bytes| String::from_utf8(bytes).is_err()) =>
+        {
+            err.reason = String::from("file is not valid UTF-8 text; did you mean --binary?");
+            Error::FailedToReadFile(err)
+        }
+        err => err,
+    }
+}
+
+/// Filepath that stands for stdin when given on the command line
+const STDIN_FILEPATH: &str = "-";
+
+/// Read source code piped in on stdin. Nothing at all being piped in is an error rather
+/// than an empty program, as it usually means the command producing it failed.
+fn read_source_from<R: Read>(mut reader: R) -> Result<String, Error> {
+    let read_error = |reason: String| {
+        Error::FailedToReadFile(Box::new(FailedToReadFile {
+            filepath: String::from("<stdin>"),
+            reason,
+        }))
+    };
+    let mut source = String::new();
+    match reader.read_to_string(&mut source) {
+        Ok(0) => Err(read_error(String::from("no source code was given"))),
+        Ok(_) => Ok(source),
+        Err(err) => Err(read_error(err.to_string())),
+    }
+}
+
+/// Read a cost model from a file, or use the built in one if none is given
+fn load_cost_model(filepath: Option<&str>) -> Result<CostModel, Error> {
+    let Some(filepath) = filepath else {
+        return Ok(CostModel::default());
+    };
+    let text = fs::read_to_string(filepath).map_err(|err| {
+        Error::FailedToReadFile(Box::new(FailedToReadFile {
+            filepath: filepath.to_string(),
+            reason: err.to_string(),
+        }))
+    })?;
+    Ok(CostModel::parse(&text)?)
+}
+
+/// Write a profile to a file as JSON
+fn write_profile(profile: &Profile, output: &str) -> Result<(), Error> {
+    fs::write(output, profile.to_json()).map_err(|err| {
+        Error::FailedToWriteFile(Box::new(FailedToWriteFile {
+            filepath: output.to_string(),
+            reason: err.to_string(),
+        }))
+    })
+}
+
+/// Assemble a source file and write it to a file as JSON, see [`formats::program_to_json`]
+fn export_json(filepath: &str, options: &MachineOptions, output: &str) -> Result<(), Error> {
+    let ExpandedSource { source, origins } = read_source(filepath, options)?;
+    let json = formats::program_to_json(&source, options)
+        .map_err(|err| err.with_source(&source, options.tabsize, &origins))?;
+    fs::write(output, json).map_err(|err| {
+        Error::FailedToWriteFile(Box::new(FailedToWriteFile {
+            filepath: output.to_string(),
+            reason: err.to_string(),
+        }))
+    })
+}
+
+/// Write the whole of memory to a file as raw bytes
+fn write_memory_image(memory: &[u8; 256], output: &str) -> Result<(), Error> {
+    fs::write(output, memory).map_err(|err| {
+        Error::FailedToWriteFile(Box::new(FailedToWriteFile {
+            filepath: output.to_string(),
+            reason: err.to_string(),
+        }))
+    })
+}
+
+/// Where the source map for a binary is stored
+fn source_map_path(filepath: &str) -> PathBuf {
+    Path::new(filepath).with_extension("map")
+}
+
+/// Write the assembled program to a file, along with its source map if `with_map` is set
+pub fn export_image(
+    machine: &Machine,
+    output: &str,
+    with_header: bool,
+    with_map: bool,
+) -> Result<(), Error> {
+    let write = |filepath: &Path, bytes: Vec<u8>| {
+        fs::write(filepath, bytes).map_err(|err| {
+            Error::FailedToWriteFile(Box::new(FailedToWriteFile {
+                filepath: filepath.display().to_string(),
+                reason: err.to_string(),
+            }))
+        })
+    };
+    // Raw images only hold the program, so values from DATA would be lost
+    let data = formats::initial_data(machine.memory(), machine.program_bytes());
+    if !with_header && !data.is_empty() {
+        return Err(Error::FailedToWriteFile(Box::new(FailedToWriteFile {
+            filepath: output.to_string(),
+            reason: String::from(
+                "binary images without a header cannot hold values from DATA, add --with-header",
+            ),
+        })));
+    }
+    write(
+        Path::new(output),
+        formats::serialise_image(machine.program(), data, with_header),
+    )?;
+    if with_map {
+        write(&source_map_path(output), machine.source_map().serialise())?;
+    }
+    Ok(())
+}
+
 pub fn run_interpreter<R: BufRead, W: Write>(
     filepath: &str,
-    tabsize: u8,
+    options: MachineOptions,
+    quiet: bool,
     reader: R,
     writer: W,
-) -> Result<(Vec<u8>, [u8; 13]), String> {
-    // Read in source file
-    let source = fs::read_to_string(filepath)
-        .map_err(|err| format!("Failed to read the file {}: {}", filepath, err))?;
+) -> Result<RunResult, Error> {
+    let machine = load_machine(filepath, false, options)?;
+    run_machine(machine, filepath, quiet, reader, writer)
+}
 
-    // Tokenize source code string
-    let tokenizer = Tokenizer::tokenize(&source, tabsize).map_err(|err| err.to_string())?;
+/// Build a reader that gives each whitespace separated value on its own line
+pub fn input_reader(values: &[String]) -> io::Cursor<String> {
+    let lines: Vec<&str> = values.iter().flat_map(|value| value.split_whitespace()).collect();
+    io::Cursor::new(lines.join("\n"))
+}
 
-    // Parse and load the instructions into memory
-    let (mut memory, program_bytes) =
-        Parser::parse(tokenizer.tokens).map_err(|err| err.to_string())?;
+/// Deterministic register values from 1 to 255 for --garbage-registers, using the
+/// SplitMix64 generator so that every seed gives a different pattern
+pub fn garbage_registers(seed: u64) -> [u8; REGISTER_COUNT as usize] {
+    let mut state = seed;
+    std::array::from_fn(|_| {
+        state = state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut value = state;
+        value = (value ^ (value >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        value = (value ^ (value >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        value ^= value >> 31;
+        (value % 255) as u8 + 1
+    })
+}
 
-    // Run the program
-    let free_memory = u8::MAX.wrapping_sub(program_bytes).wrapping_add(1);
-    let mut registers = [0; REGISTER_COUNT as usize];
+/// Describe how much of the 256 bytes of memory a program occupies
+pub fn memory_usage(program_bytes: u8) -> String {
+    let layout = MemoryLayout::new(program_bytes);
+    format!(
+        "{}/256 bytes in use, {} bytes free",
+        layout.code_end, layout.data_capacity
+    )
+}
 
+fn run_machine<R: BufRead, W: Write>(
+    mut machine: Machine,
+    filepath: &str,
+    quiet: bool,
+    reader: R,
+    mut writer: W,
+) -> Result<RunResult, Error> {
     // Print the program running message
-    good_print!(
-        "Running program '{}' ({}/256 bytes in use, {} bytes free)",
-        filepath,
-        program_bytes,
-        free_memory
-    );
+    if !quiet {
+        for warning in machine.warnings() {
+            eprintln!("{warning}");
+        }
+        good_write!(
+            writer,
+            "Running program '{}' ({})",
+            filepath,
+            memory_usage(machine.program_bytes())
+        );
+    }
 
     // Execute the program and handle errors
-    Interpreter::interpret_custom_io(
-        &mut memory,
-        &mut registers,
-        program_bytes,
-        reader,
-        writer,
-    )
-    .map_err(|err| err.to_string())?;
+    let result = machine.run(reader, &mut writer)?;
+
+    if !quiet {
+        good_write!(
+            writer,
+            "Program exited successfully ({} data bytes written)",
+            result.data_bytes_written
+        );
+    }
+
+    Ok(result)
+}
+
+/// Compare a program's output with the expected output in a file, line by line. Line
+/// endings are ignored, so "\r\n" and "\n" are treated the same.
+fn check_expected_output(expected_path: &str, output: &[u8]) -> Result<(), Error> {
+    let expected = fs::read_to_string(expected_path).map_err(|err| {
+        Error::FailedToReadFile(Box::new(FailedToReadFile {
+            filepath: expected_path.to_string(),
+            reason: err.to_string(),
+        }))
+    })?;
+    let output = String::from_utf8_lossy(output);
+    let mut expected_lines = expected.lines();
+    let mut output_lines = output.lines();
+    for line in 1.. {
+        match (expected_lines.next(), output_lines.next()) {
+            (None, None) => break,
+            (expected, actual) if expected != actual => {
+                return Err(Error::OutputMismatch(Box::new(OutputMismatch {
+                    filepath: expected_path.to_string(),
+                    line,
+                    expected: expected.map(String::from),
+                    actual: actual.map(String::from),
+                })));
+            }
+            _ => {}
+        }
+    }
+    Ok(())
+}
 
-    good_print!("Program exited successfully");
+/// Describe an instruction and list all of its valid signatures
+pub fn explain_opcode(source_opcode: SourceOpcode) -> String {
+    let signatures: String = SIGNATURE_TREE
+        .get_suggested_signatures_for_source_opcode(source_opcode)
+        .iter()
+        .map(|signature| {
+            let arguments: Vec<String> = signature.iter().map(|arg| arg.to_string()).collect();
+            if arguments.is_empty() {
+                format!("• {}\n", source_opcode)
+            } else {
+                format!("• {} {}\n", source_opcode, arguments.join(", "))
+            }
+        })
+        .collect();
+    format!("{} - {}\n{}", source_opcode, source_opcode.description(), signatures)
+}
 
-    let result_memory = memory[program_bytes as usize..].to_owned();
-    Ok((result_memory, registers))
+/// Tab-separated listing of every form of every instruction, one per line with the
+/// source opcode, its operands, and the name and value of the runtime opcode it assembles
+/// to. Forms are ordered by runtime opcode value.
+pub fn list_isa() -> String {
+    let mut listing = String::from("opcode\toperands\truntime_opcode\tvalue\n");
+    for runtime_opcode in (0..=u8::MAX).filter_map(|byte| RuntimeOpcode::try_from(byte).ok()) {
+        let (source_opcode, arguments) = SIGNATURE_TREE
+            .get_signature_for_runtime_opcode(runtime_opcode)
+            .expect("every runtime opcode has a signature");
+        let arguments: Vec<String> = arguments.iter().map(|arg| arg.to_string()).collect();
+        listing.push_str(&format!(
+            "{}\t{}\t{:?}\t{}\n",
+            source_opcode,
+            arguments.join(", "),
+            runtime_opcode,
+            runtime_opcode as u8
+        ));
+    }
+    listing
 }
 
 fn main() {
     // Command line arg handling
     let args = Args::parse();
 
-    if let Err(err) = run_interpreter(
-        &args.filepath,
-        args.tabsize,
-        BufReader::new(io::stdin()),
-        io::stdout(),
-    ) {
-        bad_print!("{}", err);
+    if let Some(opcode) = &args.explain {
+        match SourceOpcode::from_str(&opcode.to_uppercase()) {
+            Ok(source_opcode) => print!("{}", explain_opcode(source_opcode)),
+            Err(_) => {
+                bad_print!("Unknown instruction opcode '{}'", opcode);
+                process::exit(1);
+            }
+        }
+        return;
+    }
+    if args.list_isa {
+        print!("{}", list_isa());
+        return;
+    }
+
+    // These write to a single file, so running several programs would overwrite it
+    if args.filepaths.len() > 1 && (args.output.is_some() || args.profile_json.is_some()) {
+        bad_print!("--output and --profile-json can only be used with a single file");
+        process::exit(1);
+    }
+    let failed = run_each_file(&args.filepaths, io::stdout(), |filepath| {
+        run_file(&args, filepath)
+    });
+    if failed > 0 {
+        process::exit(1);
+    }
+}
+
+/// Call `run` for each file, carrying on past failures. When there is more than one file,
+/// a header is written before each and a summary at the end. Returns the number of files
+/// that failed.
+fn run_each_file<W: Write>(
+    filepaths: &[String],
+    mut writer: W,
+    mut run: impl FnMut(&str) -> Result<(), Error>,
+) -> usize {
+    let multiple = filepaths.len() > 1;
+    let mut failed = 0;
+    for filepath in filepaths {
+        if multiple {
+            good_write!(writer, "==> {} <==", filepath);
+        }
+        if let Err(err) = run(filepath) {
+            // Errors are already coloured by their Display
+            eprintln!("{err}");
+            failed += 1;
+        }
+    }
+    if multiple {
+        let summary = format!(
+            "{} of {} programs succeeded",
+            filepaths.len() - failed,
+            filepaths.len()
+        );
+        if failed == 0 {
+            good_write!(writer, "{}", summary);
+        } else {
+            let _ = writeln!(
+                writer,
+                "{style_bold}{color_red}{summary}{color_reset}{style_reset}"
+            );
+        }
+    }
+    failed
+}
+
+/// Load a single file and do whatever the arguments ask for with it
+fn run_file(args: &Args, filepath: &str) -> Result<(), Error> {
+    let options = MachineOptions {
+        tabsize: args.tabsize,
+        semicolon_comments: args.semicolon_comments,
+        defines: args.define.clone(),
+        deny_warnings: args.strict,
+        line_origins: LineOrigins::default(),
+    };
+    if let (Some(output), OutputFormat::Json) = (&args.output, args.format) {
+        export_json(filepath, &options, output)?;
+        if !args.quiet {
+            good_write!(io::stdout(), "Wrote JSON program to '{}'", output);
+        }
+        return Ok(());
     }
+    let machine = load_machine(filepath, args.binary, options)?;
+    if args.count_only {
+        println!("{}", memory_usage(machine.program_bytes()));
+        return Ok(());
+    }
+    if args.decode {
+        print!(
+            "{}",
+            disassembler::decode_dump(machine.memory(), machine.program_bytes())
+        );
+        return Ok(());
+    }
+    if args.disassemble {
+        print!(
+            "{}",
+            disassembler::disassemble(machine.memory(), machine.program_bytes())?
+        );
+        return Ok(());
+    }
+    if args.annotate {
+        print!(
+            "{}",
+            disassembler::annotate(machine.memory(), machine.program_bytes())?
+        );
+        return Ok(());
+    }
+    if let Some(output) = &args.output {
+        export_image(&machine, output, args.with_header, args.with_map)?;
+        if !args.quiet {
+            good_write!(
+                io::stdout(),
+                "Wrote {} bytes to '{}'",
+                machine.program_bytes(),
+                output
+            );
+        }
+        return Ok(());
+    }
+    let interpreter_options = InterpreterOptions {
+        stream: StreamOptions {
+            crlf_output: args.crlf_output,
+            signed_display: args.signed_display,
+            input_mode: args.input_mode,
+            print_prefix: args.print_prefix.clone(),
+        },
+        input_retries: args.input_retries.or(args.strict.then_some(0)),
+        break_on_write: args.break_on_write,
+        cycle_limit: (args.max_cycles != 0).then_some(args.max_cycles),
+    };
+    let mut machine = machine.with_interpreter_options(interpreter_options);
+    if args.garbage_registers {
+        machine = machine.with_registers(garbage_registers(args.seed.unwrap_or(0)));
+    }
+    if args.trace {
+        machine = machine.with_trace(BufWriter::new(io::stderr()));
+    }
+    if args.profile_json.is_some() {
+        machine = machine.with_cost_model(load_cost_model(args.cost_model.as_deref())?);
+    }
+    // Each program gets the --input values from the start, while stdin is shared so that
+    // each program carries on reading where the last one stopped
+    let reader: Box<dyn BufRead> = if args.input.is_empty() {
+        Box::new(io::stdin().lock())
+    } else {
+        Box::new(input_reader(&args.input))
+    };
+    let result = match &args.expect {
+        Some(expected_path) => {
+            // Status messages would end up in the captured output, so leave them out
+            let mut output = Vec::new();
+            let result = run_machine(machine, filepath, true, reader, &mut output)?;
+            check_expected_output(expected_path, &output)?;
+            if !args.quiet {
+                good_write!(io::stdout(), "Output matches '{}'", expected_path);
+            }
+            result
+        }
+        None => run_machine(machine, filepath, args.quiet, reader, io::stdout())?,
+    };
+    if let (Some(profile), Some(output)) = (&result.profile, &args.profile_json) {
+        write_profile(profile, output)?;
+    }
+    if let Some(output) = &args.dump_image {
+        write_memory_image(&result.memory, output)?;
+    }
+    if args.dump_state {
+        print!("{}", result.dump_state());
+    }
+    Ok(())
 }
 
 #[cfg(test)]
 mod tests {
-    use io::Cursor;
+    use aqa_asm::{
+        analysis::{MayRunPastEnd, Warning},
+        formats::FormatError,
+        interpreter::RuntimeError,
+    };
+    use io::{BufReader, Cursor};
 
     use super::*;
 
@@ -119,8 +755,11 @@ mod tests {
         let reader = BufReader::new(Cursor::new(inputs));
         let writer = Cursor::new(&mut output);
 
-        let (_, registers) = run_interpreter("examples/addition.aqasm", 4, reader, writer).unwrap();
-        assert_eq!(registers[2], 130);
+        let result = run_interpreter("examples/addition.aqasm", MachineOptions::default(), true, reader, writer).unwrap();
+        assert_eq!(result.registers[2], 130);
+        assert_eq!(result.program_bytes, 11);
+        assert_eq!(result.data_memory.len(), 245);
+        assert_eq!(result.instruction_count, 5);
     }
 
     #[test]
@@ -132,9 +771,9 @@ mod tests {
         let reader = BufReader::new(Cursor::new(inputs));
         let writer = Cursor::new(&mut output);
 
-        let (_, registers) = run_interpreter("examples/division.aqasm", 4, reader, writer).unwrap();
-        assert_eq!(registers[0], 5);
-        assert_eq!(registers[1], 5);
+        let result = run_interpreter("examples/division.aqasm", MachineOptions::default(), true, reader, writer).unwrap();
+        assert_eq!(result.registers[0], 5);
+        assert_eq!(result.registers[1], 5);
     }
 
     #[test]
@@ -146,8 +785,8 @@ mod tests {
         let reader = BufReader::new(Cursor::new(inputs));
         let writer = Cursor::new(&mut output);
 
-        let (_, registers) = run_interpreter("examples/subtraction.aqasm", 4, reader, writer).unwrap();
-        assert_eq!(registers[2], 29);
+        let result = run_interpreter("examples/subtraction.aqasm", MachineOptions::default(), true, reader, writer).unwrap();
+        assert_eq!(result.registers[2], 29);
     }
 
     #[test]
@@ -159,8 +798,8 @@ mod tests {
         let reader = BufReader::new(Cursor::new(inputs));
         let writer = Cursor::new(&mut output);
 
-        let (_, registers) = run_interpreter("examples/multiplication.aqasm", 4, reader, writer).unwrap();
-        assert_eq!(registers[3], 25);
+        let result = run_interpreter("examples/multiplication.aqasm", MachineOptions::default(), true, reader, writer).unwrap();
+        assert_eq!(result.registers[3], 25);
     }
 
     #[test]
@@ -172,8 +811,8 @@ mod tests {
         let reader = BufReader::new(Cursor::new(inputs));
         let writer = Cursor::new(&mut output);
 
-        let (_, registers) = run_interpreter("examples/hamming_weight.aqasm", 4, reader, writer).unwrap();
-        assert_eq!(registers[2], 2);
+        let result = run_interpreter("examples/hamming_weight.aqasm", MachineOptions::default(), true, reader, writer).unwrap();
+        assert_eq!(result.registers[2], 2);
     }
 
     #[test]
@@ -185,8 +824,8 @@ mod tests {
         let reader = BufReader::new(Cursor::new(inputs));
         let writer = Cursor::new(&mut output);
 
-        let (_, registers) = run_interpreter("examples/do_while_loop.aqasm", 4, reader, writer).unwrap();
-        assert_eq!(registers[1], 4);
+        let result = run_interpreter("examples/do_while_loop.aqasm", MachineOptions::default(), true, reader, writer).unwrap();
+        assert_eq!(result.registers[1], 4);
     }
 
     #[test]
@@ -198,28 +837,485 @@ mod tests {
         let reader = BufReader::new(Cursor::new(inputs));
         let writer = Cursor::new(&mut output);
 
-        let (_, _) = run_interpreter("examples/for_loop.aqasm", 4, reader, writer).unwrap();
+        run_interpreter("examples/for_loop.aqasm", MachineOptions::default(), true, reader, writer).unwrap();
         let output_str = String::from_utf8(output).unwrap();
         let expected_output: String = (0..=254).map(|n| format!("{}\n", n)).collect();
         assert_eq!(output_str, expected_output);
     }
 
+    #[test]
+    fn test_input_argument() {
+        let mut output: Vec<u8> = Vec::new();
+        let values = [String::from("105"), String::from(" 25\n")];
+        let reader = input_reader(&values);
+        let result = run_interpreter("examples/addition.aqasm", MachineOptions::default(), true, reader, &mut output).unwrap();
+        assert_eq!(result.registers[2], 130);
+        assert_eq!(String::from_utf8(output).unwrap(), "130\n");
+
+        let reader = input_reader(&[String::from("105 25")]);
+        let result = run_interpreter("examples/addition.aqasm", MachineOptions::default(), true, reader, io::sink()).unwrap();
+        assert_eq!(result.registers[2], 130);
+    }
+
+    #[test]
+    fn test_quiet_only_prints_program_output() {
+        let mut output: Vec<u8> = Vec::new();
+        let reader = BufReader::new(Cursor::new("105\n25"));
+        let writer = Cursor::new(&mut output);
+        run_interpreter("examples/addition.aqasm", MachineOptions::default(), true, reader, writer).unwrap();
+        assert_eq!(String::from_utf8(output).unwrap(), "130\n");
+
+        let mut output: Vec<u8> = Vec::new();
+        let reader = BufReader::new(Cursor::new("105\n25"));
+        let writer = Cursor::new(&mut output);
+        run_interpreter("examples/addition.aqasm", MachineOptions::default(), false, reader, writer).unwrap();
+        let output = String::from_utf8(output).unwrap();
+        assert!(output.contains("Running program 'examples/addition.aqasm'"));
+        assert!(output.contains("130\n"));
+        assert!(output.contains("Program exited successfully (0 data bytes written)"));
+    }
+
+    #[test]
+    fn test_export_and_load_image() {
+        let machine =
+            load_machine("examples/addition.aqasm", false, MachineOptions::default()).unwrap();
+        for with_header in [false, true] {
+            let path = std::env::temp_dir().join(format!("aqa_asm_export_{with_header}.bin"));
+            let path = path.to_str().unwrap();
+            export_image(&machine, path, with_header, false).unwrap();
+
+            let loaded = load_machine(path, true, MachineOptions::default()).unwrap();
+            assert_eq!(loaded.program(), machine.program());
+
+            let mut output: Vec<u8> = Vec::new();
+            let reader = BufReader::new(Cursor::new("105\n25"));
+            let result = run_machine(loaded, path, true, reader, Cursor::new(&mut output)).unwrap();
+            assert_eq!(result.registers[2], 130);
+            fs::remove_file(path).unwrap();
+        }
+
+        let machine = Machine::from_source(
+            "LDR R0, table\nHALT\n.data\ntable: DATA #1\n",
+            MachineOptions::default(),
+        )
+        .unwrap();
+        let path = std::env::temp_dir().join("aqa_asm_export_data.bin");
+        let path = path.to_str().unwrap();
+        assert!(matches!(
+            export_image(&machine, path, false, false),
+            Err(Error::FailedToWriteFile(_))
+        ));
+        assert!(!Path::new(path).exists());
+
+        // Images with a header keep the data after the program
+        export_image(&machine, path, true, false).unwrap();
+        let loaded = load_machine(path, true, MachineOptions::default()).unwrap();
+        assert_eq!(loaded.memory(), machine.memory());
+        let result = run_machine(loaded, path, true, io::empty(), io::sink()).unwrap();
+        assert_eq!(result.registers[0], 1);
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_load_largest_binary() {
+        // A full 256 byte image would leave no room for data, so it is rejected
+        let path = std::env::temp_dir().join("aqa_asm_too_large.bin");
+        let path = path.to_str().unwrap();
+        fs::write(path, [RuntimeOpcode::HALT as u8; 256]).unwrap();
+        assert!(matches!(
+            load_machine(path, true, MachineOptions::default()),
+            Err(Error::FormatError(FormatError::ImageTooLarge(_)))
+        ));
+
+        // One byte smaller runs, with a single byte of data memory
+        let mut image = vec![RuntimeOpcode::NOP as u8; 254];
+        image.push(RuntimeOpcode::HALT as u8);
+        fs::write(path, image).unwrap();
+        let machine = load_machine(path, true, MachineOptions::default()).unwrap();
+        let result = run_machine(machine, path, true, io::empty(), io::sink()).unwrap();
+        assert_eq!(result.program_bytes, 255);
+        assert_eq!(result.data_memory.len(), 1);
+        assert_eq!(result.instruction_count, 255);
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_dump_memory_image() {
+        let source = std::env::temp_dir().join("aqa_asm_dump_image.aqasm");
+        let source = source.to_str().unwrap();
+        fs::write(source, "MOV R0, #42\nSTR R0, 200\nHALT\n").unwrap();
+        let result = run_interpreter(
+            source,
+            MachineOptions::default(),
+            true,
+            io::empty(),
+            io::sink(),
+        )
+        .unwrap();
+
+        let path = std::env::temp_dir().join("aqa_asm_dump_image.bin");
+        let path = path.to_str().unwrap();
+        write_memory_image(&result.memory, path).unwrap();
+        let image = fs::read(path).unwrap();
+        assert_eq!(image.len(), 256);
+        assert_eq!(
+            image[..result.program_bytes as usize],
+            [
+                RuntimeOpcode::MOV_LITERAL as u8,
+                0,
+                42,
+                RuntimeOpcode::STR as u8,
+                0,
+                200,
+                RuntimeOpcode::HALT as u8,
+            ]
+        );
+        // Memory references are relative to the end of the program
+        assert_eq!(image[result.program_bytes as usize + 200], 42);
+        assert_eq!(image[result.program_bytes as usize..], result.data_memory);
+        fs::remove_file(source).unwrap();
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_export_json() {
+        let path = std::env::temp_dir().join("aqa_asm_export.json");
+        let path = path.to_str().unwrap();
+        export_json("examples/for_loop.aqasm", &MachineOptions::default(), path).unwrap();
+        let json = fs::read_to_string(path).unwrap();
+        assert!(json.starts_with(r#"{"version":1,"instructions":[{"offset":0,"mnemonic":"PRINT","operands":["R0"],"line":7},"#));
+        assert!(json.contains(r#"{"offset":9,"mnemonic":"BLT","operands":["body"],"line":11}"#));
+        assert!(json.contains(r#""labels":{"body":0,"condition":6}"#));
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_assemble_only() {
+        let path = std::env::temp_dir().join("aqa_asm_assemble_only.bin");
+        let path = path.to_str().unwrap();
+        let args = Args::try_parse_from([
+            "aqa-asm",
+            "examples/addition.aqasm",
+            "--assemble-only",
+            path,
+            "--quiet",
+        ])
+        .unwrap();
+        run_file(&args, "examples/addition.aqasm").unwrap();
+
+        let machine =
+            load_machine("examples/addition.aqasm", false, MachineOptions::default()).unwrap();
+        let bytes = fs::read(path).unwrap();
+        assert_eq!(bytes.len(), machine.program_bytes() as usize);
+        assert_eq!(bytes, machine.program());
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_export_and_load_source_map() {
+        let machine =
+            load_machine("examples/addition.aqasm", false, MachineOptions::default()).unwrap();
+        let path = std::env::temp_dir().join("aqa_asm_export_map.bin");
+        let path = path.to_str().unwrap();
+        export_image(&machine, path, true, true).unwrap();
+
+        let loaded = load_machine(path, true, MachineOptions::default()).unwrap();
+        assert_eq!(loaded.source_map(), machine.source_map());
+
+        // Running out of input fails on the first INPUT, which is on line 2
+        let err = run_machine(loaded, path, true, io::empty(), io::sink()).unwrap_err();
+        let Error::RuntimeError(RuntimeError::WithSourceLine(err)) = err else {
+            panic!("expected the error to carry a source line, got {err:?}");
+        };
+        assert_eq!(err.line, 2);
+        fs::remove_file(path).unwrap();
+        fs::remove_file(source_map_path(path)).unwrap();
+    }
+
+    #[test]
+    fn test_run_each_file() {
+        let filepaths = [
+            String::from("examples/addition.aqasm"),
+            String::from("examples/subtraction.aqasm"),
+        ];
+        let mut headers: Vec<u8> = Vec::new();
+        let mut outputs = Vec::new();
+        let failed = run_each_file(&filepaths, &mut headers, |filepath| {
+            let mut output: Vec<u8> = Vec::new();
+            let reader = BufReader::new(Cursor::new("105\n25"));
+            run_interpreter(filepath, MachineOptions::default(), true, reader, &mut output)?;
+            outputs.push(String::from_utf8(output).unwrap());
+            Ok(())
+        });
+        assert_eq!(failed, 0);
+        assert_eq!(outputs, ["130\n", "80\n"]);
+        let headers = String::from_utf8(headers).unwrap();
+        assert!(headers.contains("==> examples/addition.aqasm <=="));
+        assert!(headers.contains("==> examples/subtraction.aqasm <=="));
+        assert!(headers.contains("2 of 2 programs succeeded"));
+
+        let filepaths = [String::from("examples/addition.aqasm"), String::from("missing.aqasm")];
+        let mut headers: Vec<u8> = Vec::new();
+        let failed = run_each_file(&filepaths, &mut headers, |filepath| {
+            let reader = BufReader::new(Cursor::new("1\n2"));
+            run_interpreter(filepath, MachineOptions::default(), true, reader, io::sink())?;
+            Ok(())
+        });
+        assert_eq!(failed, 1);
+        assert!(String::from_utf8(headers)
+            .unwrap()
+            .contains("1 of 2 programs succeeded"));
+    }
+
+    #[test]
+    fn test_memory_usage() {
+        let machine =
+            load_machine("examples/addition.aqasm", false, MachineOptions::default()).unwrap();
+        assert_eq!(
+            memory_usage(machine.program_bytes()),
+            "11/256 bytes in use, 245 bytes free"
+        );
+        assert_eq!(memory_usage(0), "0/256 bytes in use, 256 bytes free");
+    }
+
+    #[test]
+    fn test_explain_opcode() {
+        let explanation = explain_opcode(SourceOpcode::MOV);
+        assert!(explanation.starts_with("MOV - Copy an operand into a register\n"));
+        assert!(explanation.contains("• MOV register, register\n"));
+        assert!(explanation.contains("• MOV register, literal\n"));
+
+        assert_eq!(
+            explain_opcode(SourceOpcode::HALT),
+            "HALT - Terminate the program\n• HALT\n"
+        );
+    }
+
+    #[test]
+    fn test_check_expected_output() {
+        let path = std::env::temp_dir().join("aqa_asm_expected_output.txt");
+        let path = path.to_str().unwrap();
+        fs::write(path, "130\r\n7\n").unwrap();
+        assert_eq!(check_expected_output(path, b"130\n7\n"), Ok(()));
+
+        assert_eq!(
+            check_expected_output(path, b"130\n8\n"),
+            Err(Error::OutputMismatch(Box::new(OutputMismatch {
+                filepath: path.to_string(),
+                line: 2,
+                expected: Some(String::from("7")),
+                actual: Some(String::from("8")),
+            })))
+        );
+        assert_eq!(
+            check_expected_output(path, b"130\n"),
+            Err(Error::OutputMismatch(Box::new(OutputMismatch {
+                filepath: path.to_string(),
+                line: 2,
+                expected: Some(String::from("7")),
+                actual: None,
+            })))
+        );
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_include_error_locations() {
+        let dir = std::env::temp_dir().join("aqa_asm_include_locations");
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let main = dir.join("main.aqasm");
+        let lib = dir.join("lib.aqasm");
+        let located = |err: Error, filepath: &Path, location: &str| {
+            let expected = format!("{}, {location}", filepath.display());
+            assert!(
+                err.to_string().contains(&expected),
+                "expected '{expected}' in {err}"
+            );
+        };
+
+        fs::write(
+            &main,
+            "MOV R0, #1\n.include \"lib.aqasm\"\nB missing\nHALT\n",
+        )
+        .unwrap();
+        fs::write(&lib, "NOP\nMOV R0, #300\n").unwrap();
+        let load = || load_machine(main.to_str().unwrap(), false, MachineOptions::default());
+        located(load().unwrap_err(), &lib, "Line 2, Columns 9-12");
+
+        fs::write(&lib, "NOP\nNOP\n").unwrap();
+        located(load().unwrap_err(), &main, "Line 3, Column 3");
+
+        fs::write(&main, "MOV R0, #1\n.include \"lib.aqasm\"\nHALT\n").unwrap();
+        fs::write(&lib, "NOP\nINPUT R1\n").unwrap();
+        let err = run_machine(load().unwrap(), "main.aqasm", true, io::empty(), io::sink());
+        located(err.unwrap_err(), &lib, "Line 2 :: INPUT R1");
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_strict_missing_halt() {
+        // Halts when run, but the BEQ would carry on into the data if it was not taken
+        let path = std::env::temp_dir().join("aqa_asm_strict_missing_halt.aqasm");
+        let path = path.to_str().unwrap();
+        fs::write(
+            path,
+            "MOV R0, #1\nB check\ndone: HALT\ncheck: CMP R0, #1\nBEQ done\n",
+        )
+        .unwrap();
+        let run = |options| run_interpreter(path, options, true, Cursor::new(""), io::sink());
+
+        assert!(run(MachineOptions::default()).is_ok());
+        let strict = MachineOptions {
+            deny_warnings: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            run(strict).unwrap_err(),
+            Error::Warnings(vec![Warning::MayRunPastEnd(Box::new(MayRunPastEnd {
+                line: 5
+            }))])
+        );
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_read_source_from_stdin() {
+        struct FailingReader;
+
+        impl Read for FailingReader {
+            fn read(&mut self, _buf: &mut [u8]) -> io::Result<usize> {
+                Err(io::Error::new(io::ErrorKind::BrokenPipe, "pipe closed"))
+            }
+        }
+
+        assert_eq!(read_source_from(&b"HALT\n"[..]).unwrap(), "HALT\n");
+        assert_eq!(
+            read_source_from(&b""[..]).unwrap_err(),
+            Error::FailedToReadFile(Box::new(FailedToReadFile {
+                filepath: String::from("<stdin>"),
+                reason: String::from("no source code was given"),
+            }))
+        );
+        assert_eq!(
+            read_source_from(FailingReader).unwrap_err(),
+            Error::FailedToReadFile(Box::new(FailedToReadFile {
+                filepath: String::from("<stdin>"),
+                reason: String::from("pipe closed"),
+            }))
+        );
+        assert!(read_source_from(&[0xFF, 0xFE][..]).is_err());
+    }
+
+    #[test]
+    fn test_run_source_from_stdin() {
+        let source = read_source_from(Cursor::new("INPUT R0\nPRINT R0\nHALT\n")).unwrap();
+        let machine = Machine::from_source(&source, MachineOptions::default()).unwrap();
+        let reader = input_reader(&[String::from("7")]);
+        let mut output = Vec::new();
+
+        let result = run_machine(machine, STDIN_FILEPATH, true, reader, &mut output).unwrap();
+        assert_eq!(result.registers[0], 7);
+        assert_eq!(output, b"7\n");
+    }
+
+    #[test]
+    fn test_load_unreadable_source() {
+        let failed = |filepath: &str, reason: &str| {
+            Error::FailedToReadFile(Box::new(FailedToReadFile {
+                filepath: filepath.to_string(),
+                reason: reason.to_string(),
+            }))
+        };
+
+        let dir = std::env::temp_dir();
+        let dir = dir.to_str().unwrap();
+        for binary in [false, true] {
+            assert_eq!(
+                load_machine(dir, binary, MachineOptions::default()).unwrap_err(),
+                failed(dir, "path is a directory")
+            );
+        }
+
+        let path = std::env::temp_dir().join("aqa_asm_not_text.bin");
+        let path = path.to_str().unwrap();
+        fs::write(path, [0xFF, 0xFE, RuntimeOpcode::HALT as u8]).unwrap();
+        assert_eq!(
+            load_machine(path, false, MachineOptions::default()).unwrap_err(),
+            failed(path, "file is not valid UTF-8 text; did you mean --binary?")
+        );
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_error_shows_source_line() {
+        let path = std::env::temp_dir().join("aqa_asm_bad_character.aqasm");
+        let path = path.to_str().unwrap();
+        fs::write(path, "MOV R0, @1\n").unwrap();
+        let err = load_machine(path, false, MachineOptions::default()).unwrap_err();
+        fs::remove_file(path).unwrap();
+        assert_eq!(
+            err.to_string(),
+            format!(
+                "{color_red}{style_bold}Line 1, Column 9 :: Unexpected character: '@'{color_reset}{style_reset}\nMOV R0, @1\n        ^"
+            )
+        );
+    }
+
+    #[test]
+    fn test_garbage_registers() {
+        let registers = garbage_registers(7);
+        assert_eq!(registers, garbage_registers(7));
+        assert_ne!(registers, garbage_registers(8));
+        assert!(registers.iter().all(|&value| value != 0));
+
+        // Reading R0 without setting it only works by accident when registers start at 0
+        let source = "ADD R1, R0, #1\nPRINT R1\nHALT\n";
+        let mut outputs = Vec::new();
+        for machine in [
+            Machine::from_source(source, MachineOptions::default()).unwrap(),
+            Machine::from_source(source, MachineOptions::default())
+                .unwrap()
+                .with_registers(registers),
+        ] {
+            let mut output = Vec::new();
+            run_machine(machine, "test", true, io::empty(), &mut output).unwrap();
+            outputs.push(String::from_utf8(output).unwrap());
+        }
+        assert_eq!(outputs[0], "1\n");
+        assert_eq!(outputs[1], format!("{}\n", registers[0].wrapping_add(1)));
+    }
+
+    #[test]
+    fn test_list_isa() {
+        let listing = list_isa();
+        assert!(listing.starts_with("opcode\toperands\truntime_opcode\tvalue\n"));
+        assert!(listing.contains("\nADD\tregister, register, register\tADD_REGISTER\t3\n"));
+        assert!(listing.contains("\nADD\tregister, register, literal\tADD_LITERAL\t4\n"));
+        assert!(listing.contains("\nHALT\t\tHALT\t"));
+        assert_eq!(listing.lines().count(), 1 + 49);
+    }
+
     #[test]
     fn test_run_interpreter_file_not_found() {
         // Arrange
         let invalid_file = "invalid_file.asm";
-        let tabsize = 4;
+        let options = MachineOptions::default();
 
         // Act
         let result = run_interpreter(
             invalid_file,
-            tabsize,
+            options,
+            false,
             BufReader::new(io::stdin()),
             io::stdout(),
         );
 
         // Assert
         assert!(result.is_err());
-        assert!(result.unwrap_err().contains("Failed to read the file"));
+        assert!(result
+            .unwrap_err()
+            .to_string()
+            .contains("Failed to read the file"));
     }
 }
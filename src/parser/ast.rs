@@ -0,0 +1,149 @@
+use super::{Parser, ParserError};
+use crate::{
+    interpreter::instruction::{operand::Operand, source_opcode::SourceOpcode},
+    tokenizer::{Comment, Token, TokenKind},
+};
+
+/// Instruction as it was written in the source code, along with the comments that
+/// explain it
+#[derive(Debug, Clone, PartialEq)]
+pub struct Instruction {
+    /// Byte offset the instruction is loaded at
+    pub offset: u8,
+    pub source_opcode: SourceOpcode,
+    pub opcode_token: Token,
+    pub operands: Vec<Token>,
+    /// Comments before the instruction that are not on the same line as another one
+    pub leading_comments: Vec<String>,
+    /// Comment after the instruction on the same line
+    pub trailing_comment: Option<String>,
+}
+
+impl Parser<'_> {
+    /// Parse the tokens into a list of instructions for documentation tools, rather than
+    /// loading them into memory. Each comment is attached to an instruction:
+    /// * A comment after an instruction on the same line trails that instruction
+    /// * Any other comment leads the next instruction, and is dropped if there isn't one
+    pub fn parse_to_ast(
+        tokens: &[Token],
+        comments: &[Comment],
+    ) -> Result<Vec<Instruction>, ParserError> {
+        let tokens = Parser::expand_constants(tokens)?;
+        // Make sure the program would assemble, so the tokens are known to be in order
+        let (labels, program_size) = Parser::label_offsets(&tokens)?;
+        Parser::parse_resolved(tokens.clone(), labels, program_size)?;
+        Ok(Parser::build_ast(&tokens, comments))
+    }
+
+    /// Build the instructions of a program that is known to assemble, from tokens that
+    /// constants have been substituted into
+    pub(crate) fn build_ast(tokens: &[Token], comments: &[Comment]) -> Vec<Instruction> {
+        let mut instructions: Vec<Instruction> = Vec::new();
+        let mut offset = 0;
+        let mut token_iter = tokens.iter().peekable();
+        while let Some(token) = token_iter.next() {
+            let TokenKind::Opcode(source_opcode) = token.kind else {
+                continue;
+            };
+            let mut operands = Vec::new();
+            while let Some(token) = token_iter
+                .next_if(|token| !matches!(token.kind, TokenKind::Newline | TokenKind::Semicolon))
+            {
+                if let TokenKind::Operand(_) = token.kind {
+                    operands.push(token.clone());
+                }
+            }
+            // Same sizes as Parser::resolve_labels
+            let size = match (source_opcode, operands.first().map(|token| token.kind)) {
+                (SourceOpcode::NOP, Some(TokenKind::Operand(Operand::Literal(count)))) => count,
+                (SourceOpcode::INC | SourceOpcode::DEC, _) => 3,
+                _ => 1 + operands.len() as u8,
+            };
+            instructions.push(Instruction {
+                offset,
+                source_opcode,
+                opcode_token: token.clone(),
+                operands,
+                leading_comments: Vec::new(),
+                trailing_comment: None,
+            });
+            offset += size;
+        }
+
+        for comment in comments {
+            let previous = instructions
+                .iter_mut()
+                .rev()
+                .find(|instruction| instruction.opcode_token.offset < comment.offset);
+            if let Some(instruction) =
+                previous.filter(|instruction| instruction.opcode_token.line == comment.line)
+            {
+                match &mut instruction.trailing_comment {
+                    Some(trailing) => {
+                        trailing.push(' ');
+                        trailing.push_str(&comment.text);
+                    }
+                    None => instruction.trailing_comment = Some(comment.text.clone()),
+                }
+            } else if let Some(instruction) = instructions
+                .iter_mut()
+                .find(|instruction| instruction.opcode_token.offset > comment.offset)
+            {
+                instruction.leading_comments.push(comment.text.clone());
+            }
+        }
+        instructions
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tokenizer::Tokenizer;
+
+    fn parse(source: &str) -> Vec<Instruction> {
+        let tokenizer = Tokenizer::tokenize(source, 4).unwrap();
+        Parser::parse_to_ast(&tokenizer.tokens, &tokenizer.comments).unwrap()
+    }
+
+    #[test]
+    fn test_trailing_comments() {
+        let instructions = parse("MOV R0, #5 // five\nINC R0; HALT /* done */\n");
+        assert_eq!(instructions.len(), 3);
+        assert_eq!(instructions[0].source_opcode, SourceOpcode::MOV);
+        assert_eq!(instructions[0].offset, 0);
+        assert_eq!(instructions[0].operands.len(), 2);
+        assert_eq!(instructions[0].trailing_comment.as_deref(), Some("five"));
+        assert_eq!(instructions[1].offset, 3);
+        assert_eq!(instructions[1].trailing_comment, None);
+        assert_eq!(instructions[2].offset, 6);
+        assert_eq!(instructions[2].trailing_comment.as_deref(), Some("done"));
+        assert!(instructions
+            .iter()
+            .all(|instruction| instruction.leading_comments.is_empty()));
+    }
+
+    #[test]
+    fn test_leading_comments() {
+        let instructions = parse(
+            "// Count down from 3\n/* using R0 */\nMOV R0, #3\nloop: // each step\nSUB R0, R0, #1\nHALT\n// the end\n",
+        );
+        assert_eq!(
+            instructions[0].leading_comments,
+            vec![String::from("Count down from 3"), String::from("using R0")]
+        );
+        assert_eq!(
+            instructions[1].leading_comments,
+            vec![String::from("each step")]
+        );
+        assert_eq!(instructions[1].trailing_comment, None);
+        assert!(instructions[2].leading_comments.is_empty());
+        assert_eq!(instructions[2].trailing_comment, None);
+    }
+
+    #[test]
+    fn test_parse_to_ast_errors() {
+        let tokenizer = Tokenizer::tokenize("MOV R0\n", 4).unwrap();
+        assert!(Parser::parse_to_ast(&tokenizer.tokens, &tokenizer.comments).is_err());
+    }
+}
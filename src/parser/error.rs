@@ -1,6 +1,11 @@
 use crate::{
-    interpreter::instruction::{
-        operand::Operand, signature::SIGNATURE_TREE, source_opcode::SourceOpcode,
+    interpreter::{
+        instruction::{
+            operand::Operand,
+            signature::{SignatureArgument, SIGNATURE_TREE},
+            source_opcode::SourceOpcode,
+        },
+        MAX_PROGRAM_BYTES,
     },
     tokenizer::{Token, TokenKind},
 };
@@ -15,89 +20,160 @@ pub enum ParserError {
     ExpectedOperand(Box<ExpectedOperand>),
     /// Expected a token out of a list of possible choices but received something else
     ExpectedTokenKind(Box<ExpectedTokenKind>),
+    /// Two instructions were written without a line delimeter between them
+    MissingInstructionDelimeter(Box<MissingInstructionDelimeter>),
     /// Expected an operand but received something else
     InvalidLabel(Box<InvalidLabel>),
     /// Signature for an instruction is incorrect
     InvalidInstructionSignature(Box<InvalidInstructionSignature>),
+    /// An operand is the wrong kind for its position in an instruction
+    OperandTypeMismatch(Box<OperandTypeMismatch>),
+    /// An operand is the right kind but its value does not make sense for what the
+    /// instruction does with it, such as shifting by 8 or more
+    OperandOutOfRange(Box<OperandOutOfRange>),
     /// Same label has been defined in multiple places
     LabelDuplicateDefinition(Box<LabelDuplicateDefinition>),
-    /// Program exceeds memory limit (256 bytes),
+    /// Branch to a label that is past the last instruction
+    BranchIntoData(Box<BranchIntoData>),
+    /// Branch to a byte offset that is not the start of an instruction
+    InvalidBranchTarget(Box<InvalidBranchTarget>),
+    /// Instruction after '.data', where only labels and reserved space are allowed
+    CodeInDataSection(Box<CodeInDataSection>),
+    /// '.space' before the data section has started
+    SpaceOutsideDataSection(Box<SpaceOutsideDataSection>),
+    /// Memory reference to a label that is not in the data section
+    NotADataLabel(Box<NotADataLabel>),
+    /// 'DATA' before the data section has started
+    DataOutsideDataSection(Box<DataOutsideDataSection>),
+    /// 'DATA' value that is not a literal or number
+    InvalidDataValue(Box<InvalidDataValue>),
+    /// '.equ' that is not followed by a name and a literal value
+    InvalidConstantDefinition(Box<InvalidConstantDefinition>),
+    /// Constant defined more than once
+    ConstantDuplicateDefinition(Box<ConstantDuplicateDefinition>),
+    /// Name defined as both a label and a constant
+    SymbolKindConflict(Box<SymbolKindConflict>),
+    /// Program exceeds memory limit (MAX_PROGRAM_BYTES bytes)
     ProgramTooLarge,
+    /// More than one error, in the order they appear in the source
+    Multiple(Vec<ParserError>),
 }
 
 impl std::error::Error for ParserError {}
 
-impl fmt::Display for ParserError {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "{color_red}{style_bold}")?;
+impl ParserError {
+    /// `Ok` if there are no errors, the error itself if there is one and
+    /// [`ParserError::Multiple`] otherwise
+    pub fn from_errors(mut errors: Vec<ParserError>) -> Result<(), ParserError> {
+        match errors.len() {
+            0 => Ok(()),
+            1 => Err(errors.remove(0)),
+            _ => Err(ParserError::Multiple(errors)),
+        }
+    }
+
+    /// Every error this one is made up of
+    pub fn into_errors(self) -> Vec<ParserError> {
         match self {
-            ParserError::ExpectedOpcode(err) => write!(
-                f,
-                "Line {}, Column {} :: Expected instruction opcode but found token {}",
-                err.got.line,
-                err.got.col,
+            ParserError::Multiple(errors) => errors,
+            err => vec![err],
+        }
+    }
+
+    /// Line and column the error is at, or `None` if it is not about a single place in
+    /// the source, such as reaching the end of the file
+    pub fn position(&self) -> Option<(usize, usize)> {
+        let token = match self {
+            ParserError::ExpectedOpcode(err) => &err.got,
+            ParserError::ExpectedOperand(err) => err.got.as_ref()?,
+            ParserError::ExpectedTokenKind(err) => err.got.as_ref()?,
+            ParserError::MissingInstructionDelimeter(err) => &err.got,
+            ParserError::InvalidLabel(err) => &err.token,
+            ParserError::InvalidInstructionSignature(err) => &err.opcode_token,
+            ParserError::OperandTypeMismatch(err) => &err.token,
+            ParserError::OperandOutOfRange(err) => &err.token,
+            ParserError::LabelDuplicateDefinition(err) => return Some((err.line, err.col)),
+            ParserError::BranchIntoData(err) => &err.token,
+            ParserError::InvalidBranchTarget(err) => &err.token,
+            ParserError::CodeInDataSection(err) => &err.token,
+            ParserError::SpaceOutsideDataSection(err) => &err.token,
+            ParserError::NotADataLabel(err) => &err.token,
+            ParserError::DataOutsideDataSection(err) => &err.token,
+            ParserError::InvalidDataValue(err) => &err.token,
+            ParserError::InvalidConstantDefinition(err) => &err.token,
+            ParserError::ConstantDuplicateDefinition(err) => return Some((err.line, err.col)),
+            ParserError::SymbolKindConflict(err) => return Some((err.line, err.col)),
+            ParserError::ProgramTooLarge => return None,
+            ParserError::Multiple(errors) => return errors.first()?.position(),
+        };
+        Some((token.line, token.col))
+    }
+
+    /// Description of the error without its position
+    pub fn message(&self) -> String {
+        match self {
+            ParserError::ExpectedOpcode(err) => format!(
+                "Expected instruction opcode but found token {}",
                 &err.got.get_token_debug_repr(),
             ),
-            ParserError::ExpectedOperand(err) => match &err.got {
-                Some(token) => write!(
-                    f,
-                    "Line {}, Column {} :: Expected operand but found token {}",
-                    token.line,
-                    token.col,
+            ParserError::ExpectedOperand(err) => match (&err.got, err.source_opcode) {
+                (Some(token), Some(source_opcode)) => format!(
+                    "Expected operand of {} but found token {}",
+                    source_opcode,
+                    &token.get_token_debug_repr()
+                ),
+                (Some(token), None) => format!(
+                    "Expected operand but found token {}",
                     &token.get_token_debug_repr()
                 ),
-                None => write!(f, "Expected operand but found EOF"),
+                (None, Some(source_opcode)) => format!(
+                    "Unexpected end of file while parsing operands of {}",
+                    source_opcode
+                ),
+                (None, None) => String::from("Expected operand but found EOF"),
             },
             ParserError::ExpectedTokenKind(err) => {
-                assert!(err.candidates.len() > 0);
+                assert!(!err.candidates.is_empty());
+                let found = match &err.got {
+                    Some(token) => format!("token {}", &token.get_token_debug_repr()),
+                    None => String::from("EOF"),
+                };
                 if err.candidates.len() == 1 {
-                    match &err.got {
-                        Some(token) => write!(
-                            f,
-                            "Line {}, Column {} :: Expected {} but found token {}",
-                            token.line,
-                            token.col,
-                            err.candidates[0],
-                            &token.get_token_debug_repr()
-                        ),
-                        None => write!(f, "Expected {} but found EOF", err.candidates[0]),
-                    }
+                    format!("Expected {} but found {}", err.candidates[0], found)
                 } else if err.candidates.len() == 2 {
-                    match &err.got {
-                        Some(token) => write!(
-                            f,
-                            "Line {}, Column {} :: Expected {} or {} but found token {}",
-                            token.line,
-                            token.col,
-                            err.candidates[0],
-                            err.candidates[1],
-                            &token.get_token_debug_repr()
-                        ),
-                        None => write!(
-                            f,
-                            "Expected {} or {} but found EOF",
-                            err.candidates[0], err.candidates[1]
-                        ),
-                    }
+                    format!(
+                        "Expected {} or {} but found {}",
+                        err.candidates[0], err.candidates[1], found
+                    )
                 } else {
                     let candidates_string: String = err
                         .candidates
                         .iter()
                         .map(|c| format!("• {}\n", c))
                         .collect();
-                    match &err.got {
-                        Some(token) => write!(f, "Line {}, Column {} :: Expected one of the following:\n{}but found token {}", token.line, token.col, &candidates_string, &token.get_token_debug_repr()),
-                        None => write!(f, "Expected one of the following:\n{}but found EOF", &candidates_string)
-                    }
+                    format!(
+                        "Expected one of the following:\n{}but found {}",
+                        &candidates_string, found
+                    )
                 }
             }
-            ParserError::InvalidLabel(err) => write!(
-                f,
-                "Line {}, Column {} :: No label exists with name: {}",
-                err.token.line,
-                err.token.col,
+            ParserError::MissingInstructionDelimeter(err) => format!(
+                "Missing ';' or newline between instructions before token {}",
+                &err.got.get_token_debug_repr()
+            ),
+            ParserError::InvalidLabel(err) => format!(
+                "No label exists with name: {}",
                 &err.token.get_token_debug_repr()
             ),
+            ParserError::InvalidConstantDefinition(_) => {
+                String::from("Constants must be defined as '.equ NAME #value'")
+            }
+            ParserError::ConstantDuplicateDefinition(err) => {
+                format!("Constant '{}' defined multiple times", &err.name)
+            }
+            ParserError::SymbolKindConflict(err) => {
+                format!("'{}' is already defined as a {}", &err.name, err.existing)
+            }
             ParserError::InvalidInstructionSignature(err) => {
                 let operand_type_strings: Vec<String> = err
                     .received
@@ -106,11 +182,11 @@ impl fmt::Display for ParserError {
                     .collect();
 
                 let potential_signatures = SIGNATURE_TREE
-                    .get_all_valid_operand_combinations_for_source_opcode(err.source_opcode);
+                    .get_suggested_signatures_for_source_opcode(err.source_opcode);
                 // Convert each inner Vec<SignatureArgument> to a comma-separated string
                 let potential_signatures_strings: Vec<String> = potential_signatures
                     .iter()
-                    .map(|(_, signature)| {
+                    .map(|signature| {
                         signature
                             .iter()
                             .map(|arg| arg.to_string())
@@ -119,11 +195,8 @@ impl fmt::Display for ParserError {
                     })
                     .collect();
 
-                write!(
-                    f,
-                    "Line {}, Column {} :: '{} {}' is not a valid signature! Potential signatures are listed below:\n{}",
-                    err.opcode_token.line,
-                    err.opcode_token.col,
+                let mut message = format!(
+                    "'{} {}' is not a valid signature! Potential signatures are listed below:\n{}",
                     err.source_opcode,
                     operand_type_strings.join(", "),
                     potential_signatures_strings
@@ -131,21 +204,98 @@ impl fmt::Display for ParserError {
                         .map(|signature| format!("• {} {signature}", err.source_opcode))
                         .collect::<Vec<String>>()
                         .join("\n")
-                )
+                );
+                if let Some(first) = err.swapped_first_argument() {
+                    message.push_str(&format!(
+                        "\nHint: the operands may be in the wrong order, '{}' expects the {} first",
+                        err.source_opcode, first
+                    ));
+                }
+                message
+            }
+            ParserError::OperandTypeMismatch(err) => format!(
+                "{} operand of '{}': expected {} but found {}",
+                ordinal(err.index + 1),
+                err.source_opcode,
+                err.expected
+                    .iter()
+                    .map(|arg| arg.to_string())
+                    .collect::<Vec<String>>()
+                    .join(" or "),
+                err.found
+            ),
+            ParserError::OperandOutOfRange(err) => format!(
+                "Invalid {} '{}' for {}, must be in range {}-{}",
+                err.role,
+                &err.token.lexeme,
+                err.source_opcode,
+                err.min,
+                err.max
+            ),
+            ParserError::LabelDuplicateDefinition(err) => {
+                format!("Label '{}' defined multiple times", &err.name)
+            }
+            ParserError::BranchIntoData(err) => format!(
+                "Label '{}' is at byte {}, past the last instruction, so branching to it would run data",
+                &err.token.lexeme,
+                err.target
+            ),
+            ParserError::InvalidBranchTarget(err) if err.target >= err.program_size => format!(
+                "Branch target {} is past the last instruction, which ends at byte {}",
+                err.target, err.program_size
+            ),
+            ParserError::InvalidBranchTarget(err) => format!(
+                "Branch target {} is not the start of an instruction",
+                err.target
+            ),
+            ParserError::CodeInDataSection(err) => format!(
+                "Found instruction '{}' after '.data', instructions must come before the data section",
+                &err.token.lexeme
+            ),
+            ParserError::SpaceOutsideDataSection(_) => {
+                String::from("'.space' can only be used after '.data'")
+            }
+            ParserError::NotADataLabel(err) => format!(
+                "Label '{}' is at byte {}, which is an instruction rather than data",
+                &err.token.lexeme, err.target
+            ),
+            ParserError::DataOutsideDataSection(_) => {
+                String::from("'DATA' can only be used after '.data'")
+            }
+            ParserError::InvalidDataValue(err) => format!(
+                "Expected a literal or number for 'DATA' but found token {}",
+                &err.token.get_token_debug_repr()
+            ),
+            ParserError::ProgramTooLarge => format!(
+                "Program exceeds memory limit (max {} bytes)",
+                MAX_PROGRAM_BYTES
+            ),
+            ParserError::Multiple(errors) => errors
+                .iter()
+                .map(ParserError::message)
+                .collect::<Vec<String>>()
+                .join("\n"),
+        }
+    }
+}
+
+impl fmt::Display for ParserError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // Each error goes on its own line
+        if let ParserError::Multiple(errors) = self {
+            for (i, err) in errors.iter().enumerate() {
+                if i > 0 {
+                    writeln!(f)?;
+                }
+                write!(f, "{err}")?;
             }
-            ParserError::LabelDuplicateDefinition(err) => write!(
-                f,
-                "Line {}, Column {} :: Label '{}' defined multiple times",
-                err.line,
-                err.col,
-                &err.name
-            ),
-            ParserError::ProgramTooLarge => write!(
-                f,
-                "Program exceeds memory limit (256 bytes)"
-            ),
-        }?;
-        write!(f, "{color_reset}{style_reset}")
+            return Ok(());
+        }
+        write!(f, "{color_red}{style_bold}")?;
+        if let Some((line, col)) = self.position() {
+            write!(f, "Line {line}, Column {col} :: ")?;
+        }
+        write!(f, "{}{color_reset}{style_reset}", self.message())
     }
 }
 
@@ -157,6 +307,8 @@ pub struct ExpectedOpcode {
 #[derive(Debug, PartialEq)]
 pub struct ExpectedOperand {
     pub got: Option<Token>,
+    /// Instruction whose operands were being parsed
+    pub source_opcode: Option<SourceOpcode>,
 }
 
 #[derive(Debug, PartialEq)]
@@ -165,6 +317,11 @@ pub struct ExpectedTokenKind {
     pub got: Option<Token>,
 }
 
+#[derive(Debug, PartialEq)]
+pub struct MissingInstructionDelimeter {
+    pub got: Token,
+}
+
 #[derive(Debug, PartialEq)]
 pub struct InvalidLabel {
     pub token: Token,
@@ -177,6 +334,60 @@ pub struct InvalidInstructionSignature {
     pub received: Vec<Operand>,
 }
 
+impl InvalidInstructionSignature {
+    /// If the operands would be valid in reverse order, the kind of operand that should
+    /// have come first
+    pub fn swapped_first_argument(&self) -> Option<SignatureArgument> {
+        if self.received.len() < 2 {
+            return None;
+        }
+        let reversed: Vec<Operand> = self.received.iter().rev().copied().collect();
+        SIGNATURE_TREE.matches_signature(self.source_opcode, &reversed)?;
+        Some(reversed[0].get_signature_argument())
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub struct OperandTypeMismatch {
+    /// The operand of the wrong kind
+    pub token: Token,
+    pub source_opcode: SourceOpcode,
+    /// Zero based position of the operand
+    pub index: usize,
+    pub expected: Vec<SignatureArgument>,
+    pub found: SignatureArgument,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct OperandOutOfRange {
+    pub token: Token,
+    pub source_opcode: SourceOpcode,
+    pub role: OperandRole,
+    pub min: u8,
+    pub max: u8,
+}
+
+/// What an instruction uses an operand for, when that limits the values it can take
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum OperandRole {
+    /// Number of bits to shift an 8 bit value by
+    ShiftAmount,
+    /// Number of values to read from memory
+    ArrayLength,
+    /// Value to divide by
+    Divisor,
+}
+
+impl fmt::Display for OperandRole {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            OperandRole::ShiftAmount => write!(f, "shift amount"),
+            OperandRole::ArrayLength => write!(f, "array length"),
+            OperandRole::Divisor => write!(f, "divisor"),
+        }
+    }
+}
+
 #[derive(Debug, PartialEq)]
 pub struct LabelDuplicateDefinition {
     pub name: String,
@@ -184,12 +395,116 @@ pub struct LabelDuplicateDefinition {
     pub col: usize,
 }
 
+#[derive(Debug, PartialEq)]
+pub struct BranchIntoData {
+    /// The label operand of the branch
+    pub token: Token,
+    /// Byte offset the label resolved to
+    pub target: u8,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct DataOutsideDataSection {
+    pub token: Token,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct InvalidDataValue {
+    pub token: Token,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct InvalidConstantDefinition {
+    /// The '.equ' token
+    pub token: Token,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct ConstantDuplicateDefinition {
+    pub name: String,
+    pub line: usize,
+    pub col: usize,
+}
+
+/// What a name in the symbol table was defined as
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SymbolKind {
+    Label,
+    Constant,
+}
+
+impl fmt::Display for SymbolKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SymbolKind::Label => write!(f, "label"),
+            SymbolKind::Constant => write!(f, "constant"),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub struct SymbolKindConflict {
+    pub name: String,
+    /// Position of the second definition
+    pub line: usize,
+    pub col: usize,
+    /// What the name was first defined as
+    pub existing: SymbolKind,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct InvalidBranchTarget {
+    /// The memory reference operand of the branch
+    pub token: Token,
+    pub target: u8,
+    /// Size of the program the branch is in
+    pub program_size: u8,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct CodeInDataSection {
+    /// The opcode of the instruction
+    pub token: Token,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct SpaceOutsideDataSection {
+    pub token: Token,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct NotADataLabel {
+    /// The label operand
+    pub token: Token,
+    /// Byte offset the label resolved to
+    pub target: u8,
+}
+
+/// 1st, 2nd, 3rd, 4th...
+fn ordinal(n: usize) -> String {
+    let suffix = match (n % 10, n % 100) {
+        (_, 11..=13) => "th",
+        (1, _) => "st",
+        (2, _) => "nd",
+        (3, _) => "rd",
+        _ => "th",
+    };
+    format!("{n}{suffix}")
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use crate::{
         interpreter::instruction::{operand::Operand, source_opcode::SourceOpcode}, tokenizer::{Token, TokenKind}
     };
+
+    #[test]
+    fn test_ordinal() {
+        for (n, expected) in [(1, "1st"), (2, "2nd"), (3, "3rd"), (4, "4th"), (11, "11th"), (12, "12th"), (21, "21st")] {
+            assert_eq!(ordinal(n), expected);
+        }
+    }
     use inline_colorization::{color_red, color_reset, style_bold, style_reset};
 
     #[test]
@@ -202,15 +517,33 @@ mod tests {
                 "Line 12, Column 5 :: Expected instruction opcode but found token ','",
             ),
             (
-                ParserError::ExpectedOperand(Box::new(ExpectedOperand { got: None })),
+                ParserError::ExpectedOperand(Box::new(ExpectedOperand {
+                    got: None,
+                    source_opcode: None,
+                })),
                 "Expected operand but found EOF",
             ),
+            (
+                ParserError::ExpectedOperand(Box::new(ExpectedOperand {
+                    got: None,
+                    source_opcode: Some(SourceOpcode::ADD),
+                })),
+                "Unexpected end of file while parsing operands of ADD",
+            ),
             (
                 ParserError::ExpectedOperand(Box::new(ExpectedOperand {
                     got: Some(Token::new(TokenKind::Semicolon, ";", 4444, 1)),
+                    source_opcode: None,
                 })),
                 "Line 4444, Column 1 :: Expected operand but found token ';'",
             ),
+            (
+                ParserError::ExpectedOperand(Box::new(ExpectedOperand {
+                    got: Some(Token::new(TokenKind::Semicolon, ";", 3, 12)),
+                    source_opcode: Some(SourceOpcode::MOV),
+                })),
+                "Line 3, Column 12 :: Expected operand of MOV but found token ';'",
+            ),
             (
                 ParserError::ExpectedTokenKind(Box::new(ExpectedTokenKind {
                     candidates: vec![TokenKind::Comma],
@@ -271,12 +604,95 @@ but found EOF",
 • newline
 but found token 'label'",
             ),
+            (
+                ParserError::MissingInstructionDelimeter(Box::new(MissingInstructionDelimeter {
+                    got: Token::new(TokenKind::Opcode(SourceOpcode::HALT), "HALT", 3, 10),
+                })),
+                "Line 3, Column 10 :: Missing ';' or newline between instructions before token 'HALT'",
+            ),
             (
                 ParserError::InvalidLabel(Box::new(InvalidLabel {
                     token: Token::new(TokenKind::Operand(Operand::Label), "label", 104, 0),
                 })),
                 "Line 104, Column 0 :: No label exists with name: 'label'",
             ),
+            (
+                ParserError::BranchIntoData(Box::new(BranchIntoData {
+                    token: Token::new(TokenKind::Operand(Operand::Label), "end", 3, 3),
+                    target: 6,
+                })),
+                "Line 3, Column 3 :: Label 'end' is at byte 6, past the last instruction, so branching to it would run data",
+            ),
+            (
+                ParserError::InvalidBranchTarget(Box::new(InvalidBranchTarget {
+                    token: Token::new(TokenKind::Operand(Operand::MemoryRef(1)), "1", 2, 5),
+                    target: 1,
+                    program_size: 6,
+                })),
+                "Line 2, Column 5 :: Branch target 1 is not the start of an instruction",
+            ),
+            (
+                ParserError::InvalidBranchTarget(Box::new(InvalidBranchTarget {
+                    token: Token::new(TokenKind::Operand(Operand::MemoryRef(9)), "9", 2, 5),
+                    target: 9,
+                    program_size: 6,
+                })),
+                "Line 2, Column 5 :: Branch target 9 is past the last instruction, which ends at byte 6",
+            ),
+            (
+                ParserError::CodeInDataSection(Box::new(CodeInDataSection {
+                    token: Token::new(TokenKind::Opcode(SourceOpcode::MOV), "MOV", 7, 1),
+                })),
+                "Line 7, Column 1 :: Found instruction 'MOV' after '.data', instructions must come before the data section",
+            ),
+            (
+                ParserError::SpaceOutsideDataSection(Box::new(SpaceOutsideDataSection {
+                    token: Token::new(TokenKind::Space(4), ".space 4", 3, 1),
+                })),
+                "Line 3, Column 1 :: '.space' can only be used after '.data'",
+            ),
+            (
+                ParserError::NotADataLabel(Box::new(NotADataLabel {
+                    token: Token::new(TokenKind::Operand(Operand::Label), "loop", 4, 9),
+                    target: 3,
+                })),
+                "Line 4, Column 9 :: Label 'loop' is at byte 3, which is an instruction rather than data",
+            ),
+            (
+                ParserError::DataOutsideDataSection(Box::new(DataOutsideDataSection {
+                    token: Token::new(TokenKind::Data, "DATA", 2, 1),
+                })),
+                "Line 2, Column 1 :: 'DATA' can only be used after '.data'",
+            ),
+            (
+                ParserError::InvalidDataValue(Box::new(InvalidDataValue {
+                    token: Token::new(TokenKind::Operand(Operand::Register(1)), "R1", 5, 6),
+                })),
+                "Line 5, Column 6 :: Expected a literal or number for 'DATA' but found token 'R1'",
+            ),
+            (
+                ParserError::InvalidConstantDefinition(Box::new(InvalidConstantDefinition {
+                    token: Token::new(TokenKind::Equ, ".equ", 3, 1),
+                })),
+                "Line 3, Column 1 :: Constants must be defined as '.equ NAME #value'",
+            ),
+            (
+                ParserError::ConstantDuplicateDefinition(Box::new(ConstantDuplicateDefinition {
+                    name: String::from("MAX"),
+                    line: 2,
+                    col: 6,
+                })),
+                "Line 2, Column 6 :: Constant 'MAX' defined multiple times",
+            ),
+            (
+                ParserError::SymbolKindConflict(Box::new(SymbolKindConflict {
+                    name: String::from("loop"),
+                    line: 2,
+                    col: 1,
+                    existing: SymbolKind::Constant,
+                })),
+                "Line 2, Column 1 :: 'loop' is already defined as a constant",
+            ),
             (
                 ParserError::InvalidInstructionSignature(Box::new(InvalidInstructionSignature {
                     opcode_token: Token::new(TokenKind::Opcode(SourceOpcode::HALT), "HALT", 50, 50),
@@ -295,6 +711,47 @@ but found token 'label'",
                 "Line 500, Column 20 :: 'MOV register, register, register' is not a valid signature! Potential signatures are listed below:
 • MOV register, register
 • MOV register, literal",
+            ),
+            (
+                ParserError::OperandTypeMismatch(Box::new(OperandTypeMismatch {
+                    token: Token::new(TokenKind::Operand(Operand::MemoryRef(5)), "5", 7, 13),
+                    source_opcode: SourceOpcode::ADD,
+                    index: 2,
+                    expected: vec![SignatureArgument::Register, SignatureArgument::Literal],
+                    found: SignatureArgument::MemoryRef,
+                })),
+                "Line 7, Column 13 :: 3rd operand of 'ADD': expected register or literal but found memory reference",
+            ),
+            (
+                ParserError::OperandOutOfRange(Box::new(OperandOutOfRange {
+                    token: Token::new(TokenKind::Operand(Operand::Literal(8)), "#8", 3, 13),
+                    source_opcode: SourceOpcode::LSL,
+                    role: OperandRole::ShiftAmount,
+                    min: 0,
+                    max: 7,
+                })),
+                "Line 3, Column 13 :: Invalid shift amount '#8' for LSL, must be in range 0-7",
+            ),
+            (
+                ParserError::InvalidInstructionSignature(Box::new(InvalidInstructionSignature {
+                    opcode_token: Token::new(TokenKind::Opcode(SourceOpcode::ADD), "ADD", 2, 1),
+                    source_opcode: SourceOpcode::ADD,
+                    received: vec![Operand::Register(0)],
+                })),
+                "Line 2, Column 1 :: 'ADD register' is not a valid signature! Potential signatures are listed below:
+• ADD register, register, register
+• ADD register, register, literal",
+            ),
+            (
+                ParserError::InvalidInstructionSignature(Box::new(InvalidInstructionSignature {
+                    opcode_token: Token::new(TokenKind::Opcode(SourceOpcode::CMP), "CMP", 4, 1),
+                    source_opcode: SourceOpcode::CMP,
+                    received: vec![Operand::Literal(5), Operand::Register(0)],
+                })),
+                "Line 4, Column 1 :: 'CMP literal, register' is not a valid signature! Potential signatures are listed below:
+• CMP register, register
+• CMP register, literal
+Hint: the operands may be in the wrong order, 'CMP' expects the register first",
             ),
             (
                 ParserError::LabelDuplicateDefinition(Box::new(LabelDuplicateDefinition {
@@ -306,7 +763,7 @@ but found token 'label'",
             ),
             (
                 ParserError::ProgramTooLarge,
-                "Program exceeds memory limit (256 bytes)"
+                "Program exceeds memory limit (max 255 bytes)"
             )
         ] {
             assert_eq!(
@@ -314,5 +771,43 @@ but found token 'label'",
                 format!("{color_red}{style_bold}{expected}{color_reset}{style_reset}")
             );
         }
+
+        let errors = vec![ParserError::ProgramTooLarge, ParserError::ProgramTooLarge];
+        let expected = format!(
+            "{color_red}{style_bold}Program exceeds memory limit (max 255 bytes){color_reset}{style_reset}"
+        );
+        assert_eq!(
+            ParserError::Multiple(errors).to_string(),
+            format!("{expected}\n{expected}")
+        );
+    }
+
+    #[test]
+    fn test_parser_error_position() {
+        use crate::{parser::Parser, tokenizer::Tokenizer};
+
+        for (source, position, message) in [
+            (
+                "HALT\nB nowhere\n",
+                Some((2, 3)),
+                "No label exists with name: 'nowhere'",
+            ),
+            (
+                "MOV R0, #1 HALT\n",
+                Some((1, 12)),
+                "Missing ';' or newline between instructions before token 'HALT'",
+            ),
+            (
+                "MOV R0,",
+                None,
+                "Unexpected end of file while parsing operands of MOV",
+            ),
+        ] {
+            let tokens = Tokenizer::tokenize(source, 4).unwrap().tokens;
+            let err = Parser::parse(tokens).unwrap_err();
+            assert_eq!(err.position(), position);
+            assert_eq!(err.message(), message);
+        }
+        assert_eq!(ParserError::ProgramTooLarge.position(), None);
     }
 }
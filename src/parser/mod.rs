@@ -1,68 +1,340 @@
+mod ast;
+pub use ast::*;
 mod error;
 pub use error::*;
 
 use crate::{
     interpreter::instruction::{
-        operand::Operand, signature::SIGNATURE_TREE, source_opcode::SourceOpcode,
+        operand::Operand, runtime_opcode::RuntimeOpcode, signature::SIGNATURE_TREE,
+        source_opcode::SourceOpcode,
     },
     tokenizer::{Token, TokenKind},
 };
 
-use std::{collections::HashMap, iter::Peekable, slice::IterMut, vec::IntoIter};
+use std::{
+    collections::{BTreeMap, HashMap},
+    iter::Peekable,
+    slice::IterMut,
+    vec::IntoIter,
+};
+
+/// Maps the byte offset of each instruction to the source line it was parsed from
+pub type LineTable = BTreeMap<u8, usize>;
+
+/// Maps each label name to the byte offset it refers to
+pub type SymbolTable = HashMap<String, u8>;
+
+/// Maps each constant name to the value it was defined with
+pub type ConstantTable = HashMap<String, u8>;
+
+/// Literal operands that can only take some of the values a literal can hold, as the
+/// source opcode, zero based operand position, role and inclusive range of valid values.
+/// Memory references past the end of data memory are reported as warnings by
+/// [`crate::analysis`] instead, as they depend on the size of the whole program.
+const OPERAND_ROLES: [(SourceOpcode, usize, OperandRole, u8, u8); 5] = [
+    (SourceOpcode::LSL, 2, OperandRole::ShiftAmount, 0, 7),
+    (SourceOpcode::LSR, 2, OperandRole::ShiftAmount, 0, 7),
+    (SourceOpcode::PRINTARR, 1, OperandRole::ArrayLength, 1, 255),
+    (SourceOpcode::DIV, 2, OperandRole::Divisor, 1, 255),
+    (SourceOpcode::MOD, 2, OperandRole::Divisor, 1, 255),
+];
 
 #[derive(Debug)]
 pub struct Parser<'a> {
     token_iter: Peekable<IntoIter<Token>>,
-    labels: HashMap<String, u8>,
+    labels: SymbolTable,
+    program_size: u8,
     memory_iter: IterMut<'a, u8>,
+    line_table: LineTable,
+    /// Branches written with a byte offset rather than a label, checked once every
+    /// instruction has been parsed
+    numeric_branch_targets: Vec<(Token, u8)>,
 }
 
 impl<'a> Parser<'a> {
     /// parse parses the tokens sequence to ensure that the tokens are in a valid order, and loads the instructions
     /// into the memory space. It will return an error if parsing fails and is also responsible for resolving label operands
     /// to their corresponding label. If there is a label operand without an associated label, an error will be returned.
-    pub fn parse(
+    pub fn parse(tokens: Vec<Token>) -> Result<([u8; 256], u8), ParserError> {
+        let (memory, program_size, _) = Parser::parse_with_line_table(tokens)?;
+        Ok((memory, program_size))
+    }
+
+    /// Same as [`Parser::parse`] but borrows the tokens, so that tokens from a single
+    /// tokenize can be parsed more than once
+    pub fn parse_ref(tokens: &[Token]) -> Result<([u8; 256], u8), ParserError> {
+        Parser::parse(tokens.to_vec())
+    }
+
+    /// Same as [`Parser::parse`] but also returns the line each instruction was parsed from,
+    /// so that runtime errors can be traced back to the source
+    pub fn parse_with_line_table(
         tokens: Vec<Token>,
-    ) -> Result<([u8; 256], u8), ParserError> {
-        // Resolve labels
-        let mut labels = HashMap::new();
+    ) -> Result<([u8; 256], u8, LineTable), ParserError> {
+        let (memory, program_size, line_table, _) = Parser::parse_with_symbols(tokens)?;
+        Ok((memory, program_size, line_table))
+    }
+
+    /// Same as [`Parser::parse_with_line_table`] but also returns the byte offset of every
+    /// label, so that constants only need to be resolved once
+    pub fn parse_with_symbols(
+        tokens: Vec<Token>,
+    ) -> Result<([u8; 256], u8, LineTable, SymbolTable), ParserError> {
+        let tokens = Parser::expand_constants(&tokens)?;
+        let (labels, program_size) = Parser::label_offsets(&tokens)?;
+        let (memory, line_table) = Parser::parse_resolved(tokens, labels.clone(), program_size)?;
+        Ok((memory, program_size, line_table, labels))
+    }
+
+    /// Load tokens that constants have been substituted into, with labels already found by
+    /// [`Parser::label_offsets`], into memory
+    pub(crate) fn parse_resolved(
+        tokens: Vec<Token>,
+        labels: SymbolTable,
+        program_size: u8,
+    ) -> Result<([u8; 256], LineTable), ParserError> {
+        let mut memory = [0; 256];
+        let mut parser = Parser {
+            token_iter: tokens.into_iter().peekable(),
+            labels,
+            program_size,
+            memory_iter: memory.iter_mut(),
+            line_table: LineTable::new(),
+            numeric_branch_targets: Vec::new(),
+        };
+        parser.internal_parse()?;
+        parser.check_numeric_branch_targets()?;
+        let line_table = parser.line_table;
+        Ok((memory, line_table))
+    }
+
+    /// Check a single statement on its own, such as a line typed into an editor. The line
+    /// delimeter at the end is optional, and label operands are assumed to exist as there
+    /// is no program for them to refer to.
+    pub fn check_statement(tokens: Vec<Token>) -> Result<(), ParserError> {
+        let mut tokens = Parser::expand_constants(&tokens)?;
+        let (mut labels, _) = Parser::label_offsets(&tokens)?;
+        // Assumed labels are placed where they pass the checks for how they are used, the
+        // start of the data for memory references and the first instruction for branches
+        let mut is_branch = false;
+        for token in &tokens {
+            match token.kind {
+                TokenKind::Opcode(source_opcode) => is_branch = source_opcode.is_branch(),
+                TokenKind::Operand(Operand::Label) => {
+                    let offset = if is_branch { 0 } else { u8::MAX };
+                    labels.entry(token.lexeme.clone()).or_insert(offset);
+                }
+                _ => {}
+            }
+        }
+        if let Some(last) = tokens.last() {
+            if !matches!(last.kind, TokenKind::Newline | TokenKind::Semicolon) {
+                let (line, col) = (last.line, last.col + last.lexeme.len());
+                tokens.push(Token::new(TokenKind::Newline, "\\n", line, col));
+            }
+        }
+        let mut memory = [0; 256];
+        let mut parser = Parser {
+            token_iter: tokens.into_iter().peekable(),
+            labels,
+            program_size: u8::MAX,
+            memory_iter: memory.iter_mut(),
+            line_table: LineTable::new(),
+            numeric_branch_targets: Vec::new(),
+        };
+        parser.internal_parse()
+    }
+
+    /// Find the byte offset of every label definition, along with the size of the program.
+    /// Labels in the data section are offset from the end of the program by the space
+    /// reserved before them.
+    pub fn resolve_labels(tokens: &[Token]) -> Result<(SymbolTable, u8), ParserError> {
+        Parser::label_offsets(&Parser::expand_constants(tokens)?)
+    }
+
+    /// Same as [`Parser::resolve_labels`] for tokens that constants have already been
+    /// substituted into
+    pub(crate) fn label_offsets(tokens: &[Token]) -> Result<(SymbolTable, u8), ParserError> {
+        let mut labels = SymbolTable::new();
         let mut program_size: u8 = 0;
-        for token in tokens.iter() {
+        // Space reserved so far, once the data section has started
+        let mut data_size: Option<u8> = None;
+        let mut token_iter = tokens.iter().peekable();
+        while let Some(token) = token_iter.next() {
             match token.kind {
+                TokenKind::DataSection => {
+                    data_size.get_or_insert(0);
+                }
+                TokenKind::Space(size) => {
+                    if let Some(data_size) = &mut data_size {
+                        *data_size = data_size
+                            .checked_add(size)
+                            .filter(|&data_size| program_size.checked_add(data_size).is_some())
+                            .ok_or(ParserError::ProgramTooLarge)?;
+                    }
+                }
+                TokenKind::Data => {
+                    // Every value is one byte, whether or not it turns out to be valid
+                    let mut size: u8 = 0;
+                    while let Some(next) = token_iter.next_if(|token| {
+                        matches!(token.kind, TokenKind::Operand(_) | TokenKind::Comma)
+                    }) {
+                        if let TokenKind::Operand(_) = next.kind {
+                            size = size.checked_add(1).ok_or(ParserError::ProgramTooLarge)?;
+                        }
+                    }
+                    if let Some(data_size) = &mut data_size {
+                        *data_size = data_size
+                            .checked_add(size)
+                            .filter(|&data_size| program_size.checked_add(data_size).is_some())
+                            .ok_or(ParserError::ProgramTooLarge)?;
+                    }
+                }
+                TokenKind::Opcode(_) | TokenKind::Operand(_) if data_size.is_some() => {}
                 TokenKind::Opcode(_) | TokenKind::Operand(_) => {
-                    match program_size.checked_add(1) {
+                    let mut size = 1;
+                    // INC Rn and DEC Rn expand to an instruction with two more operands
+                    if let TokenKind::Opcode(SourceOpcode::INC | SourceOpcode::DEC) = token.kind {
+                        size = 3;
+                    }
+                    // NOP #n expands to n NOP instructions
+                    if token.kind == TokenKind::Opcode(SourceOpcode::NOP) {
+                        if let Some(TokenKind::Operand(Operand::Literal(count))) =
+                            token_iter.peek().map(|token| token.kind)
+                        {
+                            token_iter.next();
+                            size = count;
+                        }
+                    }
+                    match program_size.checked_add(size) {
                         Some(new) => program_size = new,
                         None => return Err(ParserError::ProgramTooLarge),
                     }
                 }
+                // Names defined more than once have already been rejected by
+                // resolve_constants
                 TokenKind::LabelDefinition => {
                     let label_definition_lexeme = &token.lexeme;
                     let mut label_name = label_definition_lexeme.clone();
                     label_name.pop();
-                    if labels.get(&label_name).is_some() {
-                        return Err(ParserError::LabelDuplicateDefinition(Box::new(LabelDuplicateDefinition{
-                            name: label_name,
-                            line: token.line,
-                            col: token.col
-                        })));
-                    }
-                    labels.insert(label_name, program_size);
+                    labels.insert(label_name, program_size + data_size.unwrap_or(0));
                 }
                 _ => {}
             }
         }
-        // Parse instructions into memory
-        let mut memory = [0; 256];
-        let mut parser = Parser {
-            token_iter: tokens.into_iter().peekable(),
-            labels: labels,
-            memory_iter: memory.iter_mut(),
+        Ok((labels, program_size))
+    }
+
+    /// Find the value of every constant defined with '.equ NAME #value'. Labels and
+    /// constants share one set of names, so this also rejects a name that is defined a
+    /// second time as either, pointing at the second definition.
+    pub fn resolve_constants(tokens: &[Token]) -> Result<ConstantTable, ParserError> {
+        let mut constants = ConstantTable::new();
+        let mut symbols: HashMap<&str, SymbolKind> = HashMap::new();
+        let mut token_iter = tokens.iter();
+        while let Some(token) = token_iter.next() {
+            if token.kind == TokenKind::LabelDefinition {
+                let name = token.lexeme.strip_suffix(':').unwrap_or(&token.lexeme);
+                Parser::define_symbol(&mut symbols, name, SymbolKind::Label, token)?;
+                continue;
+            }
+            if token.kind != TokenKind::Equ {
+                continue;
+            }
+            let name = token_iter
+                .next()
+                .filter(|name| name.kind == TokenKind::Operand(Operand::Label));
+            let value = match token_iter.next().map(|token| token.kind) {
+                Some(TokenKind::Operand(Operand::Literal(value))) => Some(value),
+                _ => None,
+            };
+            let ends_line = matches!(
+                token_iter.next().map(|token| token.kind),
+                None | Some(TokenKind::Newline | TokenKind::Semicolon)
+            );
+            let (Some(name), Some(value), true) = (name, value, ends_line) else {
+                return Err(ParserError::InvalidConstantDefinition(Box::new(
+                    InvalidConstantDefinition {
+                        token: token.clone(),
+                    },
+                )));
+            };
+            Parser::define_symbol(&mut symbols, &name.lexeme, SymbolKind::Constant, name)?;
+            constants.insert(name.lexeme.clone(), value);
+        }
+        Ok(constants)
+    }
+
+    /// Add a name to the symbol table, failing if it has already been defined
+    fn define_symbol<'t>(
+        symbols: &mut HashMap<&'t str, SymbolKind>,
+        name: &'t str,
+        kind: SymbolKind,
+        token: &Token,
+    ) -> Result<(), ParserError> {
+        let Some(existing) = symbols.insert(name, kind) else {
+            return Ok(());
         };
-        parser.internal_parse()?;
-        Ok((memory, program_size))
+        let (name, line, col) = (name.to_string(), token.line, token.col);
+        Err(match (existing, kind) {
+            (SymbolKind::Label, SymbolKind::Label) => {
+                ParserError::LabelDuplicateDefinition(Box::new(LabelDuplicateDefinition {
+                    name,
+                    line,
+                    col,
+                }))
+            }
+            (SymbolKind::Constant, SymbolKind::Constant) => {
+                ParserError::ConstantDuplicateDefinition(Box::new(ConstantDuplicateDefinition {
+                    name,
+                    line,
+                    col,
+                }))
+            }
+            _ => ParserError::SymbolKindConflict(Box::new(SymbolKindConflict {
+                name,
+                line,
+                col,
+                existing,
+            })),
+        })
+    }
+
+    /// Resolve every constant and substitute it into the tokens, see
+    /// [`Parser::substitute_constants`]
+    pub(crate) fn expand_constants(tokens: &[Token]) -> Result<Vec<Token>, ParserError> {
+        let constants = Parser::resolve_constants(tokens)?;
+        Ok(Parser::substitute_constants(tokens, &constants))
+    }
+
+    /// Replace every use of a constant with a literal operand of its value, and leave out
+    /// the definitions. The token keeps the constant's name as its lexeme for errors.
+    fn substitute_constants(tokens: &[Token], constants: &ConstantTable) -> Vec<Token> {
+        let mut substituted = Vec::with_capacity(tokens.len());
+        let mut token_iter = tokens.iter();
+        while let Some(token) = token_iter.next() {
+            let mut token = token.clone();
+            match token.kind {
+                // Skip the name and value, which resolve_constants has checked are there
+                TokenKind::Equ => {
+                    token_iter.nth(1);
+                    continue;
+                }
+                TokenKind::Operand(Operand::Label) => {
+                    if let Some(&value) = constants.get(&token.lexeme) {
+                        token.kind = TokenKind::Operand(Operand::Literal(value));
+                    }
+                }
+                _ => {}
+            }
+            substituted.push(token);
+        }
+        substituted
     }
 
     fn internal_parse(&mut self) -> Result<(), ParserError> {
+        let mut in_data_section = false;
+        let mut errors = Vec::new();
         // Parser loop
         while let Some(token) = self.token_iter.next() {
             /*
@@ -70,42 +342,126 @@ impl<'a> Parser<'a> {
             If we see a newline or semicolon we can ignore them as we don't mind
             erroneous line delimeters. If we find any other token, there has been an error.
             */
-            match token.kind {
-                TokenKind::Opcode(opcode) => {
-                    self.parse_opcode(token, opcode)?;
+            let result = match token.kind {
+                TokenKind::Opcode(_) if in_data_section => Err(ParserError::CodeInDataSection(
+                    Box::new(CodeInDataSection { token }),
+                )),
+                TokenKind::Opcode(opcode) => self.parse_opcode(token, opcode),
+                TokenKind::DataSection => {
+                    in_data_section = true;
+                    Ok(())
                 }
-                TokenKind::Newline | TokenKind::Semicolon | TokenKind::LabelDefinition => {}
-                _ => {
-                    return Err(ParserError::ExpectedOpcode(Box::new(ExpectedOpcode {
-                        got: token,
-                    })))
+                // Reserved space is left as zero, but still has to be skipped over so
+                // that DATA after it is written in the right place
+                TokenKind::Space(size) if in_data_section => {
+                    (0..size).try_for_each(|_| self.write_memory(0))
+                }
+                TokenKind::Data if in_data_section => self.parse_data(),
+                TokenKind::Data => Err(ParserError::DataOutsideDataSection(Box::new(
+                    DataOutsideDataSection { token },
+                ))),
+                TokenKind::Space(_) => Err(ParserError::SpaceOutsideDataSection(Box::new(
+                    SpaceOutsideDataSection { token },
+                ))),
+                TokenKind::Newline | TokenKind::Semicolon | TokenKind::LabelDefinition => Ok(()),
+                _ => Err(ParserError::ExpectedOpcode(Box::new(ExpectedOpcode {
+                    got: token,
+                }))),
+            };
+            match result {
+                Ok(()) => {}
+                // Nothing after this can be placed in memory, so there is no point going on
+                Err(ParserError::ProgramTooLarge) => return Err(ParserError::ProgramTooLarge),
+                Err(err) => {
+                    errors.push(err);
+                    self.skip_statement();
                 }
             }
         }
-        Ok(())
+        ParserError::from_errors(errors)
     }
 
-    fn consume_operand(&mut self) -> Result<Operand, ParserError> {
+    /// Skip the rest of a statement that failed to parse, so parsing can carry on from the
+    /// next line delimeter
+    fn skip_statement(&mut self) {
+        while let Some(token) = self.token_iter.peek() {
+            if matches!(token.kind, TokenKind::Newline | TokenKind::Semicolon) {
+                break;
+            }
+            self.token_iter.next();
+        }
+    }
+
+    fn consume_operand(
+        &mut self,
+        source_opcode: SourceOpcode,
+    ) -> Result<(Operand, Token), ParserError> {
         if let Some(token) = self.token_iter.peek() {
             match token.kind {
                 TokenKind::Operand(operand) => {
-                    self.token_iter.next();
-                    Ok(operand)
+                    let token = self.token_iter.next().unwrap();
+                    Ok((operand, token))
                 }
                 _ => Err(ParserError::ExpectedOperand(Box::new(ExpectedOperand {
                     got: Some(token.clone()),
+                    source_opcode: Some(source_opcode),
                 }))),
             }
         } else {
-            return Err(ParserError::ExpectedOperand(Box::new(ExpectedOperand {
+            Err(ParserError::ExpectedOperand(Box::new(ExpectedOperand {
                 got: None,
-            })));
+                source_opcode: Some(source_opcode),
+            })))
         }
     }
 
-    fn write_memory(&mut self, val: u8) {
-        let current = self.memory_iter.next().expect("Fatal Error: Provided program is too large to load into memory. This should have been prevented earlier, please report as a bug.");
+    /// Write the comma separated values after 'DATA' into the data section
+    fn parse_data(&mut self) -> Result<(), ParserError> {
+        loop {
+            let value = self
+                .token_iter
+                .next_if(|token| matches!(token.kind, TokenKind::Operand(_)));
+            match value {
+                Some(Token {
+                    kind: TokenKind::Operand(Operand::Literal(val) | Operand::MemoryRef(val)),
+                    ..
+                }) => self.write_memory(val)?,
+                Some(token) => {
+                    return Err(ParserError::InvalidDataValue(Box::new(InvalidDataValue {
+                        token,
+                    })))
+                }
+                None => {
+                    return Err(ParserError::ExpectedOperand(Box::new(ExpectedOperand {
+                        got: self.token_iter.peek().cloned(),
+                        source_opcode: None,
+                    })))
+                }
+            }
+            let Some(token) = self.token_iter.peek() else {
+                return Ok(());
+            };
+            match token.kind {
+                TokenKind::Comma => {
+                    self.token_iter.next();
+                }
+                TokenKind::Newline | TokenKind::Semicolon => return Ok(()),
+                _ => {
+                    return Err(ParserError::ExpectedTokenKind(Box::new(
+                        ExpectedTokenKind {
+                            candidates: vec![TokenKind::Comma],
+                            got: self.token_iter.next(),
+                        },
+                    )))
+                }
+            }
+        }
+    }
+
+    fn write_memory(&mut self, val: u8) -> Result<(), ParserError> {
+        let current = self.memory_iter.next().ok_or(ParserError::ProgramTooLarge)?;
         *current = val;
+        Ok(())
     }
 
     fn parse_opcode(
@@ -122,17 +478,23 @@ impl<'a> Parser<'a> {
 
             // Consume comma seperated operands
             while let Some(TokenKind::Comma) = self.token_iter.peek().map(|token| token.kind) {
-                let token = self.token_iter.next().unwrap();
-                operands_and_tokens.push((self.consume_operand()?, token));
+                self.token_iter.next();
+                operands_and_tokens.push(self.consume_operand(source_opcode)?);
             }
         }
 
         // Consume the line delimeter, if anything else found return appropriate errors
         if let Some(token) = self.token_iter.peek() {
             match token.kind {
-                // Line delimeter - just consume
-                TokenKind::Newline | TokenKind::Semicolon => {
-                    self.token_iter.next();
+                // Line delimeter - left for the parser loop, which skips over it
+                TokenKind::Newline | TokenKind::Semicolon => {}
+                // Opcode, the user has written two instructions on one line
+                TokenKind::Opcode(_) => {
+                    return Err(ParserError::MissingInstructionDelimeter(Box::new(
+                        MissingInstructionDelimeter {
+                            got: self.token_iter.next().unwrap(),
+                        },
+                    )))
                 }
                 // Operand, we must be missing a comma between them!
                 TokenKind::Operand(_) => {
@@ -165,24 +527,116 @@ impl<'a> Parser<'a> {
 
         // Ensure the operands match an operand format for this instruction
         let operands: Vec<Operand> = operands_and_tokens.iter().map(|x| x.0).collect();
-        if let Some(runtime_opcode) = SIGNATURE_TREE.matches_signature(source_opcode, &operands) {
+        if let (SourceOpcode::NOP, [Operand::Literal(count)]) = (source_opcode, &operands[..]) {
+            // NOP #n is shorthand for n NOP instructions
+            for _ in 0..*count {
+                let offset = (256 - self.memory_iter.len()) as u8;
+                self.line_table.insert(offset, opcode_token.line);
+                self.write_memory(RuntimeOpcode::NOP as u8)?;
+            }
+        } else if let (SourceOpcode::INC | SourceOpcode::DEC, [Operand::Register(register)]) =
+            (source_opcode, &operands[..])
+        {
+            // INC Rn is shorthand for ADD Rn, Rn, #1 and DEC Rn for SUB Rn, Rn, #1
+            let runtime_opcode = if source_opcode == SourceOpcode::INC {
+                RuntimeOpcode::ADD_LITERAL
+            } else {
+                RuntimeOpcode::SUB_LITERAL
+            };
+            let offset = (256 - self.memory_iter.len()) as u8;
+            self.line_table.insert(offset, opcode_token.line);
+            for byte in [runtime_opcode as u8, *register, *register, 1] {
+                self.write_memory(byte)?;
+            }
+        } else if let (
+            SourceOpcode::LDA,
+            [(Operand::Register(register), _), (Operand::Label, token)],
+        ) = (source_opcode, &operands_and_tokens[..])
+        {
+            // LDA Rn, label is shorthand for MOV Rn, #address with the label's address. Unlike
+            // a branch, the label may point past the last instruction, such as at data.
+            let Some(&address) = self.labels.get(&token.lexeme) else {
+                return Err(ParserError::InvalidLabel(Box::new(InvalidLabel {
+                    token: token.clone(),
+                })));
+            };
+            let offset = (256 - self.memory_iter.len()) as u8;
+            self.line_table.insert(offset, opcode_token.line);
+            for byte in [RuntimeOpcode::MOV_LITERAL as u8, *register, address] {
+                self.write_memory(byte)?;
+            }
+        } else if let (true, [(Operand::MemoryRef(target), token)]) =
+            (source_opcode.is_branch(), &operands_and_tokens[..])
+        {
+            // A branch to a byte offset is encoded the same as a branch to a label at it
+            let runtime_opcode = SIGNATURE_TREE
+                .matches_signature(source_opcode, &[Operand::Label])
+                .expect("every branch takes a label");
+            let offset = (256 - self.memory_iter.len()) as u8;
+            self.line_table.insert(offset, opcode_token.line);
+            self.write_memory(runtime_opcode as u8)?;
+            self.write_memory(*target)?;
+            self.numeric_branch_targets.push((token.clone(), *target));
+        } else if let Some(runtime_opcode) = SIGNATURE_TREE.matches_signature(source_opcode, &operands) {
+            Parser::check_operand_roles(source_opcode, &operands_and_tokens)?;
+            // record where this instruction came from
+            let offset = (256 - self.memory_iter.len()) as u8;
+            self.line_table.insert(offset, opcode_token.line);
             // write opcode
-            self.write_memory(runtime_opcode as u8);
+            self.write_memory(runtime_opcode as u8)?;
             // write all operands
             for (operand, token) in operands_and_tokens {
                 match operand {
-                    Operand::Literal(val) => self.write_memory(val),
-                    Operand::Register(val) => self.write_memory(val),
-                    Operand::MemoryRef(val) => self.write_memory(val),
+                    Operand::Literal(val) => self.write_memory(val)?,
+                    Operand::Register(val) => self.write_memory(val)?,
+                    Operand::MemoryRef(val) => self.write_memory(val)?,
                     // resolve labels
                     Operand::Label => match self.labels.get(&token.lexeme) {
-                        Some(&byte) => self.write_memory(byte),
+                        // A label as a memory reference is relative to the start of the data
+                        Some(&byte) if !source_opcode.is_branch() => {
+                            match byte.checked_sub(self.program_size) {
+                                Some(offset) => self.write_memory(offset)?,
+                                None => {
+                                    return Err(ParserError::NotADataLabel(Box::new(
+                                        NotADataLabel {
+                                            token,
+                                            target: byte,
+                                        },
+                                    )))
+                                }
+                            }
+                        }
+                        // A branch can't go past the last instruction, as there is only data
+                        Some(&byte) if byte >= self.program_size => {
+                            return Err(ParserError::BranchIntoData(Box::new(BranchIntoData {
+                                token,
+                                target: byte,
+                            })))
+                        }
+                        Some(&byte) => self.write_memory(byte)?,
                         _ => {
                             return Err(ParserError::InvalidLabel(Box::new(InvalidLabel { token })))
                         }
                     },
                 };
             }
+        } else if let Some(mismatch) = SIGNATURE_TREE
+            .find_operand_mismatch(source_opcode, &operands)
+            .filter(|_| {
+                // Swapped operands get a hint from the signature error instead
+                let reversed: Vec<Operand> = operands.iter().rev().copied().collect();
+                SIGNATURE_TREE
+                    .matches_signature(source_opcode, &reversed)
+                    .is_none()
+            })
+        {
+            return Err(ParserError::OperandTypeMismatch(Box::new(OperandTypeMismatch {
+                token: operands_and_tokens[mismatch.index].1.clone(),
+                source_opcode,
+                index: mismatch.index,
+                expected: mismatch.expected,
+                found: mismatch.found,
+            })));
         } else {
             return Err(ParserError::InvalidInstructionSignature(Box::new(
                 InvalidInstructionSignature {
@@ -194,6 +648,49 @@ impl<'a> Parser<'a> {
         }
         Ok(())
     }
+
+    /// Make sure every numeric branch target is the start of an instruction, so that a
+    /// branch can never land part way through one or in the data
+    fn check_numeric_branch_targets(&self) -> Result<(), ParserError> {
+        for (token, target) in &self.numeric_branch_targets {
+            if !self.line_table.contains_key(target) {
+                return Err(ParserError::InvalidBranchTarget(Box::new(
+                    InvalidBranchTarget {
+                        token: token.clone(),
+                        target: *target,
+                        program_size: self.program_size,
+                    },
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    /// Check literal operands against [`OPERAND_ROLES`]
+    fn check_operand_roles(
+        source_opcode: SourceOpcode,
+        operands_and_tokens: &[(Operand, Token)],
+    ) -> Result<(), ParserError> {
+        for &(opcode, index, role, min, max) in &OPERAND_ROLES {
+            if opcode != source_opcode {
+                continue;
+            }
+            if let Some((Operand::Literal(value), token)) = operands_and_tokens.get(index) {
+                if !(min..=max).contains(value) {
+                    return Err(ParserError::OperandOutOfRange(Box::new(
+                        OperandOutOfRange {
+                            token: token.clone(),
+                            source_opcode,
+                            role,
+                            min,
+                            max,
+                        },
+                    )));
+                }
+            }
+        }
+        Ok(())
+    }
 }
 
 #[cfg(test)]
@@ -207,18 +704,28 @@ mod tests {
             signature::{SignatureArgument, SIGNATURE_TREE},
             source_opcode::SourceOpcode,
         },
-        parser::{ExpectedTokenKind, InvalidInstructionSignature},
-        tokenizer::{Token, TokenKind},
+        parser::{ExpectedTokenKind, InvalidInstructionSignature, OperandTypeMismatch},
+        tokenizer::{Token, TokenKind, Tokenizer},
     };
+    use inline_colorization::{color_red, color_reset, style_bold, style_reset};
 
     use super::*;
 
     fn load_test_program(program: &[u8]) -> [u8; 256] {
         let mut memory = [0; 256];
-        memory[..program.len()].copy_from_slice(&program);
+        memory[..program.len()].copy_from_slice(program);
         memory
     }
 
+    #[test]
+    fn test_parse_ref() {
+        let tokens = Tokenizer::tokenize("MOV R0, #5\nHALT\n", 4).unwrap().tokens;
+        let first = Parser::parse_ref(&tokens).unwrap();
+        let second = Parser::parse_ref(&tokens).unwrap();
+        assert_eq!(first, second);
+        assert_eq!(first, Parser::parse(tokens).unwrap());
+    }
+
     #[test]
     fn test_parse_valid_instructions() {
         for source_opcode in [
@@ -238,8 +745,8 @@ mod tests {
             SourceOpcode::ORR,
             SourceOpcode::EOR,
             SourceOpcode::MVN,
-            SourceOpcode::LSL,
-            SourceOpcode::LSR,
+            // LSL and LSR are in test_parse_valid_extra_instructions, as 127 is not a valid
+            // shift amount
             SourceOpcode::PRINT,
             SourceOpcode::INPUT,
             SourceOpcode::HALT,
@@ -266,23 +773,89 @@ mod tests {
                 // What the expected binary should look like
                 let mut expected = [0; 256];
                 expected[0] = runtime_opcode as u8;
-                let mut idx = 1;
-                for _ in combination {
-                    expected[idx] = 127;
-                    idx += 1;
+                expected[1..=combination.len()].fill(127);
+
+                // Check they are the same
+                let mut labels = HashMap::new();
+                labels.insert(String::from("test"), 127);
+                let mut memory = [0; 256];
+                let mut parser = Parser {
+                    labels,
+                    // A branch's label has to be an instruction, and a memory reference's
+                    // label has to be data
+                    program_size: if source_opcode.is_branch() { 128 } else { 0 },
+                    token_iter: tokens.into_iter().peekable(),
+                    memory_iter: memory.iter_mut(),
+                    line_table: LineTable::new(),
+                    numeric_branch_targets: Vec::new(),
+                };
+                parser.internal_parse().unwrap();
+                assert_eq!(memory, expected);
+            }
+        }
+    }
+
+    // Literals are 1 so that they are valid shift amounts, divisors and array lengths
+    #[test]
+    fn test_parse_valid_extra_instructions() {
+        for source_opcode in [
+            SourceOpcode::LSL,
+            SourceOpcode::LSR,
+            SourceOpcode::CPY,
+            SourceOpcode::PRINTARR,
+            SourceOpcode::DIV,
+            SourceOpcode::MOD,
+            SourceOpcode::PUSH,
+            SourceOpcode::POP,
+            SourceOpcode::CALL,
+            SourceOpcode::RET,
+            SourceOpcode::BCS,
+            SourceOpcode::BCC,
+            SourceOpcode::ROL,
+            SourceOpcode::ROR,
+        ] {
+            let operand_combinations =
+                SIGNATURE_TREE.get_all_valid_operand_combinations_for_source_opcode(source_opcode);
+            for (runtime_opcode, combination) in operand_combinations {
+                // Token stream for the valid operand call
+                let mut tokens = vec![Token::new(TokenKind::Opcode(source_opcode), "test", 1, 1)];
+                for (idx, operand) in combination.iter().enumerate() {
+                    let token_kind = match operand {
+                        SignatureArgument::Register => TokenKind::Operand(Operand::Register(127)),
+                        SignatureArgument::MemoryRef => TokenKind::Operand(Operand::MemoryRef(127)),
+                        SignatureArgument::Label => TokenKind::Operand(Operand::Label),
+                        SignatureArgument::Literal => TokenKind::Operand(Operand::Literal(1)),
+                    };
+                    tokens.push(Token::new(token_kind, "test", 1, 1));
+                    if idx < combination.len() - 1 {
+                        tokens.push(Token::new(TokenKind::Comma, "", 1, 1));
+                    }
+                }
+                tokens.push(Token::new(TokenKind::Semicolon, "", 1, 1));
+
+                // What the expected binary should look like
+                let mut expected = [0; 256];
+                expected[0] = runtime_opcode as u8;
+                for (byte, operand) in expected.iter_mut().skip(1).zip(&combination) {
+                    *byte = match operand {
+                        SignatureArgument::Literal => 1,
+                        _ => 127,
+                    };
                 }
 
                 // Check they are the same
                 let mut labels = HashMap::new();
-                labels.insert(
-                    String::from("test"),
-                    127,
-                );
+                labels.insert(String::from("test"), 127);
                 let mut memory = [0; 256];
                 let mut parser = Parser {
                     labels,
+                    // A branch's label has to be an instruction, and a memory reference's
+                    // label has to be data
+                    program_size: if source_opcode.is_branch() { 128 } else { 0 },
                     token_iter: tokens.into_iter().peekable(),
-                    memory_iter: memory.iter_mut()
+                    memory_iter: memory.iter_mut(),
+                    line_table: LineTable::new(),
+                    numeric_branch_targets: Vec::new(),
                 };
                 parser.internal_parse().unwrap();
                 assert_eq!(memory, expected);
@@ -327,6 +900,62 @@ mod tests {
         assert_eq!(result, expected);
     }
 
+    #[test]
+    fn test_parse_line_table() {
+        /*
+        PRINT R0
+
+        HALT
+        */
+        let tokens = vec![
+            Token::new(TokenKind::Opcode(SourceOpcode::PRINT), "PRINT", 1, 1),
+            Token::new(TokenKind::Operand(Operand::Register(0)), "R0", 1, 7),
+            Token::new(TokenKind::Newline, "\n", 1, 9),
+            Token::new(TokenKind::Newline, "\n", 2, 1),
+            Token::new(TokenKind::Opcode(SourceOpcode::HALT), "HALT", 3, 1),
+            Token::new(TokenKind::Newline, "\n", 3, 5),
+        ];
+        let (_, _, line_table) = Parser::parse_with_line_table(tokens).unwrap();
+        assert_eq!(line_table, LineTable::from([(0, 1), (2, 3)]));
+    }
+
+    #[test]
+    fn test_parse_label_before_instruction_on_same_line() {
+        /*
+        NOP
+        loop: ADD R0, R0, #1; B loop
+        */
+        let tokens = vec![
+            Token::new(TokenKind::Opcode(SourceOpcode::NOP), "NOP", 1, 1),
+            Token::new(TokenKind::Newline, "\n", 1, 4),
+            Token::new(TokenKind::LabelDefinition, "loop:", 2, 1),
+            Token::new(TokenKind::Opcode(SourceOpcode::ADD), "ADD", 2, 7),
+            Token::new(TokenKind::Operand(Operand::Register(0)), "R0", 2, 11),
+            Token::new(TokenKind::Comma, ",", 2, 13),
+            Token::new(TokenKind::Operand(Operand::Register(0)), "R0", 2, 15),
+            Token::new(TokenKind::Comma, ",", 2, 17),
+            Token::new(TokenKind::Operand(Operand::Literal(1)), "#1", 2, 19),
+            Token::new(TokenKind::Semicolon, ";", 2, 21),
+            Token::new(TokenKind::Opcode(SourceOpcode::B), "B", 2, 23),
+            Token::new(TokenKind::Operand(Operand::Label), "loop", 2, 25),
+            Token::new(TokenKind::Newline, "\n", 2, 29),
+        ];
+        let (memory, program_bytes) = Parser::parse(tokens).unwrap();
+        assert_eq!(program_bytes, 7);
+        assert_eq!(
+            memory[..7],
+            [
+                RuntimeOpcode::NOP as u8,
+                RuntimeOpcode::ADD_LITERAL as u8,
+                0,
+                0,
+                1,
+                RuntimeOpcode::B as u8,
+                1,
+            ]
+        );
+    }
+
     #[test]
     fn test_parse_error_invalid_label() {
         // Test for every branch instruction
@@ -384,6 +1013,7 @@ mod tests {
         let result = Parser::parse(tokens).unwrap_err();
         let expected = ParserError::ExpectedOperand(Box::new(ExpectedOperand {
             got: Some(semicolon),
+            source_opcode: Some(SourceOpcode::MOV),
         }));
         assert_eq!(result, expected);
         // MOV R4,
@@ -394,10 +1024,30 @@ mod tests {
             Token::new(TokenKind::Comma, ",", 1, 7),
         ];
         let result = Parser::parse(tokens).unwrap_err();
-        let expected = ParserError::ExpectedOperand(Box::new(ExpectedOperand { got: None }));
+        let expected = ParserError::ExpectedOperand(Box::new(ExpectedOperand {
+            got: None,
+            source_opcode: Some(SourceOpcode::MOV),
+        }));
         assert_eq!(result, expected);
     }
 
+    #[test]
+    fn test_parse_error_truncated_instruction_at_eof() {
+        let tokens = Tokenizer::tokenize("MOV R0, #1\nADD R0, R1,", 4).unwrap().tokens;
+        let err = Parser::parse(tokens).unwrap_err();
+        assert_eq!(
+            err,
+            ParserError::ExpectedOperand(Box::new(ExpectedOperand {
+                got: None,
+                source_opcode: Some(SourceOpcode::ADD),
+            }))
+        );
+        assert_eq!(
+            err.to_string(),
+            format!("{color_red}{style_bold}Unexpected end of file while parsing operands of ADD{color_reset}{style_reset}")
+        );
+    }
+
     #[test]
     fn test_parse_error_expected_line_delimeter() {
         // HALT HALT
@@ -407,11 +1057,26 @@ mod tests {
             halt.clone(),
         ];
         let result = Parser::parse(tokens).unwrap_err();
-        let expected = ParserError::ExpectedTokenKind(Box::new(ExpectedTokenKind {
-            candidates: vec![TokenKind::Semicolon, TokenKind::Newline],
-            got: Some(halt),
-        }));
+        let expected = ParserError::MissingInstructionDelimeter(Box::new(
+            MissingInstructionDelimeter { got: halt },
+        ));
         assert_eq!(result, expected);
+        // MOV R0, #1 HALT
+        let halt = Token::new(TokenKind::Opcode(SourceOpcode::HALT), "HALT", 1, 12);
+        let tokens = vec![
+            Token::new(TokenKind::Opcode(SourceOpcode::MOV), "MOV", 1, 1),
+            Token::new(TokenKind::Operand(Operand::Register(0)), "R0", 1, 5),
+            Token::new(TokenKind::Comma, ",", 1, 7),
+            Token::new(TokenKind::Operand(Operand::Literal(1)), "#1", 1, 9),
+            halt.clone(),
+        ];
+        let result = Parser::parse(tokens).unwrap_err();
+        assert_eq!(
+            result.to_string(),
+            format!(
+                "{color_red}{style_bold}Line 1, Column 12 :: Missing ';' or newline between instructions before token 'HALT'{color_reset}{style_reset}"
+            )
+        );
         // HALT
         let tokens = vec![Token::new(
             TokenKind::Opcode(SourceOpcode::HALT),
@@ -457,6 +1122,30 @@ mod tests {
         assert_eq!(result, expected)
     }
 
+    #[test]
+    fn test_parse_error_operand_type_mismatch() {
+        // ADD R0, 5, #1;
+        let memory_ref = Token::new(TokenKind::Operand(Operand::MemoryRef(5)), "5", 1, 9);
+        let tokens = vec![
+            Token::new(TokenKind::Opcode(SourceOpcode::ADD), "ADD", 1, 1),
+            Token::new(TokenKind::Operand(Operand::Register(0)), "R0", 1, 5),
+            Token::new(TokenKind::Comma, ",", 1, 7),
+            memory_ref.clone(),
+            Token::new(TokenKind::Comma, ",", 1, 10),
+            Token::new(TokenKind::Operand(Operand::Literal(1)), "#1", 1, 12),
+            Token::new(TokenKind::Semicolon, ";", 1, 14),
+        ];
+        let result = Parser::parse(tokens).unwrap_err();
+        let expected = ParserError::OperandTypeMismatch(Box::new(OperandTypeMismatch {
+            token: memory_ref,
+            source_opcode: SourceOpcode::ADD,
+            index: 1,
+            expected: vec![SignatureArgument::Register],
+            found: SignatureArgument::MemoryRef,
+        }));
+        assert_eq!(result, expected);
+    }
+
     #[test]
     fn test_parse_error_invalid_instruction_signature() {
         // Instruction with 0 operands given some
@@ -540,4 +1229,278 @@ mod tests {
         }));
         assert_eq!(result, expected);
     }
+
+    #[test]
+    fn test_nop_repeat() {
+        let tokens = Tokenizer::tokenize("NOP #3\nend: HALT\nB end\n", 4).unwrap().tokens;
+        let (memory, program_bytes, line_table) = Parser::parse_with_line_table(tokens).unwrap();
+        let nop = RuntimeOpcode::NOP as u8;
+        assert_eq!(
+            memory[..program_bytes as usize],
+            [nop, nop, nop, RuntimeOpcode::HALT as u8, RuntimeOpcode::B as u8, 3]
+        );
+        assert_eq!(line_table, LineTable::from([(0, 1), (1, 1), (2, 1), (3, 2), (4, 3)]));
+    }
+
+    #[test]
+    fn test_operand_roles() {
+        for (source, role, lexeme, min, max) in [
+            ("LSL R0, R1, #8\n", OperandRole::ShiftAmount, "#8", 0, 7),
+            ("LSR R0, R1, #9\n", OperandRole::ShiftAmount, "#9", 0, 7),
+            ("PRINTARR 0, #0\n", OperandRole::ArrayLength, "#0", 1, 255),
+            ("DIV R0, R1, #0\n", OperandRole::Divisor, "#0", 1, 255),
+            ("MOD R0, R1, #0\n", OperandRole::Divisor, "#0", 1, 255),
+        ] {
+            let tokens = Tokenizer::tokenize(source, 4).unwrap().tokens;
+            let ParserError::OperandOutOfRange(err) = Parser::parse(tokens).unwrap_err() else {
+                panic!("expected an out of range operand in {source:?}");
+            };
+            assert_eq!((err.role, err.token.lexeme.as_str()), (role, lexeme));
+            assert_eq!((err.min, err.max), (min, max));
+        }
+
+        // Values inside the range, and registers whose value is only known at runtime
+        for source in ["LSL R0, R1, #7\n", "LSR R0, R1, R2\n", "PRINTARR 0, #1\n"] {
+            let tokens = Tokenizer::tokenize(source, 4).unwrap().tokens;
+            assert!(Parser::parse(tokens).is_ok(), "{source:?} should parse");
+        }
+    }
+
+    #[rustfmt::skip]
+    #[test]
+    fn test_inc_dec() {
+        let tokens = Tokenizer::tokenize("INC R0\nloop: DEC R12\nB loop\n", 4)
+            .unwrap()
+            .tokens;
+        let (symbols, size) = Parser::resolve_labels(&tokens).unwrap();
+        assert_eq!((symbols["loop"], size), (4, 10));
+        let (memory, program_bytes, line_table) = Parser::parse_with_line_table(tokens).unwrap();
+        assert_eq!(
+            memory[..program_bytes as usize],
+            [
+                RuntimeOpcode::ADD_LITERAL as u8, 0, 0, 1,
+                RuntimeOpcode::SUB_LITERAL as u8, 12, 12, 1,
+                RuntimeOpcode::B as u8, 4,
+            ]
+        );
+        assert_eq!(line_table, LineTable::from([(0, 1), (4, 2), (8, 3)]));
+
+        let tokens = Tokenizer::tokenize("INC #1\n", 4).unwrap().tokens;
+        assert!(matches!(
+            Parser::parse(tokens).unwrap_err(),
+            ParserError::OperandTypeMismatch(_)
+        ));
+    }
+
+    #[rustfmt::skip]
+    #[test]
+    fn test_load_address() {
+        let tokens = Tokenizer::tokenize("start: LDA R0, data\nLDA R1, start\nHALT\ndata:\n", 4)
+            .unwrap()
+            .tokens;
+        let (memory, program_bytes) = Parser::parse(tokens).unwrap();
+        assert_eq!(
+            memory[..program_bytes as usize],
+            [
+                RuntimeOpcode::MOV_LITERAL as u8, 0, 7,
+                RuntimeOpcode::MOV_LITERAL as u8, 1, 0,
+                RuntimeOpcode::HALT as u8,
+            ]
+        );
+
+        let tokens = Tokenizer::tokenize("LDA R0, missing\nHALT\n", 4).unwrap().tokens;
+        assert!(matches!(
+            Parser::parse(tokens).unwrap_err(),
+            ParserError::InvalidLabel(err) if err.token.lexeme == "missing"
+        ));
+    }
+
+    #[rustfmt::skip]
+    #[test]
+    fn test_data_section() {
+        let source = "LDR R0, count\nADD R0, R0, #1\nSTR R0, total\nHALT\n.data\ncount:\n.space 2\ntotal:\n.space 1\nend:\n";
+        let tokens = Tokenizer::tokenize(source, 4).unwrap().tokens;
+        let (symbols, size) = Parser::resolve_labels(&tokens).unwrap();
+        assert_eq!(size, 11);
+        assert_eq!((symbols["count"], symbols["total"], symbols["end"]), (11, 13, 14));
+        let (memory, program_bytes) = Parser::parse(tokens).unwrap();
+        assert_eq!(
+            memory[..program_bytes as usize],
+            [
+                RuntimeOpcode::LDR as u8, 0, 0,
+                RuntimeOpcode::ADD_LITERAL as u8, 0, 0, 1,
+                RuntimeOpcode::STR as u8, 0, 2,
+                RuntimeOpcode::HALT as u8,
+            ]
+        );
+
+        for (source, expected) in [
+            ("HALT\n.data\nMOV R0, #1\n", "CodeInDataSection"),
+            ("HALT\n.space 4\n", "SpaceOutsideDataSection"),
+            ("start: LDR R0, start\nHALT\n", "NotADataLabel"),
+            ("HALT\n.data\nbig:\n.space 255\n", "ProgramTooLarge"),
+            ("HALT\nDATA #1\n", "DataOutsideDataSection"),
+            ("HALT\n.data\nDATA #1, R0\n", "InvalidDataValue"),
+            ("HALT\n.data\nDATA #1 #2\n", "ExpectedTokenKind"),
+            ("HALT\n.data\nDATA\n", "ExpectedOperand"),
+            ("HALT\n.data\n.space 250\nDATA #1, #2, #3, #4, #5\n", "ProgramTooLarge"),
+        ] {
+            let tokens = Tokenizer::tokenize(source, 4).unwrap().tokens;
+            let err = Parser::parse(tokens).unwrap_err();
+            assert!(format!("{err:?}").starts_with(expected), "{source:?} gave {err:?}");
+        }
+    }
+
+    #[test]
+    fn test_constants() {
+        let parse = |source| Parser::parse(Tokenizer::tokenize(source, 4).unwrap().tokens);
+        assert_eq!(
+            parse(".EQU MAX #10\nCMP R0, MAX\nHALT\n").unwrap(),
+            parse("CMP R0, #10\nHALT\n").unwrap()
+        );
+        // Definitions take up no space, so labels after them are unaffected
+        let tokens = Tokenizer::tokenize(".equ N #2\nNOP N\nend: B end\n.data\nDATA N\n", 4)
+            .unwrap()
+            .tokens;
+        let (labels, _) = Parser::resolve_labels(&tokens).unwrap();
+        assert_eq!(labels["end"], 2);
+        let (memory, _, _, symbols) = Parser::parse_with_symbols(tokens).unwrap();
+        let nop = RuntimeOpcode::NOP as u8;
+        assert_eq!(memory[..5], [nop, nop, RuntimeOpcode::B as u8, 2, 2]);
+        assert_eq!(symbols, labels);
+
+        assert_eq!(
+            parse(".equ MAX #1\n.equ MAX #2\nHALT\n").unwrap_err(),
+            ParserError::ConstantDuplicateDefinition(Box::new(ConstantDuplicateDefinition {
+                name: String::from("MAX"),
+                line: 2,
+                col: 6,
+            }))
+        );
+        // Labels and constants share one set of names, and the error is at the second
+        assert_eq!(
+            parse("X: NOP\n.equ X #1\nHALT\n").unwrap_err(),
+            ParserError::SymbolKindConflict(Box::new(SymbolKindConflict {
+                name: String::from("X"),
+                line: 2,
+                col: 6,
+                existing: SymbolKind::Label,
+            }))
+        );
+        assert_eq!(
+            parse(".equ loop #3\nloop: NOP\nB loop\n").unwrap_err(),
+            ParserError::SymbolKindConflict(Box::new(SymbolKindConflict {
+                name: String::from("loop"),
+                line: 2,
+                col: 1,
+                existing: SymbolKind::Constant,
+            }))
+        );
+        for source in [".equ N\n", ".equ N R0\n", ".equ #1 N\n", ".equ N #1 #2\n"] {
+            assert!(matches!(
+                parse(source).unwrap_err(),
+                ParserError::InvalidConstantDefinition(_)
+            ));
+        }
+    }
+
+    #[test]
+    fn test_nop_repeat_program_too_large() {
+        let tokens = Tokenizer::tokenize("NOP #200\nNOP #56\n", 4).unwrap().tokens;
+        assert_eq!(Parser::parse(tokens).unwrap_err(), ParserError::ProgramTooLarge);
+    }
+
+    #[test]
+    fn test_parser_error_branch_into_data() {
+        let tokens = Tokenizer::tokenize("B end\nHALT\nend:\n", 4).unwrap().tokens;
+        assert_eq!(
+            Parser::parse(tokens).unwrap_err(),
+            ParserError::BranchIntoData(Box::new(BranchIntoData {
+                token: Token::new(TokenKind::Operand(Operand::Label), "end", 1, 3)
+                    .with_raw_position(3, 2),
+                target: 3,
+            }))
+        );
+    }
+
+    #[rustfmt::skip]
+    #[test]
+    fn test_numeric_branch_targets() {
+        // Forward and backward branches to the start of an instruction
+        let tokens = Tokenizer::tokenize("MOV R0, #1\nBNE 7\nB 0\nHALT\n", 4)
+            .unwrap()
+            .tokens;
+        let (memory, program_bytes) = Parser::parse(tokens).unwrap();
+        assert_eq!(
+            memory[..program_bytes as usize],
+            [
+                RuntimeOpcode::MOV_LITERAL as u8, 0, 1,
+                RuntimeOpcode::BNE as u8, 7,
+                RuntimeOpcode::B as u8, 0,
+                RuntimeOpcode::HALT as u8,
+            ]
+        );
+
+        // Part way through the MOV
+        let tokens = Tokenizer::tokenize("MOV R0, #1\nB 2\nHALT\n", 4).unwrap().tokens;
+        assert_eq!(
+            Parser::parse(tokens).unwrap_err(),
+            ParserError::InvalidBranchTarget(Box::new(InvalidBranchTarget {
+                token: Token::new(TokenKind::Operand(Operand::MemoryRef(2)), "2", 2, 3)
+                    .with_raw_position(3, 13),
+                target: 2,
+                program_size: 6,
+            }))
+        );
+
+        // Past the end of the program
+        let tokens = Tokenizer::tokenize("B 3\nHALT\n", 4).unwrap().tokens;
+        assert!(matches!(
+            Parser::parse(tokens).unwrap_err(),
+            ParserError::InvalidBranchTarget(err) if err.target == 3 && err.program_size == 3
+        ));
+    }
+
+    #[test]
+    fn test_parse_error_swapped_operands() {
+        let tokens = Tokenizer::tokenize("CMP #5, R0\n", 4).unwrap().tokens;
+        let err = Parser::parse(tokens).unwrap_err();
+        let ParserError::InvalidInstructionSignature(err) = err else {
+            panic!("expected an invalid signature error, got {err:?}");
+        };
+        assert_eq!(err.swapped_first_argument(), Some(SignatureArgument::Register));
+
+        let tokens = Tokenizer::tokenize("MOV #5, #6\n", 4).unwrap().tokens;
+        assert!(matches!(
+            Parser::parse(tokens).unwrap_err(),
+            ParserError::OperandTypeMismatch(_)
+        ));
+    }
+
+    #[test]
+    fn test_parse_error_multiple() {
+        let source = "B first
+MOV R0, #1
+BNE second
+HALT
+";
+        let tokens = Tokenizer::tokenize(source, 4).unwrap().tokens;
+        let errors = Parser::parse(tokens).unwrap_err().into_errors();
+        let positions: Vec<_> = errors.iter().map(ParserError::position).collect();
+        assert_eq!(positions, [Some((1, 3)), Some((3, 5))]);
+
+        // Parsing carries on from the next line after an error part way through one
+        let source = "MOV R0, #1 HALT, R1
+ADD R0
+HALT
+";
+        let tokens = Tokenizer::tokenize(source, 4).unwrap().tokens;
+        assert!(matches!(
+            &Parser::parse(tokens).unwrap_err().into_errors()[..],
+            [
+                ParserError::MissingInstructionDelimeter(_),
+                ParserError::InvalidInstructionSignature(_)
+            ]
+        ));
+    }
 }
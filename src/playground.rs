@@ -0,0 +1,119 @@
+use crate::{interpreter::InterpreterOptions, Machine, MachineOptions, TRY_RUN_CYCLE_LIMIT};
+
+/// Assemble and run a program with all of its input given up front, describing the
+/// result as a JSON object with the fields `stdout`, `registers`, `data_memory` and
+/// `error`. `error` is `null` unless assembling or running failed, in which case the
+/// other fields hold the state of the machine when it stopped.
+///
+/// Like [`crate::try_run`], a program that has not halted after [`TRY_RUN_CYCLE_LIMIT`]
+/// instructions is stopped with an error, so that a program which loops forever still
+/// returns.
+pub fn run_program_json(source: &str, input: &str) -> String {
+    let mut machine = match Machine::from_source(source, MachineOptions::default()) {
+        Ok(machine) => machine.with_interpreter_options(InterpreterOptions {
+            cycle_limit: Some(TRY_RUN_CYCLE_LIMIT),
+            ..Default::default()
+        }),
+        Err(err) => {
+            return format!(
+                "{{\"stdout\":\"\",\"registers\":[],\"data_memory\":[],\"error\":{}}}",
+                json_string(&strip_colors(&err.to_string()))
+            )
+        }
+    };
+    let mut stdout = Vec::new();
+    let error = match machine.run(input.as_bytes(), &mut stdout) {
+        Ok(_) => String::from("null"),
+        Err(err) => json_string(&strip_colors(&err.to_string())),
+    };
+    format!(
+        "{{\"stdout\":{},\"registers\":{},\"data_memory\":{},\"error\":{}}}",
+        json_string(&String::from_utf8_lossy(&stdout)),
+        json_array(machine.registers()),
+        json_array(&machine.memory()[machine.program_bytes() as usize..]),
+        error
+    )
+}
+
+/// [`run_program_json`] exported to JavaScript, returning the result as an object
+#[cfg(feature = "wasm")]
+#[wasm_bindgen::prelude::wasm_bindgen]
+pub fn run_program(source: &str, input: &str) -> wasm_bindgen::JsValue {
+    js_sys::JSON::parse(&run_program_json(source, input))
+        .expect("run_program_json always produces valid JSON")
+}
+
+/// Remove the terminal colour codes that errors are displayed with
+fn strip_colors(text: &str) -> String {
+    let mut output = String::new();
+    let mut chars = text.chars();
+    while let Some(ch) = chars.next() {
+        if ch == '\x1b' {
+            // Skip up to and including the final letter of the escape sequence
+            chars.by_ref().find(|ch| ch.is_ascii_alphabetic());
+        } else {
+            output.push(ch);
+        }
+    }
+    output
+}
+
+fn json_string(text: &str) -> String {
+    let mut output = String::from("\"");
+    for ch in text.chars() {
+        match ch {
+            '"' => output.push_str("\\\""),
+            '\\' => output.push_str("\\\\"),
+            '\n' => output.push_str("\\n"),
+            '\r' => output.push_str("\\r"),
+            '\t' => output.push_str("\\t"),
+            ch if ch.is_control() => output.push_str(&format!("\\u{:04x}", ch as u32)),
+            ch => output.push(ch),
+        }
+    }
+    output.push('"');
+    output
+}
+
+fn json_array(values: &[u8]) -> String {
+    let values: Vec<String> = values.iter().map(u8::to_string).collect();
+    format!("[{}]", values.join(","))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_run_program_json() {
+        assert_eq!(
+            run_program_json(
+                "INPUT R0\nADD R1, R0, #1\nPRINT R1\nSTR R1, 0\nHALT\n",
+                "41\n"
+            ),
+            format!(
+                "{{\"stdout\":\"42\\n\",\"registers\":[41,42,0,0,0,0,0,0,0,0,0,0,0],\"data_memory\":[42{}],\"error\":null}}",
+                ",0".repeat(243)
+            )
+        );
+    }
+
+    #[test]
+    fn test_run_program_json_errors() {
+        assert_eq!(
+            run_program_json("MOV R0, #1\nFOO\n", ""),
+            "{\"stdout\":\"\",\"registers\":[],\"data_memory\":[],\"error\":\"Line 2, Column 1 :: Expected instruction opcode but found token 'FOO'\"}"
+        );
+        let output = run_program_json("PRINT R0\nINPUT R1\nHALT\n", "");
+        assert!(output.starts_with("{\"stdout\":\"0\\n\",\"registers\":[0,0,"));
+        assert!(output.contains("\"error\":\"Runtime Error at byte 2 :: "));
+    }
+
+    #[test]
+    fn test_run_program_json_infinite_loop() {
+        let output = run_program_json("loop: B loop\n", "");
+        assert!(output.contains(&format!(
+            "\"error\":\"Runtime Error at byte 0 :: Stopped after {TRY_RUN_CYCLE_LIMIT} instructions"
+        )));
+    }
+}
@@ -0,0 +1,183 @@
+use crate::interpreter::instruction::runtime_opcode::RuntimeOpcode;
+use std::{collections::BTreeMap, fmt};
+
+/// Number of cycles each runtime opcode takes to execute
+#[derive(Debug, Clone, PartialEq)]
+pub struct CostModel {
+    costs: BTreeMap<RuntimeOpcode, u64>,
+}
+
+impl Default for CostModel {
+    /// Register and literal operations take 1 cycle, branches 2 and anything touching
+    /// memory or I/O 3
+    fn default() -> Self {
+        let costs = runtime_opcodes()
+            .map(|opcode| {
+                let cost = match opcode {
+                    RuntimeOpcode::B
+                    | RuntimeOpcode::BEQ
+                    | RuntimeOpcode::BNE
+                    | RuntimeOpcode::BGT
+                    | RuntimeOpcode::BLT
+                    | RuntimeOpcode::BCS
+                    | RuntimeOpcode::BCC => 2,
+                    RuntimeOpcode::LDR
+                    | RuntimeOpcode::STR
+                    | RuntimeOpcode::CPY
+                    | RuntimeOpcode::PUSH
+                    | RuntimeOpcode::POP
+                    | RuntimeOpcode::CALL
+                    | RuntimeOpcode::RET
+                    | RuntimeOpcode::PRINT_REGISTER
+                    | RuntimeOpcode::PRINT_MEMORY
+                    | RuntimeOpcode::PRINTARR
+                    | RuntimeOpcode::INPUT_REGISTER
+                    | RuntimeOpcode::INPUT_MEMORY => 3,
+                    _ => 1,
+                };
+                (opcode, cost)
+            })
+            .collect();
+        Self { costs }
+    }
+}
+
+impl CostModel {
+    /// Parse a cost model where each line is a runtime opcode name and its cost, such as
+    /// `ADD_LITERAL 2`. Opcodes that are not listed keep their default cost. Empty lines
+    /// and lines starting with '#' are ignored.
+    pub fn parse(text: &str) -> Result<Self, InvalidCostModel> {
+        let mut model = Self::default();
+        for (idx, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let invalid = || InvalidCostModel {
+                line: idx + 1,
+                text: line.to_string(),
+            };
+            let mut parts = line.split_whitespace();
+            let (Some(name), Some(cost), None) = (parts.next(), parts.next(), parts.next()) else {
+                return Err(invalid());
+            };
+            let opcode = runtime_opcodes()
+                .find(|opcode| format!("{opcode:?}") == name.to_uppercase())
+                .ok_or_else(invalid)?;
+            let cost = cost.parse().map_err(|_| invalid())?;
+            model.costs.insert(opcode, cost);
+        }
+        Ok(model)
+    }
+
+    pub fn cost(&self, opcode: RuntimeOpcode) -> u64 {
+        self.costs[&opcode]
+    }
+}
+
+/// Line of a cost model that could not be understood
+#[derive(Debug, PartialEq)]
+pub struct InvalidCostModel {
+    pub line: usize,
+    pub text: String,
+}
+
+impl fmt::Display for InvalidCostModel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Line {} :: Invalid cost model entry '{}', expected a runtime opcode name and a cycle count",
+            self.line, self.text
+        )
+    }
+}
+
+fn runtime_opcodes() -> impl Iterator<Item = RuntimeOpcode> {
+    (0..=u8::MAX).filter_map(|byte| RuntimeOpcode::try_from(byte).ok())
+}
+
+/// Instructions executed and cycles spent while running a program
+#[derive(Debug, Clone, PartialEq)]
+pub struct Profile {
+    cost_model: CostModel,
+    pub instructions: u64,
+    pub cycles: u64,
+    /// Number of times each opcode was executed
+    pub by_opcode: BTreeMap<RuntimeOpcode, u64>,
+}
+
+impl Profile {
+    pub fn new(cost_model: CostModel) -> Self {
+        Self {
+            cost_model,
+            instructions: 0,
+            cycles: 0,
+            by_opcode: BTreeMap::new(),
+        }
+    }
+
+    /// Record an opcode being executed
+    pub fn record(&mut self, opcode: RuntimeOpcode) {
+        self.instructions += 1;
+        self.cycles += self.cost_model.cost(opcode);
+        *self.by_opcode.entry(opcode).or_insert(0) += 1;
+    }
+
+    /// Serialise the profile as a JSON object
+    pub fn to_json(&self) -> String {
+        let by_opcode: Vec<String> = self
+            .by_opcode
+            .iter()
+            .map(|(opcode, &count)| {
+                format!(
+                    "\"{:?}\":{{\"count\":{},\"cycles\":{}}}",
+                    opcode,
+                    count,
+                    count * self.cost_model.cost(*opcode)
+                )
+            })
+            .collect();
+        format!(
+            "{{\"instructions\":{},\"cycles\":{},\"by_opcode\":{{{}}}}}",
+            self.instructions,
+            self.cycles,
+            by_opcode.join(",")
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_cost_model() {
+        let model = CostModel::parse("# slow adds\nadd_literal 5\n\nHALT 0\n").unwrap();
+        assert_eq!(model.cost(RuntimeOpcode::ADD_LITERAL), 5);
+        assert_eq!(model.cost(RuntimeOpcode::HALT), 0);
+        assert_eq!(model.cost(RuntimeOpcode::LDR), 3);
+
+        assert_eq!(
+            CostModel::parse("MOV 1").unwrap_err(),
+            InvalidCostModel {
+                line: 1,
+                text: String::from("MOV 1"),
+            }
+        );
+        assert!(CostModel::parse("NOP").is_err());
+        assert!(CostModel::parse("NOP -1").is_err());
+        assert!(CostModel::parse("NOP 1 2").is_err());
+    }
+
+    #[test]
+    fn test_profile_json() {
+        let mut profile = Profile::new(CostModel::default());
+        profile.record(RuntimeOpcode::MOV_LITERAL);
+        profile.record(RuntimeOpcode::B);
+        profile.record(RuntimeOpcode::MOV_LITERAL);
+        assert_eq!(
+            profile.to_json(),
+            r#"{"instructions":3,"cycles":4,"by_opcode":{"MOV_LITERAL":{"count":2,"cycles":2},"B":{"count":1,"cycles":2}}}"#
+        );
+    }
+}
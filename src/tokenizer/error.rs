@@ -5,87 +5,158 @@ use std::fmt;
 pub enum TokenizerError {
     // Literal with value > 255
     LiteralValueTooLarge(Box<LiteralValueTooLarge>),
+    /// Negative literal with value < -128
+    NegativeLiteralValueTooSmall(Box<NegativeLiteralValueTooSmall>),
     /// Register denoter 'R' without a number following
     MissingNumberAfterRegisterDenoter(Box<MissingNumberAfterRegisterDenoter>),
-    /// Literal value '#' without a number following
+    /// Literal value '#' without a number following, including '#0x' and '#0b'
     MissingNumberAfterLiteralDenoter(Box<MissingNumberAfterLiteralDenoter>),
+    /// '0x' or '0b' prefix of a memory reference without any digits following
+    MissingDigitsAfterRadixPrefix(Box<MissingDigitsAfterRadixPrefix>),
+    /// Digit that is not valid for the number's radix, such as '2' in '0b12'
+    InvalidDigitForRadix(Box<InvalidDigitForRadix>),
     /// Invalid register number (greater than REGISTER_COUNT)
     InvalidRegisterNumber(Box<InvalidRegisterNumber>),
     /// Missing a */ delimeter for a block comment
     UnterminatedBlockComment(Box<UnterminatedBlockComment>),
     /// '/' character is an invalid comment denoter
     InvalidCommentDenoter(Box<InvalidCommentDenoter>),
+    /// Identifier longer than the configured maximum length
+    IdentifierTooLong(Box<IdentifierTooLong>),
+    /// Character that is usually pasted in from a word processor, such as a non-breaking
+    /// space or a smart quote
+    UnicodeLookalike(Box<UnicodeLookalike>),
+    /// Directive that is not a valid '.if NAME', '.endif', '.data' or '.space n' on a line
+    /// of its own
+    InvalidDirective(Box<InvalidDirective>),
+    /// '.endif' without an '.if' before it
+    UnmatchedEndif(Box<UnmatchedEndif>),
+    /// '.if' without an '.endif' after it
+    UnterminatedIf(Box<UnterminatedIf>),
     /// Any invalid character
     UnexpectedCharacter(Box<UnexpectedCharacter>),
 }
 
 impl std::error::Error for TokenizerError {}
 
-impl fmt::Display for TokenizerError {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "{color_red}{style_bold}")?;
+impl TokenizerError {
+    /// Line and column the error starts at
+    pub fn position(&self) -> (usize, usize) {
+        match self {
+            TokenizerError::LiteralValueTooLarge(err) => (err.line, err.col),
+            TokenizerError::NegativeLiteralValueTooSmall(err) => (err.line, err.col),
+            TokenizerError::MissingNumberAfterRegisterDenoter(err) => (err.line, err.col),
+            TokenizerError::MissingNumberAfterLiteralDenoter(err) => (err.line, err.col),
+            TokenizerError::MissingDigitsAfterRadixPrefix(err) => (err.line, err.col),
+            TokenizerError::InvalidDigitForRadix(err) => (err.line, err.col),
+            TokenizerError::InvalidRegisterNumber(err) => (err.line, err.col),
+            TokenizerError::UnterminatedBlockComment(err) => (err.line, err.col),
+            TokenizerError::InvalidCommentDenoter(err) => (err.line, err.col),
+            TokenizerError::IdentifierTooLong(err) => (err.line, err.col),
+            TokenizerError::UnicodeLookalike(err) => (err.line, err.col),
+            TokenizerError::InvalidDirective(err) => (err.line, err.col),
+            TokenizerError::UnmatchedEndif(err) => (err.line, err.col),
+            TokenizerError::UnterminatedIf(err) => (err.line, err.col),
+            TokenizerError::UnexpectedCharacter(err) => (err.line, err.col),
+        }
+    }
+
+    /// Column of the last character of the operand, for errors about a whole operand
+    pub fn end_col(&self) -> Option<usize> {
         match self {
-            TokenizerError::LiteralValueTooLarge(err) => write!(
-                f,
-                "Line {}, Column {} :: Literal value '{}' too large (max value of 255)",
-                err.line,
-                err.col,
+            TokenizerError::LiteralValueTooLarge(err) => Some(err.end_col),
+            TokenizerError::NegativeLiteralValueTooSmall(err) => Some(err.end_col),
+            TokenizerError::InvalidRegisterNumber(err) => Some(err.end_col),
+            _ => None,
+        }
+    }
+
+    /// Description of the error without its position
+    pub fn message(&self) -> String {
+        match self {
+            TokenizerError::LiteralValueTooLarge(err) => format!(
+                "Literal value '{}' too large (max value of 255)",
                 &err.value_string
             ),
-            TokenizerError::MissingNumberAfterRegisterDenoter(err) => write!(
-                f,
-                "Line {}, Column {} :: Missing number after register denoter 'R'",
-                err.line,
-                err.col,
-            ),
-            TokenizerError::MissingNumberAfterLiteralDenoter(err) => write!(
-                f,
-                "Line {}, Column {} :: Missing number after literal denoter '#'",
-                err.line,
-                err.col,
-            ),
-            TokenizerError::InvalidRegisterNumber(err) => write!(
-                f,
-                "Line {}, Column {} :: Invalid register 'R{}' (must be in range 0-12 inclusive)",
-                err.line,
-                err.col,
-                err.value,
-            ),
-            TokenizerError::UnterminatedBlockComment(err) => write!(
-                f,
-                "Line {}, Column {} :: Unterminated block comment begins here",
-                err.line,
-                err.col,
-            ),
-            TokenizerError::InvalidCommentDenoter(err) => write!(
-                f,
-                "Line {}, Column {} :: Expected '//' or '/*' for comment, not '/'",
-                err.line,
-                err.col,
-            ),
-            TokenizerError::UnexpectedCharacter(err) => write!(
-                f,
-                "Line {}, Column {} :: Unexpected character: '{}'",
-                err.line,
-                err.col,
-                err.char
-            ),
-        }?;
-        write!(f, "{color_reset}{style_reset}")
+            TokenizerError::NegativeLiteralValueTooSmall(err) => format!(
+                "Negative literal value '{}' too small (min value of -128)",
+                &err.value_string
+            ),
+            TokenizerError::MissingNumberAfterRegisterDenoter(_) => {
+                String::from("Missing number after register denoter 'R'")
+            }
+            TokenizerError::MissingNumberAfterLiteralDenoter(_) => {
+                String::from("Missing number after literal denoter '#'")
+            }
+            TokenizerError::MissingDigitsAfterRadixPrefix(err) => {
+                format!("Missing digits after number prefix '{}'", err.prefix)
+            }
+            TokenizerError::InvalidDigitForRadix(err) => format!(
+                "Invalid digit '{}' for a base {} number",
+                err.char, err.radix
+            ),
+            TokenizerError::InvalidRegisterNumber(err) => format!(
+                "Invalid register 'R{}' (must be in range 0-12 inclusive)",
+                err.value
+            ),
+            TokenizerError::UnterminatedBlockComment(_) => {
+                String::from("Unterminated block comment begins here")
+            }
+            TokenizerError::InvalidCommentDenoter(_) => {
+                String::from("Expected '//' or '/*' for comment, not '/'")
+            }
+            TokenizerError::IdentifierTooLong(err) => format!(
+                "Identifier '{}...' is too long (max length of {})",
+                err.name_prefix, err.max_length
+            ),
+            TokenizerError::UnicodeLookalike(err) => {
+                let (name, advice) = lookalike_description(err.char)
+                    .unwrap_or(("an unsupported character", "remove it"));
+                format!("Found {} (U+{:04X}); {}", name, err.char as u32, advice)
+            }
+            TokenizerError::InvalidDirective(err) => format!(
+                "Invalid directive '{}', expected '.if NAME', '.endif', '.data', '.space n' or '.equ NAME #value' on a line of its own",
+                err.directive
+            ),
+            TokenizerError::UnmatchedEndif(_) => {
+                String::from("Found '.endif' without a matching '.if'")
+            }
+            TokenizerError::UnterminatedIf(_) => String::from("Missing '.endif' for this '.if'"),
+            TokenizerError::UnexpectedCharacter(err) => {
+                format!("Unexpected character: '{}'", err.char)
+            }
+        }
+    }
+}
+
+impl fmt::Display for TokenizerError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (line, col) = self.position();
+        write!(f, "{color_red}{style_bold}")?;
+        match self.end_col() {
+            Some(end_col) => write!(f, "Line {line}, Columns {col}-{end_col}")?,
+            None => write!(f, "Line {line}, Column {col}")?,
+        }
+        write!(f, " :: {}{color_reset}{style_reset}", self.message())
     }
 }
 
 #[derive(Debug, PartialEq)]
-pub struct ProgramTooLarge {
+pub struct LiteralValueTooLarge {
+    pub value_string: String,
     pub line: usize,
     pub col: usize,
+    /// Column of the last character of the operand
+    pub end_col: usize,
 }
 
 #[derive(Debug, PartialEq)]
-pub struct LiteralValueTooLarge {
+pub struct NegativeLiteralValueTooSmall {
     pub value_string: String,
     pub line: usize,
     pub col: usize,
+    /// Column of the last character of the operand
+    pub end_col: usize,
 }
 
 #[derive(Debug, PartialEq)]
@@ -100,11 +171,28 @@ pub struct MissingNumberAfterLiteralDenoter {
     pub col: usize,
 }
 
+#[derive(Debug, PartialEq)]
+pub struct MissingDigitsAfterRadixPrefix {
+    pub prefix: String,
+    pub line: usize,
+    pub col: usize,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct InvalidDigitForRadix {
+    pub char: char,
+    pub radix: u32,
+    pub line: usize,
+    pub col: usize,
+}
+
 #[derive(Debug, PartialEq)]
 pub struct InvalidRegisterNumber {
     pub value: u8,
     pub line: usize,
     pub col: usize,
+    /// Column of the last character of the operand
+    pub end_col: usize,
 }
 
 #[derive(Debug, PartialEq)]
@@ -119,6 +207,66 @@ pub struct InvalidCommentDenoter {
     pub col: usize,
 }
 
+/// Number of characters of a long identifier that are shown in errors
+pub const IDENTIFIER_PREFIX_LENGTH: usize = 16;
+
+#[derive(Debug, PartialEq)]
+pub struct IdentifierTooLong {
+    /// First [`IDENTIFIER_PREFIX_LENGTH`] characters of the identifier
+    pub name_prefix: String,
+    pub max_length: usize,
+    pub line: usize,
+    pub col: usize,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct UnicodeLookalike {
+    pub char: char,
+    pub line: usize,
+    pub col: usize,
+}
+
+/// Name and suggested fix for characters that look like valid source code but are not,
+/// usually because they were pasted in from a word processor
+pub(crate) fn lookalike_description(ch: char) -> Option<(&'static str, &'static str)> {
+    match ch {
+        '\u{00A0}' | '\u{2007}' | '\u{202F}' => {
+            Some(("a non-breaking space", "use a regular space instead"))
+        }
+        '\u{200B}' | '\u{2060}' | '\u{FEFF}' => {
+            Some(("an invisible zero width character", "remove it"))
+        }
+        '\u{2018}' | '\u{2019}' | '\u{201C}' | '\u{201D}' => {
+            Some(("a smart quote", "quotes are not valid outside of comments"))
+        }
+        '\u{2013}' | '\u{2014}' | '\u{2212}' => Some(("a dash", "use a regular '-' instead")),
+        ch if ch.is_whitespace() && !ch.is_ascii() => {
+            Some(("a unicode space", "use a regular space instead"))
+        }
+        _ => None,
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub struct InvalidDirective {
+    /// Text of the line from the start of the directive
+    pub directive: String,
+    pub line: usize,
+    pub col: usize,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct UnmatchedEndif {
+    pub line: usize,
+    pub col: usize,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct UnterminatedIf {
+    pub line: usize,
+    pub col: usize,
+}
+
 #[derive(Debug, PartialEq)]
 pub struct UnexpectedCharacter {
     pub char: char,
@@ -138,8 +286,20 @@ mod tests {
                     value_string: String::from("12345"),
                     line: 36,
                     col: 7778,
+                    end_col: 7782,
                 })),
-                "Line 36, Column 7778 :: Literal value '12345' too large (max value of 255)",
+                "Line 36, Columns 7778-7782 :: Literal value '12345' too large (max value of 255)",
+            ),
+            (
+                TokenizerError::NegativeLiteralValueTooSmall(Box::new(
+                    NegativeLiteralValueTooSmall {
+                        value_string: String::from("-200"),
+                        line: 2,
+                        col: 5,
+                        end_col: 8,
+                    },
+                )),
+                "Line 2, Columns 5-8 :: Negative literal value '-200' too small (min value of -128)",
             ),
             (
                 TokenizerError::MissingNumberAfterRegisterDenoter(Box::new(
@@ -156,13 +316,33 @@ mod tests {
                 )),
                 "Line 3, Column 4 :: Missing number after literal denoter '#'",
             ),
+            (
+                TokenizerError::MissingDigitsAfterRadixPrefix(Box::new(
+                    MissingDigitsAfterRadixPrefix {
+                        prefix: String::from("0x"),
+                        line: 2,
+                        col: 9,
+                    },
+                )),
+                "Line 2, Column 9 :: Missing digits after number prefix '0x'",
+            ),
+            (
+                TokenizerError::InvalidDigitForRadix(Box::new(InvalidDigitForRadix {
+                    char: 'G',
+                    radix: 16,
+                    line: 1,
+                    col: 5,
+                })),
+                "Line 1, Column 5 :: Invalid digit 'G' for a base 16 number",
+            ),
             (
                 TokenizerError::InvalidRegisterNumber(Box::new(InvalidRegisterNumber {
                     value: 0,
                     line: 13,
                     col: 8,
+                    end_col: 9,
                 })),
-                "Line 13, Column 8 :: Invalid register 'R0' (must be in range 0-12 inclusive)",
+                "Line 13, Columns 8-9 :: Invalid register 'R0' (must be in range 0-12 inclusive)",
             ),
             (
                 TokenizerError::UnterminatedBlockComment(Box::new(UnterminatedBlockComment {
@@ -178,6 +358,39 @@ mod tests {
                 })),
                 "Line 4, Column 9 :: Expected '//' or '/*' for comment, not '/'",
             ),
+            (
+                TokenizerError::IdentifierTooLong(Box::new(IdentifierTooLong {
+                    name_prefix: String::from("a_very_long_labe"),
+                    max_length: 16,
+                    line: 3,
+                    col: 1,
+                })),
+                "Line 3, Column 1 :: Identifier 'a_very_long_labe...' is too long (max length of 16)",
+            ),
+            (
+                TokenizerError::UnicodeLookalike(Box::new(UnicodeLookalike {
+                    char: '\u{00A0}',
+                    line: 2,
+                    col: 4,
+                })),
+                "Line 2, Column 4 :: Found a non-breaking space (U+00A0); use a regular space instead",
+            ),
+            (
+                TokenizerError::InvalidDirective(Box::new(InvalidDirective {
+                    directive: String::from(".ifdef DEBUG"),
+                    line: 4,
+                    col: 1,
+                })),
+                "Line 4, Column 1 :: Invalid directive '.ifdef DEBUG', expected '.if NAME', '.endif', '.data', '.space n' or '.equ NAME #value' on a line of its own",
+            ),
+            (
+                TokenizerError::UnmatchedEndif(Box::new(UnmatchedEndif { line: 6, col: 1 })),
+                "Line 6, Column 1 :: Found '.endif' without a matching '.if'",
+            ),
+            (
+                TokenizerError::UnterminatedIf(Box::new(UnterminatedIf { line: 2, col: 5 })),
+                "Line 2, Column 5 :: Missing '.endif' for this '.if'",
+            ),
             (
                 TokenizerError::UnexpectedCharacter(Box::new(UnexpectedCharacter {
                     char: '@',
@@ -193,4 +406,27 @@ mod tests {
             );
         }
     }
+
+    #[test]
+    fn test_tokenizer_error_position() {
+        use crate::tokenizer::Tokenizer;
+
+        for (source, position, message) in [
+            (
+                "MOV R0, #300\n",
+                (1, 9),
+                "Literal value '300' too large (max value of 255)",
+            ),
+            ("HALT\n  MOV R0, @\n", (2, 11), "Unexpected character: '@'"),
+            (
+                "HALT\n/* never closed",
+                (2, 1),
+                "Unterminated block comment begins here",
+            ),
+        ] {
+            let err = Tokenizer::tokenize(source, 4).unwrap_err();
+            assert_eq!(err.position(), position);
+            assert_eq!(err.message(), message);
+        }
+    }
 }
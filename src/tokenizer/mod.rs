@@ -12,64 +12,175 @@ use std::{
     str::{Chars, FromStr},
 };
 
+/// Options that change how source code is tokenized
+#[derive(Debug, Clone)]
+pub struct TokenizerOptions {
+    /// Width of tabs, used for column numbers
+    pub tabsize: u8,
+    /// Treat ';' as the start of a comment rather than an instruction delimeter, leaving
+    /// newlines as the only delimeter
+    pub semicolon_comments: bool,
+    /// Longest allowed identifier, such as a label name
+    pub max_identifier_length: usize,
+    /// Names that are defined for '.if NAME' directives
+    pub defines: Vec<String>,
+}
+
+impl Default for TokenizerOptions {
+    fn default() -> Self {
+        Self {
+            tabsize: 4,
+            semicolon_comments: false,
+            max_identifier_length: 64,
+            defines: Vec::new(),
+        }
+    }
+}
+
 #[derive(Debug)]
 pub struct Tokenizer<'a> {
     pub tokens: Vec<Token>,
+    /// Every comment in the input, in the order they appear
+    pub comments: Vec<Comment>,
     input: &'a str,
     iter: Peekable<Chars<'a>>,
     prev_pos: TokenPosition,
     current_pos: TokenPosition,
     tabsize: usize,
+    semicolon_comments: bool,
+    max_identifier_length: usize,
+    defines: Vec<String>,
+    /// Each '.if' that has not been closed yet, with whether its block is included
+    conditions: Vec<(bool, TokenPosition)>,
+    /// Include directives read so far, only collected by [`Tokenizer::find_includes`]
+    includes: Option<Vec<IncludeDirective>>,
+}
+
+/// An '.include' line that [`Tokenizer::find_includes`] found
+#[derive(Debug, Clone, PartialEq)]
+pub struct IncludeDirective {
+    pub line: usize,
+    /// Everything after '.include' on the line, which should be a quoted path
+    pub argument: String,
 }
 
 impl<'a> Tokenizer<'a> {
     pub fn tokenize(input: &'a str, tabsize: u8) -> Result<Self, TokenizerError> {
-        let mut tokenizer = Tokenizer {
-            tokens: Vec::new(),
-            input: input,
-            iter: input.chars().peekable(),
-            prev_pos: TokenPosition::default(),
-            current_pos: TokenPosition::default(),
-            tabsize: tabsize as usize,
-        };
-        tokenizer.interal_tokenize()?;
+        Tokenizer::tokenize_with_options(
+            input,
+            TokenizerOptions {
+                tabsize,
+                ..Default::default()
+            },
+        )
+    }
+
+    pub fn tokenize_with_options(
+        input: &'a str,
+        options: TokenizerOptions,
+    ) -> Result<Self, TokenizerError> {
+        let mut iter = Tokenizer::iter_with_options(input, options);
+        let tokens = iter.by_ref().collect::<Result<Vec<_>, _>>()?;
+        let mut tokenizer = iter.tokenizer;
+        tokenizer.tokens = tokens;
         Ok(tokenizer)
     }
 
+    /// Lazily tokenize the input, yielding each token as it is read and stopping after
+    /// the first error
+    pub fn iter(input: &'a str, tabsize: u8) -> TokenIter<'a> {
+        Tokenizer::iter_with_options(
+            input,
+            TokenizerOptions {
+                tabsize,
+                ..Default::default()
+            },
+        )
+    }
+
+    pub fn iter_with_options(input: &'a str, options: TokenizerOptions) -> TokenIter<'a> {
+        TokenIter {
+            tokenizer: Tokenizer {
+                tokens: Vec::new(),
+                comments: Vec::new(),
+                input,
+                iter: input.chars().peekable(),
+                prev_pos: TokenPosition::default(),
+                current_pos: TokenPosition::default(),
+                tabsize: options.tabsize as usize,
+                semicolon_comments: options.semicolon_comments,
+                max_identifier_length: options.max_identifier_length,
+                defines: options.defines,
+                conditions: Vec::new(),
+                includes: None,
+            },
+            finished: false,
+        }
+    }
+
+    /// Find the '.include' lines that would be assembled, leaving out any inside comments
+    /// or '.if' blocks that are not included. Stops at the first error, which tokenizing
+    /// the source once its includes are expanded will report.
+    pub fn find_includes(input: &'a str, options: TokenizerOptions) -> Vec<IncludeDirective> {
+        let mut iter = Tokenizer::iter_with_options(input, options);
+        iter.tokenizer.includes = Some(Vec::new());
+        while let Some(Ok(_)) = iter.next() {}
+        iter.tokenizer.includes.unwrap_or_default()
+    }
+
     /// tokenize takes in an input source code string and returns a Vec of tokens and
     /// a HashMap of label definitions to their corresponding byte position and the
     /// amount of bytes the resulting progam will use. There are multiple types of token:
     /// * Newlines / Semicolons (both are used a line delimeters)
     /// * Commas
-    /// * Memory references (An unsigned 8 bit number e.g 12)
+    /// * Memory references (An unsigned 8 bit number e.g 12, 0x0C or 0b1100)
     /// * Literals (A '#' follwed by an unsigned 8 bit number e.g. #12)
     /// * Registers (An 'R' followed by a number in the range 0 to REGISTER_COUNT-1)
     /// * Opcodes (A string of chars that make any of our opcodes)
     /// * Label operands (A string of chars)
     /// * Label definitions (A string of chars followed by a colon)
+    ///
     /// Label checking does not happen in this stage. All label operands are initialised
     /// with the value 0. The next stage, parsing and instruction loading, will verify
     /// that labels are correct and exist when using them.
     /// The tokenzier will terminate early if it detects that too many bytes for the program
     /// have been loaded. Max 255.
-    fn interal_tokenize(&mut self) -> Result<(), TokenizerError> {
-        // Main tokenization loop
-        while let Some(&ch) = self.iter.peek() {
+    ///
+    /// Each call consumes the input up to the end of the next token, or past a run of
+    /// whitespace or a comment, adding at most one token.
+    fn tokenize_next(&mut self) -> Result<(), TokenizerError> {
+        if self.is_skipping() {
+            return self.skip_excluded();
+        }
+        if let Some(&ch) = self.iter.peek() {
+            // Give a clearer error for characters pasted in from a word processor, which
+            // would otherwise be skipped as whitespace or reported as unexpected
+            if lookalike_description(ch).is_some() {
+                return Err(TokenizerError::UnicodeLookalike(Box::new(
+                    UnicodeLookalike {
+                        char: ch,
+                        line: self.prev_pos.line,
+                        col: self.prev_pos.col,
+                    },
+                )));
+            }
             // Ignore any whitespace characters
             if ch != '\n' && ch.is_whitespace() {
                 self.next();
                 self.prev_pos = self.current_pos.clone();
-                continue;
+                return Ok(());
             }
 
             match ch {
                 '\n' => self.add_single_char_token(TokenKind::Newline)?,
+                ';' if self.semicolon_comments => self.semicolon_comment(),
                 ';' => self.add_single_char_token(TokenKind::Semicolon)?,
                 ',' => self.add_single_char_token(TokenKind::Comma)?,
-                '0'..='9' => self.tokenize_memory_reference()?,
+                '0'..='9' => self.tokenize_memory_reference(ch)?,
                 'a'..='z' | 'A'..='Z' | '_' => self.tokenize_identifier()?,
                 '#' => self.tokenize_literal()?,
                 '/' => self.comment()?,
+                '.' => self.directive()?,
                 ch => {
                     return Err(TokenizerError::UnexpectedCharacter(Box::new(
                         UnexpectedCharacter {
@@ -84,6 +195,141 @@ impl<'a> Tokenizer<'a> {
         Ok(())
     }
 
+    /// Whether all of the input has been consumed
+    fn is_finished(&mut self) -> bool {
+        self.iter.peek().is_none()
+    }
+
+    /// Whether the input is inside an '.if' block that is not included
+    fn is_skipping(&self) -> bool {
+        self.conditions.iter().any(|(included, _)| !included)
+    }
+
+    /// Consume excluded input a line at a time, only looking for directives that start
+    /// a line so that nested blocks are matched up
+    fn skip_excluded(&mut self) -> Result<(), TokenizerError> {
+        match self.iter.peek() {
+            Some('.') => return self.directive(),
+            Some(&ch) if ch.is_whitespace() => {
+                self.next();
+            }
+            Some(_) => self.skip_to_end_of_line(),
+            None => {}
+        }
+        self.prev_pos = self.current_pos.clone();
+        Ok(())
+    }
+
+    /// Handle a directive on a line of its own. These are either for conditional assembly,
+    /// '.if NAME' which only includes the lines up to the matching '.endif' if NAME is
+    /// defined, for the data section, '.data' and '.space n', or '.equ NAME #value' to
+    /// define a constant. '.include' is only read when finding includes, as they are
+    /// expanded before tokenizing, or when it is skipped inside an '.if' block.
+    fn directive(&mut self) -> Result<(), TokenizerError> {
+        let line_start = self.input[..self.prev_pos.idx]
+            .rfind('\n')
+            .map_or(0, |idx| idx + 1);
+        let starts_line = self.input[line_start..self.prev_pos.idx].trim().is_empty();
+        self.next(); // Consume the '.'
+        let name = self.consume_while(|ch| ch.is_alphanumeric() || ch == '_');
+        self.consume_while(|ch| ch != '\n' && ch.is_whitespace());
+        // The name and value of a constant are tokenized as operands, for the parser to
+        // collect
+        if starts_line && matches!(name.as_str(), "equ" | "EQU") {
+            if self.is_skipping() {
+                self.skip_to_end_of_line();
+                self.prev_pos = self.current_pos.clone();
+                return Ok(());
+            }
+            return self.add_directive_token(TokenKind::Equ);
+        }
+        if starts_line && name == "include" && (self.includes.is_some() || self.is_skipping()) {
+            let skipping = self.is_skipping();
+            let start = self.current_pos.idx;
+            self.skip_to_end_of_line();
+            let argument = self.input[start..self.current_pos.idx]
+                .trim_end()
+                .to_string();
+            if let (false, Some(includes)) = (skipping, &mut self.includes) {
+                includes.push(IncludeDirective {
+                    line: self.prev_pos.line,
+                    argument,
+                });
+            }
+            self.prev_pos = self.current_pos.clone();
+            return Ok(());
+        }
+        let argument = self.consume_while(|ch| ch.is_alphanumeric() || ch == '_');
+        self.consume_while(|ch| ch != '\n' && ch.is_whitespace());
+        // Anything after the directive must be a comment
+        let ends_line = match self.iter.peek() {
+            None | Some('\n' | '/') => true,
+            Some(';') => self.semicolon_comments,
+            Some(_) => false,
+        };
+
+        match (name.as_str(), argument.is_empty()) {
+            ("if", false) if starts_line && ends_line => {
+                let included = !self.is_skipping() && self.defines.contains(&argument);
+                self.conditions.push((included, self.prev_pos.clone()));
+            }
+            ("endif", true) if starts_line && ends_line => {
+                if self.conditions.pop().is_none() {
+                    return Err(TokenizerError::UnmatchedEndif(Box::new(UnmatchedEndif {
+                        line: self.prev_pos.line,
+                        col: self.prev_pos.col,
+                    })));
+                }
+            }
+            ("data", true) if starts_line && ends_line => {
+                if !self.is_skipping() {
+                    return self.add_directive_token(TokenKind::DataSection);
+                }
+            }
+            ("space", false) if starts_line && ends_line && argument.parse::<u8>().is_ok() => {
+                if !self.is_skipping() {
+                    return self.add_directive_token(TokenKind::Space(argument.parse().unwrap()));
+                }
+            }
+            _ => {
+                self.skip_to_end_of_line();
+                return Err(TokenizerError::InvalidDirective(Box::new(
+                    InvalidDirective {
+                        directive: self.input[self.prev_pos.idx..self.current_pos.idx]
+                            .trim_end()
+                            .to_string(),
+                        line: self.prev_pos.line,
+                        col: self.prev_pos.col,
+                    },
+                )));
+            }
+        }
+        self.prev_pos = self.current_pos.clone();
+        Ok(())
+    }
+
+    /// Add a token for the directive that was just consumed, leaving out the whitespace
+    /// after it
+    fn add_directive_token(&mut self, kind: TokenKind) -> Result<(), TokenizerError> {
+        let lexeme = self.input[self.prev_pos.idx..self.current_pos.idx].trim_end();
+        self.tokens.push(
+            Token::new(kind, lexeme, self.prev_pos.line, self.prev_pos.col)
+                .with_raw_position(self.prev_pos.raw_col, self.prev_pos.idx),
+        );
+        self.prev_pos = self.current_pos.clone();
+        Ok(())
+    }
+
+    /// Error for the innermost '.if' that was never closed, if there is one
+    fn unterminated_if(&self) -> Option<TokenizerError> {
+        self.conditions.last().map(|(_, pos)| {
+            TokenizerError::UnterminatedIf(Box::new(UnterminatedIf {
+                line: pos.line,
+                col: pos.col,
+            }))
+        })
+    }
+
     /// Consume character, adjusting token positions accordingly
     fn next(&mut self) -> Option<char> {
         if let Some(ch) = self.iter.next() {
@@ -91,14 +337,21 @@ impl<'a> Tokenizer<'a> {
                 '\n' => {
                     self.current_pos.line += 1;
                     self.current_pos.col = 1;
+                    self.current_pos.raw_col = 1;
+                }
+                '\t' => {
+                    self.current_pos.col += self.tabsize;
+                    self.current_pos.raw_col += 1;
+                }
+                _ => {
+                    self.current_pos.col += 1;
+                    self.current_pos.raw_col += 1;
                 }
-                '\t' => self.current_pos.col += self.tabsize as usize,
-                _ => self.current_pos.col += 1,
             }
             self.current_pos.idx += ch.len_utf8();
             return Some(ch);
         }
-        return None;
+        None
     }
 
     /// Consume a string of characters while a condition is met
@@ -122,33 +375,66 @@ impl<'a> Tokenizer<'a> {
         if lexeme == "\n" {
             lexeme = String::from("\\n");
         }
-        self.tokens.push(Token::new(
-            kind,
-            &lexeme,
-            self.prev_pos.line,
-            self.prev_pos.col,
-        ));
+        self.tokens.push(
+            Token::new(kind, &lexeme, self.prev_pos.line, self.prev_pos.col)
+                .with_raw_position(self.prev_pos.raw_col, self.prev_pos.idx),
+        );
         self.prev_pos = self.current_pos.clone();
         Ok(())
     }
 
+    /// Consume an unsigned 8 bit number. Numbers are decimal unless prefixed with `0x`
+    /// for hexadecimal or `0b` for binary, in either case. A bare `0` is decimal zero.
     fn consume_u8(&mut self) -> Option<Result<u8, TokenizerError>> {
-        let value_string = self.consume_while(|ch| ch.is_digit(10));
-        if value_string == "" {
-            None
+        let mut value_string = self.consume_while(|ch| ch.is_ascii_digit());
+        if value_string.is_empty() {
+            return None;
+        }
+        let radix = match self.iter.peek() {
+            Some('x' | 'X') if value_string == "0" => 16,
+            Some('b' | 'B') if value_string == "0" => 2,
+            _ => 10,
+        };
+        let digits = if radix == 10 {
+            value_string.clone()
         } else {
-            match value_string.parse::<u8>() {
-                Ok(value) => Some(Ok(value)),
-                Err(_) => {
-                    return Some(Err(TokenizerError::LiteralValueTooLarge(Box::new(
-                        LiteralValueTooLarge {
-                            value_string,
-                            line: self.prev_pos.line,
-                            col: self.prev_pos.col,
-                        },
-                    ))))
-                }
+            value_string.push(self.next().unwrap());
+            // Take every alphanumeric character so that `0xG` is an error rather than
+            // a zero followed by a label
+            let digits_col = self.current_pos.col;
+            let digits = self.consume_while(|ch| ch.is_ascii_alphanumeric());
+            if let Some(idx) = digits.find(|ch: char| !ch.is_digit(radix)) {
+                return Some(Err(TokenizerError::InvalidDigitForRadix(Box::new(
+                    InvalidDigitForRadix {
+                        char: digits[idx..].chars().next().unwrap(),
+                        radix,
+                        line: self.prev_pos.line,
+                        col: digits_col + idx,
+                    },
+                ))));
             }
+            if digits.is_empty() {
+                return Some(Err(TokenizerError::MissingDigitsAfterRadixPrefix(
+                    Box::new(MissingDigitsAfterRadixPrefix {
+                        prefix: value_string,
+                        line: self.prev_pos.line,
+                        col: self.prev_pos.col,
+                    }),
+                )));
+            }
+            value_string.push_str(&digits);
+            digits
+        };
+        match u8::from_str_radix(&digits, radix) {
+            Ok(value) => Some(Ok(value)),
+            Err(_) => Some(Err(TokenizerError::LiteralValueTooLarge(Box::new(
+                LiteralValueTooLarge {
+                    value_string,
+                    line: self.prev_pos.line,
+                    col: self.prev_pos.col,
+                    end_col: self.current_pos.col - 1,
+                },
+            )))),
         }
     }
 
@@ -157,29 +443,67 @@ impl<'a> Tokenizer<'a> {
         self.add_token(kind)
     }
 
-    fn tokenize_memory_reference(&mut self) -> Result<(), TokenizerError> {
-        let value = self.consume_u8().unwrap()?;
+    fn tokenize_memory_reference(&mut self, ch: char) -> Result<(), TokenizerError> {
+        let Some(value) = self.consume_u8() else {
+            return Err(TokenizerError::UnexpectedCharacter(Box::new(UnexpectedCharacter {
+                char: ch,
+                line: self.prev_pos.line,
+                col: self.prev_pos.col,
+            })));
+        };
+        let value = value?;
         self.add_token(TokenKind::Operand(Operand::MemoryRef(value)))
     }
 
     fn tokenize_literal(&mut self) -> Result<(), TokenizerError> {
         self.next();
+        if let Some('-') = self.iter.peek() {
+            self.next();
+            return self.tokenize_negative_literal();
+        }
         match self.consume_u8() {
             Some(Ok(val)) => self.add_token(TokenKind::Operand(Operand::Literal(val))),
-            Some(Err(err)) => return Err(err),
-            None => {
-                return Err(TokenizerError::MissingNumberAfterLiteralDenoter(Box::new(
+            // '#0x' has no number after the '#' any more than '#' on its own does
+            Some(Err(TokenizerError::MissingDigitsAfterRadixPrefix(_))) | None => {
+                Err(TokenizerError::MissingNumberAfterLiteralDenoter(Box::new(
                     MissingNumberAfterLiteralDenoter {
                         line: self.prev_pos.line,
                         col: self.prev_pos.col,
                     },
                 )))
             }
+            Some(Err(err)) => Err(err),
+        }
+    }
+
+    /// Negative literals are encoded as their 8 bit two's complement, so #-1 is 255
+    fn tokenize_negative_literal(&mut self) -> Result<(), TokenizerError> {
+        let value_string = self.consume_while(|ch| ch.is_ascii_digit());
+        if value_string.is_empty() {
+            return Err(TokenizerError::MissingNumberAfterLiteralDenoter(Box::new(
+                MissingNumberAfterLiteralDenoter {
+                    line: self.prev_pos.line,
+                    col: self.prev_pos.col,
+                },
+            )));
+        }
+        match value_string.parse::<u8>() {
+            Ok(magnitude) if magnitude <= 128 => {
+                self.add_token(TokenKind::Operand(Operand::Literal(0u8.wrapping_sub(magnitude))))
+            }
+            _ => Err(TokenizerError::NegativeLiteralValueTooSmall(Box::new(
+                NegativeLiteralValueTooSmall {
+                    value_string: format!("-{value_string}"),
+                    line: self.prev_pos.line,
+                    col: self.prev_pos.col,
+                    end_col: self.current_pos.col - 1,
+                },
+            ))),
         }
     }
 
     fn tokenize_identifier(&mut self) -> Result<(), TokenizerError> {
-        let identifier = self.consume_while(|ch| ch.is_alphabetic() || ch == '_');
+        let mut identifier = self.consume_while(|ch| ch.is_alphabetic() || ch == '_');
         // Register
         if identifier == "R" {
             return match self.consume_u8() {
@@ -190,6 +514,7 @@ impl<'a> Tokenizer<'a> {
                                 value: val,
                                 line: self.prev_pos.line,
                                 col: self.prev_pos.col,
+                                end_col: self.current_pos.col - 1,
                             },
                         )));
                     }
@@ -205,6 +530,22 @@ impl<'a> Tokenizer<'a> {
                 ))),
             };
         }
+        // Labels may contain digits after the first character, such as `loop2`
+        identifier.push_str(&self.consume_while(|ch| ch.is_alphanumeric() || ch == '_'));
+        if identifier.chars().count() > self.max_identifier_length {
+            return Err(TokenizerError::IdentifierTooLong(Box::new(
+                IdentifierTooLong {
+                    name_prefix: identifier.chars().take(IDENTIFIER_PREFIX_LENGTH).collect(),
+                    max_length: self.max_identifier_length,
+                    line: self.prev_pos.line,
+                    col: self.prev_pos.col,
+                },
+            )));
+        }
+        if identifier == "DATA" {
+            self.add_token(TokenKind::Data)?;
+            return Ok(());
+        }
         // Is it an opcode?
         if let Ok(source_opcode) = SourceOpcode::from_str(&identifier) {
             self.add_token(TokenKind::Opcode(source_opcode))?;
@@ -222,27 +563,53 @@ impl<'a> Tokenizer<'a> {
         Ok(())
     }
 
+    /// Consume characters up to, but not including, the next newline
+    fn skip_to_end_of_line(&mut self) {
+        while let Some(&ch) = self.iter.peek() {
+            if ch == '\n' {
+                break;
+            }
+            self.next();
+        }
+    }
+
+    /// In semicolon comment mode a ';' comments out the rest of the line
+    fn semicolon_comment(&mut self) {
+        self.skip_to_end_of_line();
+        self.add_comment(1, 0);
+    }
+
+    /// Record the comment between the previous and current positions, with the given
+    /// number of delimeter bytes at its start and end
+    fn add_comment(&mut self, start_delimeter: usize, end_delimeter: usize) {
+        let start = self.prev_pos.idx + start_delimeter;
+        let end = self.current_pos.idx - end_delimeter;
+        // The '*' of "/*/" is shared by both delimeters, leaving no text
+        let text = self.input.get(start..end).unwrap_or_default();
+        self.comments.push(Comment {
+            text: text.trim().to_string(),
+            line: self.prev_pos.line,
+            col: self.prev_pos.col,
+            offset: self.prev_pos.idx,
+        });
+        self.prev_pos = self.current_pos.clone();
+    }
+
     fn comment(&mut self) -> Result<(), TokenizerError> {
         self.next();
-        match self.iter.peek() {
+        let end_delimeter = match self.iter.peek() {
             // Comments starts with // therefore its a line comment
             Some('/') => {
-                self.next();
-                while let Some(&ch) = self.iter.peek() {
-                    if ch == '\n' {
-                        break;
-                    } else {
-                        self.next();
-                    }
-                }
+                self.skip_to_end_of_line();
+                0
             }
             // Comment starts with a /* so its multiline
             Some('*') => loop {
                 match self.next() {
-                    Some(ch) if ch == '*' => {
+                    Some('*') => {
                         if self.iter.peek() == Some(&'/') {
                             self.next(); // Consume the '/'
-                            break; // Exit the loop
+                            break 2; // Exit the loop
                         }
                     }
                     Some(_) => continue, // Continue if it's not '*'
@@ -265,12 +632,42 @@ impl<'a> Tokenizer<'a> {
                     },
                 )))
             }
-        }
-        self.prev_pos = self.current_pos.clone();
+        };
+        self.add_comment(2, end_delimeter);
         Ok(())
     }
 }
 
+/// Iterator over the tokens of some source code, created by [`Tokenizer::iter`]
+#[derive(Debug)]
+pub struct TokenIter<'a> {
+    tokenizer: Tokenizer<'a>,
+    finished: bool,
+}
+
+impl Iterator for TokenIter<'_> {
+    type Item = Result<Token, TokenizerError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        // Each step adds at most one token, so there is never more than one waiting
+        while !self.finished {
+            if let Some(token) = self.tokenizer.tokens.pop() {
+                return Some(Ok(token));
+            }
+            if self.tokenizer.is_finished() {
+                self.finished = true;
+                if let Some(err) = self.tokenizer.unterminated_if() {
+                    return Some(Err(err));
+                }
+            } else if let Err(err) = self.tokenizer.tokenize_next() {
+                self.finished = true;
+                return Some(Err(err));
+            }
+        }
+        None
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use crate::interpreter::instruction::source_opcode::SourceOpcode;
@@ -327,6 +724,21 @@ mod tests {
             ("PRINT", TokenKind::Opcode(SourceOpcode::PRINT)),
             ("INPUT", TokenKind::Opcode(SourceOpcode::INPUT)),
             ("HALT", TokenKind::Opcode(SourceOpcode::HALT)),
+            ("CPY", TokenKind::Opcode(SourceOpcode::CPY)),
+            ("PRINTARR", TokenKind::Opcode(SourceOpcode::PRINTARR)),
+            ("DIV", TokenKind::Opcode(SourceOpcode::DIV)),
+            ("MOD", TokenKind::Opcode(SourceOpcode::MOD)),
+            ("PUSH", TokenKind::Opcode(SourceOpcode::PUSH)),
+            ("POP", TokenKind::Opcode(SourceOpcode::POP)),
+            ("CALL", TokenKind::Opcode(SourceOpcode::CALL)),
+            ("RET", TokenKind::Opcode(SourceOpcode::RET)),
+            ("BCS", TokenKind::Opcode(SourceOpcode::BCS)),
+            ("BCC", TokenKind::Opcode(SourceOpcode::BCC)),
+            ("ROL", TokenKind::Opcode(SourceOpcode::ROL)),
+            ("ROR", TokenKind::Opcode(SourceOpcode::ROR)),
+            ("INC", TokenKind::Opcode(SourceOpcode::INC)),
+            ("DEC", TokenKind::Opcode(SourceOpcode::DEC)),
+            ("LDA", TokenKind::Opcode(SourceOpcode::LDA)),
         ] {
             test_token_type_sequence(input, &[expected_output]);
         }
@@ -356,6 +768,244 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_identifier_too_long() {
+        let label = "a".repeat(100);
+        let options = TokenizerOptions {
+            max_identifier_length: 32,
+            ..Default::default()
+        };
+        assert_eq!(
+            Tokenizer::tokenize_with_options(&format!("NOP\n  {label}: HALT"), options.clone())
+                .unwrap_err(),
+            TokenizerError::IdentifierTooLong(Box::new(IdentifierTooLong {
+                name_prefix: "a".repeat(IDENTIFIER_PREFIX_LENGTH),
+                max_length: 32,
+                line: 2,
+                col: 3,
+            }))
+        );
+        assert!(Tokenizer::tokenize_with_options(&format!("B {}", &label[..32]), options).is_ok());
+        assert!(Tokenizer::tokenize(&format!("B {}", &label[..64]), 4).is_ok());
+    }
+
+    #[test]
+    fn test_semicolon_comments() {
+        let options = TokenizerOptions {
+            semicolon_comments: true,
+            ..Default::default()
+        };
+        let tokens = Tokenizer::tokenize_with_options("MOV R0,#1 ; set\nHALT", options)
+            .unwrap()
+            .tokens;
+        assert_eq!(
+            extract_token_types(tokens),
+            [
+                TokenKind::Opcode(SourceOpcode::MOV),
+                TokenKind::Operand(Operand::Register(0)),
+                TokenKind::Comma,
+                TokenKind::Operand(Operand::Literal(1)),
+                TokenKind::Newline,
+                TokenKind::Opcode(SourceOpcode::HALT),
+            ]
+        );
+
+        // Default mode is unchanged
+        test_token_type_sequence(
+            "MOV R0,#1 ; HALT",
+            &[
+                TokenKind::Opcode(SourceOpcode::MOV),
+                TokenKind::Operand(Operand::Register(0)),
+                TokenKind::Comma,
+                TokenKind::Operand(Operand::Literal(1)),
+                TokenKind::Semicolon,
+                TokenKind::Opcode(SourceOpcode::HALT),
+            ],
+        );
+    }
+
+    #[test]
+    fn test_collected_comments() {
+        let comments = Tokenizer::tokenize("NOP // first \n\t/* second\nline */ HALT /*/", 4)
+            .unwrap()
+            .comments;
+        assert_eq!(
+            comments,
+            [
+                Comment {
+                    text: String::from("first"),
+                    line: 1,
+                    col: 5,
+                    offset: 4,
+                },
+                Comment {
+                    text: String::from("second\nline"),
+                    line: 2,
+                    col: 5,
+                    offset: 15,
+                },
+                Comment {
+                    text: String::new(),
+                    line: 3,
+                    col: 14,
+                    offset: 38,
+                },
+            ]
+        );
+
+        let options = TokenizerOptions {
+            semicolon_comments: true,
+            ..Default::default()
+        };
+        let comments = Tokenizer::tokenize_with_options("HALT ;done", options)
+            .unwrap()
+            .comments;
+        assert_eq!(comments[0].text, "done");
+        assert_eq!(comments[0].offset, 5);
+    }
+
+    #[test]
+    fn test_conditional_assembly() {
+        let source = "MOV R0, #1\n.if DEBUG // only when debugging\nPRINT R0\n  .if VERBOSE\n  PRINT R1\n  .endif\n.endif\nHALT";
+        let tokenize = |defines: &[&str]| {
+            let options = TokenizerOptions {
+                defines: defines.iter().map(|name| name.to_string()).collect(),
+                ..Default::default()
+            };
+            let tokens = Tokenizer::tokenize_with_options(source, options)
+                .unwrap()
+                .tokens;
+            tokens
+                .into_iter()
+                .filter_map(|token| match token.kind {
+                    TokenKind::Opcode(opcode) => Some((opcode, token.line)),
+                    _ => None,
+                })
+                .collect::<Vec<_>>()
+        };
+
+        // Excluded block, along with the block nested inside it
+        assert_eq!(
+            tokenize(&["VERBOSE"]),
+            [(SourceOpcode::MOV, 1), (SourceOpcode::HALT, 8)]
+        );
+        // Included block
+        assert_eq!(
+            tokenize(&["DEBUG"]),
+            [
+                (SourceOpcode::MOV, 1),
+                (SourceOpcode::PRINT, 3),
+                (SourceOpcode::HALT, 8)
+            ]
+        );
+        assert_eq!(tokenize(&["DEBUG", "VERBOSE"]).len(), 4);
+    }
+
+    #[test]
+    fn test_invalid_conditional_assembly() {
+        assert_eq!(
+            Tokenizer::tokenize("NOP\n.if DEBUG\nNOP\n", 4).unwrap_err(),
+            TokenizerError::UnterminatedIf(Box::new(UnterminatedIf { line: 2, col: 1 }))
+        );
+        assert_eq!(
+            Tokenizer::tokenize("NOP\n  .endif\n", 4).unwrap_err(),
+            TokenizerError::UnmatchedEndif(Box::new(UnmatchedEndif { line: 2, col: 3 }))
+        );
+        assert_eq!(
+            Tokenizer::tokenize("NOP .if DEBUG\n", 4).unwrap_err(),
+            TokenizerError::InvalidDirective(Box::new(InvalidDirective {
+                directive: String::from(".if DEBUG"),
+                line: 1,
+                col: 5,
+            }))
+        );
+        for source in [".if\n.endif", ".if A B\n.endif", ".endif A", ".else\n"] {
+            assert!(matches!(
+                Tokenizer::tokenize(source, 4).unwrap_err(),
+                TokenizerError::InvalidDirective(_)
+            ));
+        }
+    }
+
+    #[test]
+    fn test_data_section_directives() {
+        let tokens = Tokenizer::tokenize("HALT\n.data\nvalues:\n  .space 12 // three\n", 4)
+            .unwrap()
+            .tokens;
+        assert_eq!(
+            tokens
+                .iter()
+                .map(|token| (token.kind, token.lexeme.as_str()))
+                .collect::<Vec<_>>(),
+            [
+                (TokenKind::Opcode(SourceOpcode::HALT), "HALT"),
+                (TokenKind::Newline, "\\n"),
+                (TokenKind::DataSection, ".data"),
+                (TokenKind::Newline, "\\n"),
+                (TokenKind::LabelDefinition, "values:"),
+                (TokenKind::Newline, "\\n"),
+                (TokenKind::Space(12), ".space 12"),
+                (TokenKind::Newline, "\\n"),
+            ]
+        );
+        // Left out of excluded blocks like any other line
+        assert_eq!(
+            Tokenizer::tokenize(".if DEBUG\n.data\n.space 1\n.endif\n", 4)
+                .unwrap()
+                .tokens
+                .len(),
+            1
+        );
+        let tokens = Tokenizer::tokenize("table: DATA #1, 2\n", 4)
+            .unwrap()
+            .tokens;
+        let kinds: Vec<TokenKind> = tokens.iter().map(|token| token.kind).collect();
+        assert_eq!(
+            kinds,
+            [
+                TokenKind::LabelDefinition,
+                TokenKind::Data,
+                TokenKind::Operand(Operand::Literal(1)),
+                TokenKind::Comma,
+                TokenKind::Operand(Operand::MemoryRef(2)),
+                TokenKind::Newline,
+            ]
+        );
+        for source in [".data 4\n", ".space\n", ".space 256\n", ".space n\n"] {
+            assert!(matches!(
+                Tokenizer::tokenize(source, 4).unwrap_err(),
+                TokenizerError::InvalidDirective(_)
+            ));
+        }
+    }
+
+    #[test]
+    fn test_equ_directive() {
+        let tokens = Tokenizer::tokenize(".EQU MAX #10 // limit\n", 4)
+            .unwrap()
+            .tokens;
+        assert_eq!(
+            tokens
+                .iter()
+                .map(|token| (token.kind, token.lexeme.as_str()))
+                .collect::<Vec<_>>(),
+            [
+                (TokenKind::Equ, ".EQU"),
+                (TokenKind::Operand(Operand::Label), "MAX"),
+                (TokenKind::Operand(Operand::Literal(10)), "#10"),
+                (TokenKind::Newline, "\\n"),
+            ]
+        );
+        let tokens = Tokenizer::tokenize(".if DEBUG\n.equ MAX #10\n.endif\n", 4)
+            .unwrap()
+            .tokens;
+        assert_eq!(tokens.len(), 1);
+        assert!(matches!(
+            Tokenizer::tokenize("HALT .equ MAX #10\n", 4).unwrap_err(),
+            TokenizerError::InvalidDirective(_)
+        ));
+    }
+
     #[test]
     fn test_empty_program() {
         test_token_type_sequence("", &[]);
@@ -421,7 +1071,8 @@ mod tests {
             TokenizerError::InvalidRegisterNumber(Box::new(InvalidRegisterNumber {
                 value: 13,
                 line: 1,
-                col: 1
+                col: 1,
+                end_col: 3
             }))
         );
 
@@ -430,7 +1081,8 @@ mod tests {
             TokenizerError::LiteralValueTooLarge(Box::new(LiteralValueTooLarge {
                 value_string: String::from("12345"),
                 line: 1,
-                col: 1
+                col: 1,
+                end_col: 6
             }))
         )
     }
@@ -442,7 +1094,30 @@ mod tests {
             TokenizerError::LiteralValueTooLarge(Box::new(LiteralValueTooLarge {
                 value_string: String::from("256"),
                 line: 1,
-                col: 1
+                col: 1,
+                end_col: 3
+            }))
+        );
+    }
+
+    #[test]
+    fn test_operand_error_spans() {
+        assert_eq!(
+            Tokenizer::tokenize("MOV R99, #1", 4).unwrap_err(),
+            TokenizerError::InvalidRegisterNumber(Box::new(InvalidRegisterNumber {
+                value: 99,
+                line: 1,
+                col: 5,
+                end_col: 7
+            }))
+        );
+        assert_eq!(
+            Tokenizer::tokenize("MOV R0, #300", 4).unwrap_err(),
+            TokenizerError::LiteralValueTooLarge(Box::new(LiteralValueTooLarge {
+                value_string: String::from("300"),
+                line: 1,
+                col: 9,
+                end_col: 12
             }))
         );
     }
@@ -454,11 +1129,106 @@ mod tests {
             TokenizerError::LiteralValueTooLarge(Box::new(LiteralValueTooLarge {
                 value_string: String::from("256"),
                 line: 1,
-                col: 1
+                col: 1,
+                end_col: 4
             }))
         );
     }
 
+    #[test]
+    fn test_label_names_with_digits() {
+        test_token_type_sequence(
+            "loop2: B L_0A",
+            &[
+                TokenKind::LabelDefinition,
+                TokenKind::Opcode(SourceOpcode::B),
+                TokenKind::Operand(Operand::Label),
+            ],
+        );
+        test_token_type_sequence("R12", &[TokenKind::Operand(Operand::Register(12))]);
+    }
+
+    #[test]
+    fn test_radix_literals() {
+        for (input, expected) in [
+            ("#0", TokenKind::Operand(Operand::Literal(0))),
+            ("#0x1F", TokenKind::Operand(Operand::Literal(31))),
+            ("#0XfF", TokenKind::Operand(Operand::Literal(255))),
+            ("#0xFF", TokenKind::Operand(Operand::Literal(255))),
+            ("#0b101", TokenKind::Operand(Operand::Literal(5))),
+            ("#0b1010", TokenKind::Operand(Operand::Literal(10))),
+            ("#0B11", TokenKind::Operand(Operand::Literal(3))),
+            ("0x10", TokenKind::Operand(Operand::MemoryRef(16))),
+        ] {
+            test_token_type_sequence(input, &[expected]);
+        }
+        assert_eq!(
+            Tokenizer::tokenize("MOV R0, #0x", 4).unwrap_err(),
+            TokenizerError::MissingNumberAfterLiteralDenoter(Box::new(
+                MissingNumberAfterLiteralDenoter { line: 1, col: 9 }
+            ))
+        );
+        assert_eq!(
+            Tokenizer::tokenize("LDR R0, 0x", 4).unwrap_err(),
+            TokenizerError::MissingDigitsAfterRadixPrefix(Box::new(
+                MissingDigitsAfterRadixPrefix {
+                    prefix: String::from("0x"),
+                    line: 1,
+                    col: 9
+                }
+            ))
+        );
+        assert_eq!(
+            Tokenizer::tokenize("#0b2", 4).unwrap_err(),
+            TokenizerError::InvalidDigitForRadix(Box::new(InvalidDigitForRadix {
+                char: '2',
+                radix: 2,
+                line: 1,
+                col: 4
+            }))
+        );
+        assert_eq!(
+            Tokenizer::tokenize("#0x1G", 4).unwrap_err(),
+            TokenizerError::InvalidDigitForRadix(Box::new(InvalidDigitForRadix {
+                char: 'G',
+                radix: 16,
+                line: 1,
+                col: 5
+            }))
+        );
+        assert_eq!(
+            Tokenizer::tokenize("#0x100", 4).unwrap_err(),
+            TokenizerError::LiteralValueTooLarge(Box::new(LiteralValueTooLarge {
+                value_string: String::from("0x100"),
+                line: 1,
+                col: 1,
+                end_col: 6
+            }))
+        );
+    }
+
+    #[test]
+    fn test_negative_literals() {
+        for (input, expected) in [("#-1", 255), ("#-5", 251), ("#-128", 128), ("#-0", 0)] {
+            test_token_type_sequence(input, &[TokenKind::Operand(Operand::Literal(expected))]);
+        }
+        assert_eq!(
+            Tokenizer::tokenize("#-129", 4).unwrap_err(),
+            TokenizerError::NegativeLiteralValueTooSmall(Box::new(NegativeLiteralValueTooSmall {
+                value_string: String::from("-129"),
+                line: 1,
+                col: 1,
+                end_col: 5
+            }))
+        );
+        assert_eq!(
+            Tokenizer::tokenize("MOV R5 #-", 4).unwrap_err(),
+            TokenizerError::MissingNumberAfterLiteralDenoter(Box::new(
+                MissingNumberAfterLiteralDenoter { line: 1, col: 8 }
+            ))
+        );
+    }
+
     #[test]
     fn test_invalid_characters() {
         assert_eq!(
@@ -471,6 +1241,95 @@ mod tests {
         )
     }
 
+    #[test]
+    fn test_streamed_tokens_match_tokenize() {
+        let input = "start: MOV R0, #0x0A ; NOP\n/* block\ncomment */ loop2: SUB R0, R0, #-1 // done\n\tBNE loop2\nHALT";
+        let streamed: Vec<Token> = Tokenizer::iter(input, 4).collect::<Result<_, _>>().unwrap();
+        assert_eq!(streamed, Tokenizer::tokenize(input, 4).unwrap().tokens);
+
+        // Tokens before an error are still yielded, and nothing is yielded after it
+        let mut iter = Tokenizer::iter("NOP\nMOV R0, ?\nHALT", 4);
+        assert_eq!(
+            iter.by_ref()
+                .take(4)
+                .map(|token| token.unwrap().kind)
+                .collect::<Vec<_>>(),
+            [
+                TokenKind::Opcode(SourceOpcode::NOP),
+                TokenKind::Newline,
+                TokenKind::Opcode(SourceOpcode::MOV),
+                TokenKind::Operand(Operand::Register(0)),
+            ]
+        );
+        assert_eq!(
+            iter.next(),
+            Some(Ok(
+                Token::new(TokenKind::Comma, ",", 2, 7).with_raw_position(7, 10)
+            ))
+        );
+        assert_eq!(
+            iter.next(),
+            Some(Err(TokenizerError::UnexpectedCharacter(Box::new(
+                UnexpectedCharacter {
+                    char: '?',
+                    line: 2,
+                    col: 9
+                }
+            ))))
+        );
+        assert_eq!(iter.next(), None);
+    }
+
+    #[test]
+    fn test_raw_positions() {
+        let tokens = Tokenizer::tokenize("\tMOV\tR0, #1\n\t\tHALT", 4)
+            .unwrap()
+            .tokens;
+        let positions: Vec<(usize, usize, usize, usize)> = tokens
+            .iter()
+            .map(|token| (token.line, token.col, token.raw_col, token.offset))
+            .collect();
+        assert_eq!(
+            positions,
+            [
+                (1, 5, 2, 1),
+                (1, 12, 6, 5),
+                (1, 14, 8, 7),
+                (1, 16, 10, 9),
+                (1, 18, 12, 11),
+                (2, 9, 3, 14),
+            ]
+        );
+        // Offsets are in bytes, so they skip over multi-byte characters
+        let tokens = Tokenizer::tokenize("/* é */ NOP", 4).unwrap().tokens;
+        assert_eq!(
+            (tokens[0].col, tokens[0].raw_col, tokens[0].offset),
+            (9, 9, 9)
+        );
+    }
+
+    #[test]
+    fn test_unicode_lookalikes() {
+        assert_eq!(
+            Tokenizer::tokenize("MOV\u{00A0}R0, #1", 4).unwrap_err(),
+            TokenizerError::UnicodeLookalike(Box::new(UnicodeLookalike {
+                char: '\u{00A0}',
+                line: 1,
+                col: 4
+            }))
+        );
+        assert_eq!(
+            Tokenizer::tokenize("NOP\nB \u{2018}loop\u{2019}", 4).unwrap_err(),
+            TokenizerError::UnicodeLookalike(Box::new(UnicodeLookalike {
+                char: '\u{2018}',
+                line: 2,
+                col: 3
+            }))
+        );
+        // Pasted text is fine inside comments
+        assert!(Tokenizer::tokenize("NOP // don\u{2019}t\u{00A0}care", 4).is_ok());
+    }
+
     #[rustfmt::skip]
     #[test]
     fn test_line_col_calculations() {
@@ -10,7 +10,15 @@ pub enum TokenKind {
     Newline,
     Semicolon,
     Comma,
-    LabelDefinition
+    LabelDefinition,
+    /// '.data', after which only labels and reserved space are allowed
+    DataSection,
+    /// '.space n', reserving n bytes of the data section
+    Space(u8),
+    /// 'DATA', followed by the values of bytes to put in the data section
+    Data,
+    /// '.equ', followed by the name and value of a constant
+    Equ,
 }
 
 impl fmt::Display for TokenKind {
@@ -21,7 +29,11 @@ impl fmt::Display for TokenKind {
             TokenKind::Newline => write!(f, "newline"),
             TokenKind::Semicolon => write!(f, "semicolon"),
             TokenKind::Comma => write!(f, "comma"),
-            TokenKind::LabelDefinition => write!(f, "label definition")
+            TokenKind::LabelDefinition => write!(f, "label definition"),
+            TokenKind::DataSection => write!(f, "'.data'"),
+            TokenKind::Space(_) => write!(f, "'.space'"),
+            TokenKind::Data => write!(f, "'DATA'"),
+            TokenKind::Equ => write!(f, "'.equ'"),
         }
     }
 }
@@ -31,22 +43,43 @@ pub struct Token {
     pub kind: TokenKind,
     pub lexeme: String,
     pub line: usize,
+    /// Column for people to read, where tabs advance to the tab width
     pub col: usize,
+    /// Column counting every character as one, including tabs
+    pub raw_col: usize,
+    /// Byte offset of the token from the start of the source
+    pub offset: usize,
+}
+
+/// Comment in the source code, kept so that tools can show it alongside the code
+#[derive(Debug, Clone, PartialEq)]
+pub struct Comment {
+    /// Text of the comment without its delimeters or surrounding whitespace
+    pub text: String,
+    pub line: usize,
+    pub col: usize,
+    /// Byte offset of the comment from the start of the source
+    pub offset: usize,
 }
 
 #[derive(Debug, Clone, PartialEq)]
 pub struct TokenPosition {
+    /// Byte offset from the start of the source
     pub idx: usize,
     pub line: usize,
+    /// Column for people to read, where tabs advance to the tab width
     pub col: usize,
+    /// Column counting every character as one, including tabs
+    pub raw_col: usize,
 }
 
-impl TokenPosition {
-    pub fn default() -> Self {
+impl Default for TokenPosition {
+    fn default() -> Self {
         Self {
             idx: 0,
             line: 1,
             col: 1,
+            raw_col: 1,
         }
     }
 }
@@ -58,8 +91,18 @@ impl Token {
             lexeme: lexeme.to_string(),
             line,
             col,
+            raw_col: col,
+            offset: 0,
         }
     }
+
+    /// Set the raw column and byte offset of a token, see [`Token::raw_col`] and
+    /// [`Token::offset`]
+    pub fn with_raw_position(mut self, raw_col: usize, offset: usize) -> Self {
+        self.raw_col = raw_col;
+        self.offset = offset;
+        self
+    }
     pub fn get_token_debug_repr(&self) -> String {
         match &self.kind {
             TokenKind::Newline => String::from("'newline'"),
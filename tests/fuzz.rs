@@ -0,0 +1,89 @@
+use aqa_asm::{interpreter::RuntimeError, try_run, Error};
+
+/// Small deterministic xorshift generator so the fuzz cases are reproducible
+struct Rng(u64);
+
+impl Rng {
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+
+    fn below(&mut self, n: usize) -> usize {
+        (self.next() % n as u64) as usize
+    }
+}
+
+// try_run stops programs that loop forever, so labels and branches can be generated freely
+const FRAGMENTS: &[&str] = &[
+    "NOP", "LDR", "STR", "ADD", "SUB", "MOV", "CMP", "B", "BEQ", "BNE", "BGT", "BLT", "AND",
+    "ORR", "EOR", "MVN", "LSL", "LSR", "PRINT", "INPUT", "HALT", "CPY", "CALL", "RET", "PUSH",
+    "POP", "DIV", "MOD", "ROL", "ROR", "BCS", "BCC", "INC", "DEC", "LDA", "PRINTARR", "R0",
+    "R5", "R12", "R13", "R255", "R", "#", "#0", "#1", "#255", "#256", "#-1", "#-128", "#-129",
+    "#-", "0", "100", "255", "999", "label", "label:", ".data", ".space", ".space 4",
+    ".space 300", ".equ", ".equ N #3", "N", "DATA", ".if X", ".endif", ",", ",", ";", "\n",
+    "\n", " ", "\t", "//", "/*", "*/", "/", "@", "é", "\r",
+];
+
+fn random_program(rng: &mut Rng) -> String {
+    let length = rng.below(40);
+    (0..length)
+        .map(|_| {
+            let fragment = FRAGMENTS[rng.below(FRAGMENTS.len())];
+            if rng.below(2) == 0 {
+                format!("{fragment} ")
+            } else {
+                fragment.to_string()
+            }
+        })
+        .collect()
+}
+
+#[test]
+fn test_try_run_random_programs_never_panic() {
+    let mut rng = Rng(0x2545_f491_4f6c_dd1d);
+    for _ in 0..20_000 {
+        let program = random_program(&mut rng);
+        let input: Vec<u8> = (0..rng.below(16)).map(|_| rng.next() as u8).collect();
+        let _ = try_run(&program, &input);
+    }
+}
+
+#[test]
+fn test_try_run_random_characters_never_panic() {
+    let mut rng = Rng(0x9e37_79b9_7f4a_7c15);
+    for _ in 0..20_000 {
+        let program: String = (0..rng.below(64))
+            .filter_map(|_| char::from_u32(rng.below(0x3000) as u32))
+            .collect();
+        let _ = try_run(&program, b"");
+    }
+}
+
+#[test]
+fn test_try_run_input_exhausted() {
+    let result = try_run("INPUT R0\nINPUT R1\nHALT\n", b"7\n");
+    assert!(matches!(
+        result,
+        Err(Error::RuntimeError(RuntimeError::WithSourceLine(err)))
+            if matches!(err.error, RuntimeError::EndOfInput(_)) && err.line == 2
+    ));
+}
+
+#[test]
+fn test_try_run_stops_infinite_loops() {
+    assert!(matches!(
+        try_run("loop: B loop\n", b""),
+        Err(Error::RuntimeError(RuntimeError::WithSourceLine(err)))
+            if matches!(err.error, RuntimeError::CycleLimitExceeded(_))
+    ));
+}
+
+#[test]
+fn test_try_run_output() {
+    let output = try_run("INPUT R0\nADD R1, R0, #1\nPRINT R1\nHALT\n", b"41\n").unwrap();
+    assert_eq!(output.stdout, b"42\n");
+    assert_eq!(output.registers[1], 42);
+}
@@ -0,0 +1,70 @@
+use aqa_asm::interpreter::{
+    instruction::runtime_opcode::RuntimeOpcode, Interpreter, StreamIo, REGISTER_COUNT,
+};
+use std::io;
+
+/// Run a single conditional branch to offset 4 with the given flags, returning whether
+/// it was taken
+fn branch_taken(opcode: RuntimeOpcode, comparison_result: u8, underflow: bool) -> bool {
+    let program = [
+        opcode as u8,
+        4,
+        RuntimeOpcode::HALT as u8,
+        RuntimeOpcode::HALT as u8,
+        RuntimeOpcode::NOP as u8,
+        RuntimeOpcode::HALT as u8,
+    ];
+    let mut memory = [0; 256];
+    memory[..program.len()].copy_from_slice(&program);
+    let mut registers = [0; REGISTER_COUNT as usize];
+    let mut interpreter = Interpreter::new(
+        &mut memory,
+        &mut registers,
+        program.len() as u8,
+        StreamIo::new(io::empty(), io::sink()),
+    )
+    .with_flags(comparison_result, underflow);
+    interpreter.run().unwrap();
+    // Taken branches run NOP and HALT, others only run HALT
+    interpreter.instruction_count() == 3
+}
+
+#[test]
+fn test_branches_with_preset_flags() {
+    assert!(branch_taken(RuntimeOpcode::BEQ, 0, false));
+    assert!(!branch_taken(RuntimeOpcode::BEQ, 1, false));
+    assert!(branch_taken(RuntimeOpcode::BNE, 1, false));
+    assert!(!branch_taken(RuntimeOpcode::BNE, 0, false));
+    assert!(branch_taken(RuntimeOpcode::BGT, 1, false));
+    assert!(!branch_taken(RuntimeOpcode::BGT, 1, true));
+    assert!(branch_taken(RuntimeOpcode::BLT, 255, true));
+    assert!(!branch_taken(RuntimeOpcode::BLT, 0, false));
+}
+
+#[test]
+fn test_state_after_run() {
+    // CMP R0, R1 with R0 < R1, then HALT
+    let program = [
+        RuntimeOpcode::CMP_REGISTER as u8,
+        0,
+        1,
+        RuntimeOpcode::HALT as u8,
+    ];
+    let mut memory = [0; 256];
+    memory[..program.len()].copy_from_slice(&program);
+    let mut registers = [0; REGISTER_COUNT as usize];
+    registers[0] = 2;
+    registers[1] = 3;
+    let mut interpreter = Interpreter::new(
+        &mut memory,
+        &mut registers,
+        program.len() as u8,
+        StreamIo::new(io::empty(), io::sink()),
+    );
+    interpreter.run().unwrap();
+    assert_eq!(interpreter.comparison_result(), 255);
+    assert!(interpreter.underflow());
+    assert_eq!(interpreter.program_counter(), 4);
+    assert_eq!(interpreter.registers()[..2], [2, 3]);
+    assert_eq!(interpreter.memory()[..program.len()], program);
+}
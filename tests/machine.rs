@@ -0,0 +1,360 @@
+use aqa_asm::{
+    assemble_layout, disassembler,
+    interpreter::{instruction::runtime_opcode::RuntimeOpcode, MachineIo, RuntimeError},
+    profile::CostModel,
+    CompiledProgram, Error, Machine, MachineOptions, MemoryLayout,
+};
+use std::{
+    cell::RefCell,
+    collections::VecDeque,
+    fs,
+    io::{self, Cursor, Write},
+    rc::Rc,
+};
+
+/// Feeds values from a queue and records every printed value
+#[derive(Default)]
+struct QueueIo {
+    input: VecDeque<u8>,
+    printed: Vec<u8>,
+}
+
+impl MachineIo for QueueIo {
+    fn read_u8(&mut self) -> io::Result<Option<u8>> {
+        Ok(self.input.pop_front())
+    }
+
+    fn write_value(&mut self, value: u8) -> io::Result<()> {
+        self.printed.push(value);
+        Ok(())
+    }
+}
+
+/// Buffer that can still be read after a clone of it has been given to a [`Machine`]
+#[derive(Clone, Default)]
+struct SharedBuffer(Rc<RefCell<Vec<u8>>>);
+
+impl Write for SharedBuffer {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.borrow_mut().write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[test]
+fn test_machine_runs_example() {
+    let source = fs::read_to_string("examples/addition.aqasm").unwrap();
+    let mut machine = Machine::from_source(&source, MachineOptions::default()).unwrap();
+    let mut output = Vec::new();
+
+    let result = machine.run(Cursor::new("105\n25"), &mut output).unwrap();
+
+    assert_eq!(machine.registers()[2], 130);
+    assert_eq!(result.registers, *machine.registers());
+    assert_eq!(result.instruction_count, 5);
+    assert_eq!(String::from_utf8(output).unwrap(), "130\n");
+    assert_eq!(machine.program_bytes(), 11);
+}
+
+#[test]
+fn test_machine_trace_example() {
+    let source = fs::read_to_string("examples/addition.aqasm").unwrap();
+    let trace = SharedBuffer::default();
+    let mut machine = Machine::from_source(&source, MachineOptions::default())
+        .unwrap()
+        .with_trace(trace.clone());
+    let mut output = Cursor::new(Vec::new());
+
+    machine.run(Cursor::new("105\n25"), &mut output).unwrap();
+
+    let trace = String::from_utf8(trace.0.take()).unwrap();
+    let trace: Vec<&str> = trace.lines().collect();
+    let instructions: Vec<&str> = trace
+        .iter()
+        .map(|line| line.split_whitespace().nth(1).unwrap())
+        .collect();
+    assert_eq!(instructions, ["INPUT", "INPUT", "ADD", "PRINT", "HALT"]);
+    // Registers are shown before each instruction runs
+    let registers = "[R0=105 R1=25 R2=0 R3=0 R4=0 R5=0 R6=0 R7=0 R8=0 R9=0 R10=0 R11=0 R12=0]";
+    assert!(trace[2].ends_with(registers));
+    assert!(trace[3].contains("R2=130"));
+    assert_eq!(output.into_inner(), b"130\n");
+}
+
+#[test]
+fn test_machine_dump_state() {
+    let source = fs::read_to_string("examples/division.aqasm").unwrap();
+    let mut machine = Machine::from_source(&source, MachineOptions::default()).unwrap();
+    let result = machine.run(Cursor::new("35\n6"), io::sink()).unwrap();
+
+    let dump = result.dump_state();
+    let lines: Vec<&str> = dump.lines().collect();
+    assert_eq!(lines[..2], ["R0 = 5", "R1 = 5"]);
+    assert_eq!(lines[12], "R12 = 0");
+    // The data region follows, 16 bytes to a line
+    let data_bytes = 256 - machine.program_bytes() as usize;
+    assert_eq!(lines.len(), 13 + data_bytes.div_ceil(16));
+    assert!(lines[13].starts_with(&format!("{:02X}: 00 00", machine.program_bytes())));
+}
+
+#[test]
+fn test_disassemble_round_trip() {
+    let source = fs::read_to_string("examples/multiplication.aqasm").unwrap();
+    let machine = Machine::from_source(&source, MachineOptions::default()).unwrap();
+    let disassembled =
+        disassembler::disassemble(machine.memory(), machine.program_bytes()).unwrap();
+    assert!(disassembled.starts_with("INPUT R0\nINPUT R1\nMOV R3, #0\nCMP R1, #0\nBEQ "));
+
+    let reassembled = Machine::from_source(&disassembled, MachineOptions::default()).unwrap();
+    assert_eq!(reassembled.program(), machine.program());
+}
+
+#[test]
+fn test_machine_data_section() {
+    let source = "INPUT R0\nSTR R0, value\nLDR R1, value\nADD R1, R1, R0\nSTR R1, double\nPRINT R1\nHALT\n.data\nvalue:\n.space 1\ndouble:\n";
+    let mut machine = Machine::from_source(source, MachineOptions::default()).unwrap();
+    let mut output = Vec::new();
+
+    let result = machine.run(Cursor::new("21"), &mut output).unwrap();
+
+    assert_eq!(String::from_utf8(output).unwrap(), "42\n");
+    assert_eq!(result.data_memory[..2], [21, 42]);
+}
+
+#[test]
+fn test_machine_data_directive() {
+    // The table starts after the 2 reserved bytes, so its second element is at 3
+    let source = "LDR R0, table\nLDR R1, 3\nHALT\n.data\n.space 2\ntable: DATA #1, #2, #3\n";
+    let mut machine = Machine::from_source(source, MachineOptions::default()).unwrap();
+
+    let result = machine.run(Cursor::new(""), io::sink()).unwrap();
+
+    assert_eq!(result.registers[..2], [1, 2]);
+    assert_eq!(result.data_memory[..5], [0, 0, 1, 2, 3]);
+}
+
+#[test]
+fn test_machine_data_after_halt() {
+    // Code after HALT is still part of the program, while everything after .data is data
+    // that is never executed and that memory references count from
+    let source = "LDR R0, value\nPRINT R0\nHALT\nend: B end\n.data\nvalue: DATA #42\n";
+    let mut machine = Machine::from_source(source, MachineOptions::default()).unwrap();
+    assert_eq!(machine.program_bytes(), 8);
+    assert_eq!(machine.memory()[8], 42);
+
+    let mut output = Vec::new();
+    let result = machine.run(Cursor::new(""), &mut output).unwrap();
+
+    assert_eq!(output, b"42\n");
+    assert_eq!(result.data_memory[0], 42);
+    assert_eq!(result.instruction_count, 3);
+}
+
+#[test]
+fn test_machine_reports_assemble_errors() {
+    let result = Machine::from_source("MOV R0, missing_label\nHALT", MachineOptions::default());
+    assert!(matches!(result, Err(Error::ParserError(_))));
+}
+
+#[test]
+fn test_compiled_program_try_from() {
+    let program: CompiledProgram = "MOV R0, #5\nHALT\n".try_into().unwrap();
+    assert_eq!(program.len(), 4);
+    assert_eq!(program.bytes(), &program.memory()[..4]);
+    let machine = Machine::from_source("MOV R0, #5\nHALT\n", MachineOptions::default()).unwrap();
+    assert_eq!(program.bytes(), machine.program());
+
+    let result = CompiledProgram::try_from("MOV R0, missing_label\nHALT");
+    assert!(matches!(result, Err(Error::ParserError(_))));
+}
+
+#[test]
+fn test_compiled_program_label_address() {
+    let source = "start:\nMOV R0, #1\nmiddle: ADD R0, R0, #1\npause: NOP #3\nHALT\nend:\n";
+    let program = CompiledProgram::try_from(source).unwrap();
+    assert_eq!(program.label_address("start"), Some(0));
+    assert_eq!(program.label_address("middle"), Some(3));
+    assert_eq!(program.label_address("pause"), Some(7));
+    assert_eq!(program.label_address("end"), Some(11));
+    assert_eq!(program.len(), 11);
+    assert_eq!(program.label_address("missing"), None);
+}
+
+#[test]
+fn test_assemble_layout() {
+    let (program, layout) = assemble_layout("MOV R0, #5\nSTR R0, 0\nHALT\n", 4).unwrap();
+    assert_eq!(program.len(), 7);
+    assert_eq!(
+        layout,
+        MemoryLayout {
+            code_end: 7,
+            data_start: 7,
+            data_capacity: 249,
+        }
+    );
+    assert_eq!(assemble_layout("", 4).unwrap().1.data_capacity, 256);
+    assert!(matches!(
+        assemble_layout("MOV R0\n", 4),
+        Err(Error::ParserError(_))
+    ));
+}
+
+#[test]
+fn test_compiled_program_round_trip() {
+    for entry in fs::read_dir("examples").unwrap() {
+        let path = entry.unwrap().path();
+        let source = fs::read_to_string(&path).unwrap();
+        let program = CompiledProgram::try_from(source.as_str()).unwrap();
+        let reassembled = CompiledProgram::try_from(program.to_source().unwrap().as_str())
+            .unwrap_or_else(|err| panic!("{} did not reassemble: {err}", path.display()));
+        assert_eq!(reassembled.memory(), program.memory(), "{}", path.display());
+    }
+}
+
+#[test]
+fn test_compiled_program_round_trip_data() {
+    let source = "LDR R0, table\nPRINT R0\nLDR R1, flag\nHALT\n.data\ntable: DATA #7, #8\n.space 4\nflag: DATA #1\n";
+    let program = CompiledProgram::try_from(source).unwrap();
+    let disassembled = program.to_source().unwrap();
+    let reassembled = CompiledProgram::try_from(disassembled.as_str()).unwrap();
+    assert_eq!(reassembled.memory(), program.memory());
+
+    let mut machine = Machine::from_source(&disassembled, MachineOptions::default()).unwrap();
+    let mut output = Vec::new();
+    let result = machine.run(Cursor::new(""), &mut output).unwrap();
+    assert_eq!(String::from_utf8(output).unwrap(), "7\n");
+    assert_eq!(result.registers[1], 1);
+}
+
+#[test]
+fn test_machine_stores_cannot_reach_program() {
+    // Memory references are offsets from the end of the program, so the last data byte is
+    // as far as a store can go and anything past it is out of bounds rather than wrapping
+    // round to the program
+    let source = "MOV R0, #9\nSTR R0, 245\nSTR R0, 246\nHALT\n";
+    let mut machine = Machine::from_source(source, MachineOptions::default()).unwrap();
+    assert_eq!(machine.program_bytes(), 10);
+    let program = machine.program().to_vec();
+
+    let result = machine.run(Cursor::new(""), io::sink());
+
+    assert!(matches!(
+        result,
+        Err(Error::RuntimeError(RuntimeError::WithSourceLine(err)))
+            if matches!(&err.error, RuntimeError::OutOfBoundsWrite(write) if write.address == 256)
+    ));
+    assert_eq!(machine.memory()[255], 9);
+    assert_eq!(machine.program(), program);
+}
+
+#[test]
+fn test_machine_reports_runtime_errors() {
+    let mut machine = Machine::from_source("NOP\n", MachineOptions::default()).unwrap();
+    let result = machine.run(Cursor::new(""), Vec::new());
+    assert!(matches!(result, Err(Error::RuntimeError(_))));
+}
+
+#[test]
+fn test_machine_stack_overflow_and_underflow() {
+    // Every CALL pushes a return address, so recursing without a base case fills the free
+    // memory until the next push would reach the program
+    let source = "NOP\nrecurse: CALL recurse\n";
+    let mut machine = Machine::from_source(source, MachineOptions::default()).unwrap();
+    let program = machine.program().to_vec();
+    let result = machine.run(Cursor::new(""), io::sink());
+    assert!(matches!(
+        result,
+        Err(Error::RuntimeError(RuntimeError::WithSourceLine(err)))
+            if matches!(err.error, RuntimeError::StackOverflow(_)) && err.line == 2
+    ));
+    assert_eq!(machine.program(), program);
+
+    let mut machine = Machine::from_source("POP R0\nHALT\n", MachineOptions::default()).unwrap();
+    let result = machine.run(Cursor::new(""), io::sink());
+    assert!(matches!(
+        result,
+        Err(Error::RuntimeError(RuntimeError::WithSourceLine(err)))
+            if matches!(err.error, RuntimeError::StackUnderflow(_)) && err.line == 1
+    ));
+}
+
+#[test]
+fn test_machine_label_before_instruction_on_same_line() {
+    let source = "MOV R1, #3\nloop: ADD R0, R0, #2; SUB R1, R1, #1; CMP R1, #0; BNE loop\nHALT\n";
+    let mut machine = Machine::from_source(source, MachineOptions::default()).unwrap();
+    machine.run(Cursor::new(""), Vec::new()).unwrap();
+    assert_eq!(machine.registers()[0], 6);
+}
+
+#[test]
+fn test_machine_warnings() {
+    for entry in fs::read_dir("examples").unwrap() {
+        let source = fs::read_to_string(entry.unwrap().path()).unwrap();
+        let machine = Machine::from_source(&source, MachineOptions::default()).unwrap();
+        assert_eq!(machine.warnings(), []);
+    }
+
+    let mut machine =
+        Machine::from_source("PRINT R4\nSTR R4, 0\nHALT\n", MachineOptions::default()).unwrap();
+    let warnings = machine.warnings();
+    assert_eq!(warnings.len(), 1);
+    machine.run(Cursor::new(""), Vec::new()).unwrap();
+    assert_eq!(machine.warnings(), warnings);
+}
+
+#[test]
+fn test_machine_runs_with_custom_io() {
+    let mut machine = Machine::from_source(
+        "INPUT R0\nINPUT R1\nADD R2, R0, R1\nPRINT R2\nPRINT R0\nHALT\n",
+        MachineOptions::default(),
+    )
+    .unwrap();
+    let mut io = QueueIo {
+        input: VecDeque::from([200, 100]),
+        ..Default::default()
+    };
+    machine.run_with_io(&mut io).unwrap();
+    assert_eq!(io.printed, [44, 200]);
+}
+
+#[test]
+fn test_machine_profile() {
+    let source = "MOV R0, #3\nloop: SUB R0, R0, #1\nCMP R0, #0\nBNE loop\nHALT\n";
+    let cost_model = CostModel::parse("SUB_LITERAL 4\nHALT 0\n").unwrap();
+    let mut machine = Machine::from_source(source, MachineOptions::default())
+        .unwrap()
+        .with_cost_model(cost_model);
+    let result = machine.run(Cursor::new(""), Vec::new()).unwrap();
+    let profile = result.profile.unwrap();
+
+    assert_eq!(profile.instructions, 11);
+    assert_eq!(profile.instructions, result.instruction_count);
+    // MOV 1 + 3 * (SUB 4 + CMP 1 + BNE 2) + HALT 0
+    assert_eq!(profile.cycles, 22);
+    assert_eq!(profile.by_opcode[&RuntimeOpcode::SUB_LITERAL], 3);
+    assert_eq!(profile.by_opcode[&RuntimeOpcode::HALT], 1);
+    assert_eq!(
+        profile.to_json(),
+        r#"{"instructions":11,"cycles":22,"by_opcode":{"SUB_LITERAL":{"count":3,"cycles":12},"MOV_LITERAL":{"count":1,"cycles":1},"CMP_LITERAL":{"count":3,"cycles":3},"BNE":{"count":3,"cycles":6},"HALT":{"count":1,"cycles":0}}}"#
+    );
+
+    let mut machine = Machine::from_source(source, MachineOptions::default()).unwrap();
+    assert_eq!(machine.run(Cursor::new(""), Vec::new()).unwrap().profile, None);
+}
+
+#[test]
+fn test_machine_reset() {
+    let mut machine =
+        Machine::from_source("INPUT R0\nSTR R0, 0\nHALT\n", MachineOptions::default()).unwrap();
+    let first = machine.run(Cursor::new("7"), Vec::new()).unwrap();
+    assert_eq!(first.data_memory[0], 7);
+
+    machine.reset();
+    assert_eq!(machine.registers()[0], 0);
+    assert_eq!(machine.memory()[machine.program_bytes() as usize], 0);
+    let second = machine.run(Cursor::new("7"), Vec::new()).unwrap();
+    assert_eq!(first, second);
+}